@@ -0,0 +1,329 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+
+use crate::env_vars::cargo::build_rs::OUT_DIR;
+use crate::env_vars::vcpkg_rs::VCPKG_DEFAULT_BINARY_CACHE;
+use crate::Error;
+
+/// Directory in OUT_DIR that ports from the binary cache are restored into. It
+/// is laid out as a vcpkg root so that the ports can be found as usual.
+pub(crate) const RESTORED_ROOT: &'static str = "vcpkg-binary-cache";
+
+/// The binary cache vcpkg uses by default.
+fn cache_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os(VCPKG_DEFAULT_BINARY_CACHE) {
+        return Some(PathBuf::from(dir));
+    }
+    if cfg!(windows) {
+        env::var_os("LOCALAPPDATA").map(|dir| Path::new(&dir).join("vcpkg").join("archives"))
+    } else {
+        env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+            .map(|dir| dir.join("vcpkg").join("archives"))
+    }
+}
+
+enum Action<'a> {
+    /// list the files in the archive, one per line
+    List,
+    /// write the contents of a file in the archive to stdout
+    Print(&'a str),
+    /// extract the archive into a directory
+    Extract(&'a Path),
+}
+
+/// Run `unzip`, or `tar` if that is not available, on a zip archive.
+fn unzip(archive: &Path, action: Action) -> Result<Vec<u8>, Error> {
+    let mut unzip = Command::new("unzip");
+    // the bsdtar that ships with windows 10 also reads zip files
+    let mut tar = Command::new("tar");
+    match action {
+        Action::List => {
+            unzip.arg("-Z1").arg(archive);
+            tar.arg("-tf").arg(archive);
+        }
+        Action::Print(name) => {
+            unzip.arg("-p").arg(archive).arg(name);
+            tar.arg("-xOf").arg(archive).arg(name);
+        }
+        Action::Extract(dir) => {
+            unzip.arg("-q").arg("-o").arg(archive).arg("-d").arg(dir);
+            tar.arg("-xf").arg(archive).arg("-C").arg(dir);
+        }
+    }
+
+    let describe = |e: &dyn std::fmt::Display| {
+        Error::CommandFailed(format!("could not unpack {}: {}", archive.display(), e))
+    };
+    let output = match unzip.output() {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => tar.output(),
+        output => output,
+    }
+    .map_err(|e| describe(&e))?;
+    if !output.status.success() {
+        return Err(describe(&String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(output.stdout)
+}
+
+/// The value of a field in the first paragraph of a CONTROL file.
+fn control_field<'a>(control: &'a str, name: &str) -> Option<&'a str> {
+    control
+        .lines()
+        .take_while(|line| !line.trim().is_empty())
+        .filter_map(|line| {
+            let pos = line.find(':')?;
+            if line[..pos].trim() == name {
+                Some(line[pos + 1..].trim())
+            } else {
+                None
+            }
+        })
+        .next()
+}
+
+/// The dependencies of a port from its CONTROL file, leaving out the host
+/// tools that are qualified with another triplet, e.g. `vcpkg-cmake:x64-linux`.
+fn control_deps(control: &str) -> Vec<String> {
+    control_field(control, "Depends")
+        .unwrap_or("")
+        .split(',')
+        .map(|dep| dep.split(|c| c == '[' || c == '(').next().unwrap().trim())
+        .filter(|dep| !dep.is_empty() && !dep.contains(':'))
+        .map(|dep| dep.to_owned())
+        .collect()
+}
+
+/// Directory in OUT_DIR that the CONTROL files read from the archives are kept
+/// in, so that an archive is only unpacked to find out what it contains the first
+/// time it is seen.
+const INDEX_DIR: &'static str = "vcpkg-binary-cache-index";
+
+/// Name of the file in the restored root listing the archives it was restored
+/// from, so that a later build with the same archives does not unpack them again.
+const RESTORED_STAMP: &'static str = "restored-archives";
+
+/// The CONTROL file of `archive`, read from the index in `index_dir` if it was
+/// read before from an archive with the same name and modification time. vcpkg
+/// names the archives after a hash of the build inputs, so the name identifies
+/// the contents.
+fn read_control(archive: &Path, modified: SystemTime, index_dir: &Path) -> Option<String> {
+    let secs = modified
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let cached_path = index_dir
+        .join(archive.file_stem()?)
+        .with_extension("control");
+    if let Ok(cached) = fs::read_to_string(&cached_path) {
+        let mut parts = cached.splitn(2, '\n');
+        if parts.next() == Some(&secs.to_string()[..]) {
+            return Some(parts.next().unwrap_or("").to_owned()).filter(|c| !c.is_empty());
+        }
+    }
+
+    // archives that can't be read are remembered with an empty CONTROL file
+    let control = unzip(archive, Action::Print("CONTROL"))
+        .map(|control| String::from_utf8_lossy(&control).replace("\r\n", "\n"))
+        .unwrap_or_default();
+    let _ = fs::create_dir_all(index_dir)
+        .and_then(|_| fs::write(&cached_path, format!("{}\n{}", secs, control)));
+    Some(control).filter(|c| !c.is_empty())
+}
+
+/// The archives in the binary cache by package and triplet, with the contents
+/// of their CONTROL files, which are cached in `index_dir`.
+fn index_archives(
+    cache_dir: &Path,
+    index_dir: &Path,
+) -> BTreeMap<(String, String), (PathBuf, String)> {
+    let mut archives = Vec::new();
+    let mut dirs = vec![cache_dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|e| e.ok())
+        {
+            let path = entry.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().map_or(false, |ext| ext == "zip") {
+                archives.push(path);
+            }
+        }
+    }
+
+    let mut index: BTreeMap<(String, String), (PathBuf, String, SystemTime)> = BTreeMap::new();
+    for archive in archives {
+        let modified = fs::metadata(&archive)
+            .and_then(|m| m.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        let control = match read_control(&archive, modified, index_dir) {
+            Some(control) => control,
+            None => continue,
+        };
+        let key = match (
+            control_field(&control, "Package"),
+            control_field(&control, "Architecture"),
+        ) {
+            (Some(package), Some(arch)) => (package.to_owned(), arch.to_owned()),
+            _ => continue,
+        };
+        // prefer the most recent build of a port
+        if index.get(&key).map_or(true, |entry| modified > entry.2) {
+            index.insert(key, (archive, control, modified));
+        }
+    }
+    index
+        .into_iter()
+        .map(|(key, (archive, control, _))| (key, (archive, control)))
+        .collect()
+}
+
+/// Restore `ports` and the ports they depend on from the binary cache into a
+/// vcpkg root in OUT_DIR, returning the root or `None` if the cache has no
+/// archive for one of the ports. It is an error for the cache to have a port
+/// but not one of its dependencies. Nothing is unpacked if the root was already
+/// restored from the same archives.
+pub(crate) fn restore_ports(ports: &[&str], triplet: &str) -> Result<Option<PathBuf>, Error> {
+    let cache_dir = match cache_dir() {
+        Some(cache_dir) => cache_dir,
+        None => return Ok(None),
+    };
+    let out_dir =
+        env::var_os(OUT_DIR).ok_or_else(|| Error::RequiredEnvMissing(OUT_DIR.to_owned()))?;
+    let index = index_archives(&cache_dir, &Path::new(&out_dir).join(INDEX_DIR));
+    let archive = |port: &str| index.get(&(port.to_owned(), triplet.to_owned()));
+    if ports.iter().any(|port| archive(port).is_none()) {
+        return Ok(None);
+    }
+
+    // the ports to restore with the archives they are in
+    let mut closure = BTreeMap::new();
+    let mut to_restore: Vec<(String, Option<String>)> =
+        ports.iter().map(|port| (port.to_string(), None)).collect();
+    while let Some((name, dependent)) = to_restore.pop() {
+        if closure.contains_key(&name) {
+            continue;
+        }
+        let entry = match archive(&name) {
+            Some(entry) => entry,
+            None => {
+                return Err(Error::VcpkgInstallation(format!(
+                    "vcpkg port {} depends on {}, which is not in the binary cache for {}",
+                    dependent.unwrap_or_default(),
+                    name,
+                    triplet
+                )))
+            }
+        };
+        to_restore.extend(
+            control_deps(&entry.1)
+                .into_iter()
+                .map(|dep| (dep, Some(name.clone()))),
+        );
+        closure.insert(name, entry);
+    }
+
+    let root = Path::new(&out_dir).join(RESTORED_ROOT);
+    let stamp: String = closure
+        .iter()
+        .map(|(name, &&(ref archive, _))| format!("{} {}\n", name, archive.display()))
+        .collect();
+    let stamp_path = root.join(RESTORED_STAMP);
+    if fs::read_to_string(&stamp_path).ok().as_ref() == Some(&stamp) {
+        return Ok(Some(root));
+    }
+
+    let status_path = root.join("installed").join("vcpkg");
+    let installed_path = root.join("installed").join(triplet);
+    let describe = |e: io::Error| {
+        Error::VcpkgInstallation(format!(
+            "could not restore {} from the binary cache: {}",
+            ports.join(", "),
+            e
+        ))
+    };
+    fs::create_dir_all(status_path.join("updates")).map_err(&describe)?;
+    fs::create_dir_all(status_path.join("info")).map_err(&describe)?;
+    fs::create_dir_all(&installed_path).map_err(&describe)?;
+    fs::write(root.join(".vcpkg-root"), "").map_err(&describe)?;
+
+    let mut status = String::new();
+    for (name, &&(ref archive, ref control)) in &closure {
+        unzip(archive, Action::Extract(&installed_path))?;
+        let files = unzip(archive, Action::List)?;
+        let manifest: String = String::from_utf8_lossy(&files)
+            .lines()
+            .map(|file| file.trim())
+            .filter(|&file| !file.is_empty() && file != "CONTROL" && file != "BUILD_INFO")
+            .map(|file| format!("{}/{}\n", triplet, file))
+            .collect();
+        let version = control_field(control, "Version").unwrap_or("");
+        fs::write(
+            status_path
+                .join("info")
+                .join(format!("{}_{}_{}.list", name, version, triplet)),
+            manifest,
+        )
+        .map_err(&describe)?;
+
+        // each paragraph is a port or one of its features
+        for paragraph in control.split("\n\n").filter(|p| !p.trim().is_empty()) {
+            status.push_str(paragraph.trim());
+            status.push_str("\nStatus: install ok installed\n\n");
+        }
+    }
+    fs::write(status_path.join("updates").join("0000000001"), status).map_err(&describe)?;
+    fs::write(&stamp_path, stamp).map_err(&describe)?;
+
+    Ok(Some(root))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTROL: &'static str = "Package: curl\n\
+                                   Version: 8.0.0\n\
+                                   Depends: vcpkg-cmake:x64-linux, zlib, openssl (!windows), \
+                                   libssh2[core]\n\
+                                   Architecture: x64-linux\n\
+                                   \n\
+                                   Package: curl\n\
+                                   Feature: ssl\n\
+                                   Depends: openssl\n";
+
+    #[test]
+    fn control_fields() {
+        for &(name, value) in &[
+            ("Package", Some("curl")),
+            ("Version", Some("8.0.0")),
+            ("Architecture", Some("x64-linux")),
+            ("Feature", None),
+            ("Status", None),
+        ] {
+            assert_eq!(control_field(CONTROL, name), value, "{}", name);
+        }
+    }
+
+    #[test]
+    fn dependencies() {
+        for &(control, deps) in &[
+            (CONTROL, &["zlib", "openssl", "libssh2"][..]),
+            ("Package: zlib\nVersion: 1.2.13\n", &[]),
+            ("Package: foo\nDepends: \n", &[]),
+            ("Package: foo\nDepends: bar,baz\n", &["bar", "baz"]),
+            ("Package: foo\nDepends: vcpkg-cmake:x64-linux\n", &[]),
+        ] {
+            assert_eq!(control_deps(control), deps, "{}", control);
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! Set up a vcpkg tree in the cargo target directory when there is no vcpkg
+//! installation, so that building a crate needs no manual steps.
+//!
+//! This clones vcpkg at a pinned commit into `<target dir>/vcpkg`, like
+//! [cargo-vcpkg](https://crates.io/crates/cargo-vcpkg) does, and builds the
+//! vcpkg tool. It is enabled with the `bootstrap` feature and needs `git`.
+//! The ports are not installed, which `Config::install_missing` can do.
+//!
+//! ```rust,no_run
+//! // build.rs
+//! vcpkg::Config::new()
+//!     .bootstrap("2024.01.12")
+//!     .install_missing(true)
+//!     .find_package("zlib")
+//!     .unwrap();
+//! ```
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+use crate::env_vars::cargo::build_rs::OUT_DIR;
+use crate::Error;
+
+/// The repository vcpkg is cloned from.
+pub const VCPKG_REPOSITORY: &'static str = "https://github.com/microsoft/vcpkg";
+
+/// The cargo target directory, which is `CARGO_TARGET_DIR` if it is set and
+/// otherwise the directory above OUT_DIR that is tagged as a cache by cargo.
+pub(crate) fn target_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("CARGO_TARGET_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+    let mut dir = PathBuf::from(env::var_os(OUT_DIR)?);
+    while dir.pop() {
+        if dir.join("CACHEDIR.TAG").exists() {
+            return Some(dir);
+        }
+    }
+    None
+}
+
+fn run(command: &mut Command) -> Result<(), Error> {
+    match command.status() {
+        Ok(ref status) if status.success() => Ok(()),
+        Ok(status) => Err(Error::CommandFailed(format!(
+            "{:?} exited with {}",
+            command, status
+        ))),
+        Err(e) => Err(Error::CommandFailed(format!(
+            "could not run {:?}: {}",
+            command, e
+        ))),
+    }
+}
+
+fn git(dir: &Path) -> Command {
+    let mut command = Command::new("git");
+    command.arg("-C").arg(dir);
+    command
+}
+
+/// Check out `commit` in the vcpkg tree at `root` and build the vcpkg tool.
+fn checkout(root: &Path, commit: &str) -> Result<(), Error> {
+    if run(git(root).args(&["checkout", "--quiet", commit])).is_err() {
+        run(git(root).args(&["fetch", "--quiet", "origin"]))?;
+        run(git(root).args(&["checkout", "--quiet", commit]))?;
+    }
+    let script = if cfg!(windows) {
+        root.join("bootstrap-vcpkg.bat")
+    } else {
+        root.join("bootstrap-vcpkg.sh")
+    };
+    run(Command::new(script)
+        .arg("-disableMetrics")
+        .current_dir(root))
+}
+
+/// Make sure that there is a vcpkg tree at `commit` in the cargo target
+/// directory, cloning and bootstrapping it if necessary, and return its root.
+pub fn bootstrap(commit: &str) -> Result<PathBuf, Error> {
+    let target_dir = target_dir().ok_or_else(|| {
+        Error::VcpkgNotFound(
+            "could not find the cargo target directory to bootstrap vcpkg in".into(),
+        )
+    })?;
+    let root = target_dir.join("vcpkg");
+
+    if root.join(".vcpkg-root").exists() {
+        let head = Command::new("git")
+            .arg("-C")
+            .arg(&root)
+            .args(&["rev-parse", "HEAD"])
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+            .unwrap_or(String::new());
+        if !head.starts_with(commit) {
+            checkout(&root, commit)?;
+        }
+        return Ok(root);
+    }
+
+    // several build scripts may bootstrap at the same time, so each one works
+    // in a directory of its own and the first to finish wins
+    let tmp_root = target_dir.join(format!("vcpkg.tmp-{}", process::id()));
+    let _ = fs::remove_dir_all(&tmp_root);
+    run(Command::new("git")
+        .args(&["clone", "--quiet", VCPKG_REPOSITORY])
+        .arg(&tmp_root))?;
+    checkout(&tmp_root, commit)?;
+    if fs::rename(&tmp_root, &root).is_err() {
+        let _ = fs::remove_dir_all(&tmp_root);
+        if !root.join(".vcpkg-root").exists() {
+            return Err(Error::VcpkgNotFound(format!(
+                "could not move the bootstrapped vcpkg tree to {}",
+                root.display()
+            )));
+        }
+    }
+    Ok(root)
+}
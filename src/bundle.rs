@@ -0,0 +1,216 @@
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::directive::cargo_path;
+use crate::env_vars::cargo::build_rs::{OUT_DIR, TARGET};
+use crate::env_vars::vcpkg_rs::VCPKGRS_LIB;
+use crate::{Error, Library, MetadataDirective, Triplet, VcpkgTriplet};
+
+/// The archiver used to merge static libraries for a triplet.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Archiver {
+    /// `lib.exe /OUT:<archive> <libs>`
+    Lib,
+    /// `libtool -static -o <archive> <libs>`
+    Libtool,
+    /// `ar -M` driven by an MRI script on stdin
+    Ar,
+}
+
+impl Archiver {
+    fn for_triplet(triplet: &VcpkgTriplet) -> Archiver {
+        if triplet.lib_suffix == "lib" {
+            Archiver::Lib
+        } else {
+            match Triplet::parse(&triplet.name).os() {
+                // Apple's ar has no MRI mode
+                Some("osx") | Some("ios") => Archiver::Libtool,
+                _ => Archiver::Ar,
+            }
+        }
+    }
+}
+
+/// Build an `ar` MRI script that merges `libs` into `archive`.
+pub(crate) fn mri_script(archive: &Path, libs: &[PathBuf]) -> String {
+    let mut script = format!("create {}\n", archive.display());
+    for lib in libs {
+        script.push_str(&format!("addlib {}\n", lib.display()));
+    }
+    script.push_str("save\nend\n");
+    script
+}
+
+/// `ar` ends the paths in an MRI script at the first space and has no way to
+/// quote them, so libraries in directories with spaces in their names are
+/// linked into `staging_dir` under names that `ar` can read, which are returned
+/// relative to it.
+fn stage_libs(staging_dir: &Path, libs: &[PathBuf]) -> Result<Vec<PathBuf>, Error> {
+    let _ = fs::remove_dir_all(staging_dir);
+    fs::create_dir_all(staging_dir).map_err(|e| Error::io(staging_dir, e))?;
+    let mut staged = Vec::new();
+    for (index, lib) in libs.iter().enumerate() {
+        let name = PathBuf::from(format!("{}.a", index));
+        let dest = staging_dir.join(&name);
+        fs::hard_link(lib, &dest)
+            .or_else(|_| fs::copy(lib, &dest).map(|_| ()))
+            .map_err(|e| Error::io(&dest, e))?;
+        staged.push(name);
+    }
+    Ok(staged)
+}
+
+/// `lib.exe` as named by `VCPKGRS_LIB`, found on the `PATH` as it is in a Visual
+/// Studio developer prompt or, with the `cc` feature, found in the Visual Studio
+/// installation the way `cc` finds the compiler.
+fn lib_exe() -> Result<Command, Error> {
+    if let Some(lib_exe) = env::var_os(VCPKGRS_LIB) {
+        return Ok(Command::new(lib_exe));
+    }
+    if let Some(path) = env::var_os("PATH") {
+        if env::split_paths(&path).any(|dir| dir.join("lib.exe").is_file()) {
+            return Ok(Command::new("lib.exe"));
+        }
+    }
+    #[cfg(feature = "cc")]
+    {
+        let target = env::var(TARGET).unwrap_or(String::new());
+        if let Some(command) = ::cc::windows_registry::find(&target, "lib.exe") {
+            return Ok(command);
+        }
+    }
+    Err(Error::CommandFailed(format!(
+        "could not find lib.exe to bundle the static libraries, set {} to its path, \
+         build from a Visual Studio developer prompt or enable the cc feature of vcpkg",
+        VCPKGRS_LIB
+    )))
+}
+
+fn has_whitespace(path: &Path) -> bool {
+    path.to_string_lossy().contains(char::is_whitespace)
+}
+
+/// Merge the static libraries found for `lib` into a single archive named
+/// after `name` in OUT_DIR, and replace the `rustc-link-lib` lines for the
+/// individual libraries with one for the merged archive, where the first of
+/// them was. Other libraries, such as system libraries, are still linked.
+pub(crate) fn bundle_libs(
+    lib: &mut Library,
+    name: &str,
+    triplet: &VcpkgTriplet,
+) -> Result<(), Error> {
+    let out_dir = match env::var_os(OUT_DIR) {
+        Some(out_dir) => PathBuf::from(out_dir),
+        None => return Err(Error::RequiredEnvMissing(OUT_DIR.to_owned())),
+    };
+
+    let file_name = if triplet.strip_lib_prefix {
+        format!("lib{}.{}", name, triplet.lib_suffix)
+    } else {
+        format!("{}.{}", name, triplet.lib_suffix)
+    };
+    let archive = out_dir.join(file_name);
+
+    // the archive that `ar` writes in the staging directory, if there is one
+    let mut staged_archive = None;
+    let (mut command, script) = match Archiver::for_triplet(triplet) {
+        Archiver::Lib => {
+            let mut command = lib_exe()?;
+            command
+                .arg("/NOLOGO")
+                .arg(format!("/OUT:{}", archive.display()))
+                .args(&lib.found_libs);
+            (command, None)
+        }
+        Archiver::Libtool => {
+            let mut command = Command::new("libtool");
+            command
+                .arg("-static")
+                .arg("-o")
+                .arg(&archive)
+                .args(&lib.found_libs);
+            (command, None)
+        }
+        Archiver::Ar => {
+            let ar = env::var_os("AR").unwrap_or("ar".into());
+            let mut command = Command::new(ar);
+            command.arg("-M").stdin(Stdio::piped());
+            if has_whitespace(&archive) || lib.found_libs.iter().any(|lib| has_whitespace(lib)) {
+                let staging_dir = out_dir.join("vcpkg-bundle");
+                let libs = stage_libs(&staging_dir, &lib.found_libs)?;
+                command.current_dir(&staging_dir);
+                staged_archive = Some(staging_dir.join("bundle.a"));
+                (command, Some(mri_script(Path::new("bundle.a"), &libs)))
+            } else {
+                (command, Some(mri_script(&archive, &lib.found_libs)))
+            }
+        }
+    };
+
+    let describe = |e: &dyn std::fmt::Display| {
+        Error::CommandFailed(format!("could not bundle static libraries: {}", e))
+    };
+    let mut child = command.spawn().map_err(|e| describe(&e))?;
+    if let Some(script) = script {
+        let mut stdin = child.stdin.take().unwrap();
+        stdin
+            .write_all(script.as_bytes())
+            .map_err(|e| describe(&e))?;
+    }
+    let status = child.wait().map_err(|e| describe(&e))?;
+    if !status.success() {
+        return Err(describe(&format!("{:?} exited with {}", command, status)));
+    }
+    if let Some(staged_archive) = staged_archive {
+        fs::rename(&staged_archive, &archive).map_err(|e| Error::io(&archive, e))?;
+        let _ = fs::remove_dir_all(staged_archive.parent().unwrap());
+    }
+
+    let bundle_lines = vec![
+        format!("cargo:rustc-link-search=native={}", cargo_path(&out_dir)?),
+        format!("cargo:rustc-link-lib=static={}", name),
+    ];
+    let mut bundle_lines = Some(bundle_lines);
+    let mut cargo_metadata = Vec::new();
+    for line in lib.cargo_metadata.drain(..) {
+        let bundled = match MetadataDirective::parse(&line) {
+            MetadataDirective::LinkLib { name, .. } => lib.found_names.contains(&name),
+            _ => false,
+        };
+        if !bundled {
+            cargo_metadata.push(line);
+        } else if let Some(bundle_lines) = bundle_lines.take() {
+            cargo_metadata.extend(bundle_lines);
+        }
+    }
+    cargo_metadata.extend(bundle_lines.into_iter().flatten());
+    lib.cargo_metadata = cargo_metadata;
+    lib.link_paths.push(out_dir);
+    lib.bundled_lib = Some(archive);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn archivers() {
+        for &(name, archiver) in &[
+            ("x64-windows-static", Archiver::Lib),
+            ("x64-osx", Archiver::Libtool),
+            ("arm64-osx-dynamic", Archiver::Libtool),
+            ("x64-osx-release", Archiver::Libtool),
+            ("arm64-osx-release", Archiver::Libtool),
+            ("arm64-ios", Archiver::Libtool),
+            ("x64-linux", Archiver::Ar),
+            ("x64-linux-release", Archiver::Ar),
+            ("x64-mingw-static", Archiver::Ar),
+        ] {
+            assert_eq!(Archiver::for_triplet(&name.into()), archiver, "{}", name);
+        }
+    }
+}
@@ -1,12 +1,36 @@
 use std::collections::BTreeMap;
 use std::env;
 use std::fs;
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 
-use crate::env_vars::cargo::build_rs::OUT_DIR;
+use crate::binary_cache;
+#[cfg(feature = "bootstrap")]
+use crate::bootstrap;
+use crate::bundle;
+use crate::conflicts::{self, ConflictPolicy};
+use crate::copy_mode::{self, CopyMode};
+use crate::crt;
+use crate::directive::cargo_path;
+use crate::env_vars::cargo::build_rs::{
+    CARGO_CFG_TARGET_FEATURE, CARGO_MANIFEST_DIR, HOST, OUT_DIR, TARGET,
+};
+use crate::install;
+use crate::linkage_preference::LinkagePreference;
+use crate::metadata;
+use crate::port_cache::{load_port_manifest_cached, load_ports_cached};
+use crate::probe_json;
+use crate::rpath::{self, Rpath};
+use crate::stale;
+use crate::target_triplet::{check_compatibility, check_mixable};
+use crate::usage;
+use crate::vcpkg_target::is_versioned_shared_lib;
+use crate::verbose;
 use crate::{
-    envify, find_vcpkg_target, load_ports, msvc_target, remove_item, Error, Library, Port,
-    VcpkgTriplet, VcpkgTarget,
+    default_host_triplet, dynamic_allowed, env_default_triplet, envify, find_vcpkg_root,
+    find_vcpkg_target, forced_dynamic, installed_triplets, is_broken_status, msvc_target,
+    musl_fallback_target, port_statuses, remove_item, status_paths, Compatibility, Error, Library,
+    Mismatch, Port, TripletSettings, VcpkgInstallation, VcpkgTarget, VcpkgTriplet,
 };
 
 /// Configuration options for finding packages, setting up the tree and emitting metadata to cargo
@@ -18,6 +42,15 @@ pub struct Config {
     /// should cargo:include= metadata be emitted (defaults to false)
     pub(crate) emit_includes: bool,
 
+    /// should cargo:include= be emitted for the package of each port
+    pub(crate) emit_port_includes: bool,
+
+    /// should cargo:root=, cargo:include=, cargo:lib= and cargo:libs= be emitted
+    pub(crate) emit_links_metadata: bool,
+
+    /// should cargo:rerun-if-changed= be emitted for the status database and manifests
+    pub(crate) emit_rerun_if_changed: bool,
+
     /// .lib/.a files that must be be found for probing to be considered successful
     pub(crate) required_libs: Vec<String>,
 
@@ -27,10 +60,147 @@ pub struct Config {
     /// should DLLs be copied to OUT_DIR?
     pub(crate) copy_dlls: bool,
 
+    /// link the debug variants of the libraries for debug builds
+    pub(crate) use_debug_profile: bool,
+
+    /// copy the DLLs or link to them
+    pub(crate) dll_copy_mode: CopyMode,
+
+    /// how to add the shared library directories to the rpath, if at all
+    pub(crate) rpath: Option<Rpath>,
+
+    /// should DLLs be copied next to the executables cargo builds?
+    pub(crate) copy_dlls_to_profile_dir: bool,
+
+    /// which of the DLLs to copy to OUT_DIR, all of them if `None`
+    pub(crate) copy_dlls_filter: Option<Box<dyn Fn(&Path) -> bool + Send + Sync>>,
+
+    /// where to write the cargo metadata instead of stdout
+    pub(crate) metadata_writer: Option<Box<dyn Write + Send>>,
+
     /// override VCPKG_ROOT environment variable
     pub(crate) vcpkg_root: Option<PathBuf>,
 
+    /// directory of installed packages to use instead of vcpkg_root/installed
+    pub(crate) installed_root: Option<PathBuf>,
+
+    /// accept a vcpkg_root without .vcpkg-root, such as an exported tree
+    pub(crate) export_tree: bool,
+
     pub(crate) target: Option<VcpkgTriplet>,
+
+    /// the triplet that vcpkg builds tools for, instead of the default host triplet
+    pub(crate) host: Option<VcpkgTriplet>,
+
+    /// triplets to use for rust targets, instead of the default ones
+    pub(crate) target_map: BTreeMap<String, String>,
+
+    /// use the triplet of the glibc target for musl targets
+    pub(crate) allow_musl_fallback: bool,
+
+    /// the triplet was chosen with the musl fallback, which is warned about
+    pub(crate) used_musl_fallback: bool,
+
+    /// `target` was inferred from the rust target rather than configured, so its
+    /// release-only variant may be used instead, see `find_vcpkg_target`
+    pub(crate) inferred_triplet: bool,
+
+    /// whether the static or the dynamic default triplet is looked in, and in which order
+    pub(crate) linkage_preference: Option<LinkagePreference>,
+
+    /// override whether the `lib` prefix is taken off the library names to link with
+    pub(crate) strip_lib_prefix: Option<bool>,
+
+    /// triplets to find the packages in, in order, when they are not installed for `target`
+    pub(crate) triplet_fallbacks: Vec<VcpkgTriplet>,
+
+    /// what to do with ports that are also provided by a -sys crate in the build
+    pub(crate) conflict_policy: ConflictPolicy,
+
+    /// default libraries to remove from the link with /NODEFAULTLIB
+    pub(crate) suppressed_default_libs: Vec<String>,
+
+    /// should the C runtimes requested by MSVC libraries be checked?
+    pub(crate) inspect_crt: bool,
+
+    /// name of the archive to merge static libraries into
+    pub(crate) bundle: Option<String>,
+
+    /// link to libraries by their exact file names on non-windows targets
+    pub(crate) verbatim_link_names: bool,
+
+    /// give the kind of each library, `static` or `dylib`, in the link metadata
+    pub(crate) explicit_link_kind: bool,
+
+    /// should the probe result be written to OUT_DIR/vcpkg-probe.json?
+    pub(crate) write_probe_json: bool,
+
+    /// preprocessor defines needed by code that uses the libraries
+    pub(crate) defines: Vec<(String, Option<String>)>,
+
+    /// arguments to pass to the linker, with placeholders for the directories found
+    pub(crate) link_args: Vec<String>,
+
+    /// triplets to find packages in instead of `target`, with their dependencies
+    pub(crate) package_triplets: BTreeMap<String, VcpkgTriplet>,
+
+    /// ports to take from a triplet other than `target`
+    pub(crate) port_triplets: BTreeMap<String, VcpkgTriplet>,
+
+    /// link to the system libraries named in the usage files of the ports
+    pub(crate) emit_usage_libs: bool,
+
+    /// link to the libraries in the Libs of the .pc files that are not vcpkg files
+    pub(crate) emit_system_libs: bool,
+
+    /// emit a cargo warning whenever the details of a port had to be guessed
+    pub(crate) warn_on_fallback: bool,
+
+    /// restore ports that are not installed from the vcpkg binary cache
+    pub(crate) binary_cache: bool,
+
+    /// features of the port being found to link to, all installed features if empty
+    pub(crate) features: Vec<String>,
+
+    /// whether the default features of the port are selected along with `features`
+    pub(crate) default_features: bool,
+
+    /// link all of the libraries in lib/manual-link of the ports found
+    pub(crate) include_manual_link: bool,
+
+    /// libraries in lib/manual-link to link, by the name they are linked with
+    pub(crate) manual_link_libs: Vec<String>,
+
+    /// subdirectories of lib whose libraries are linked as well
+    pub(crate) extra_lib_subdirs: Vec<String>,
+
+    /// run `vcpkg install` for ports that are not installed
+    pub(crate) install_missing: bool,
+
+    /// vcpkg commit to bootstrap a tree at when there is no installation
+    #[cfg(feature = "bootstrap")]
+    pub(crate) bootstrap_commit: Option<String>,
+}
+
+/// What the packages are found for, see `Config::resolve`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Purpose {
+    /// linking them into the crate being built, see `find_package`
+    Link,
+    /// checking that they are installed, see `is_installed`
+    Check,
+    /// finding them in the host triplet, see `find_host_package`
+    Host,
+}
+
+/// Packages that were found by `Config::resolve`, before anything is emitted
+/// for them.
+struct Resolved {
+    lib: Library,
+    vcpkg_target: VcpkgTarget,
+
+    // the packages only install executables, so there is nothing to link
+    links_nothing: bool,
 }
 
 impl Config {
@@ -38,23 +208,182 @@ impl Config {
         Config {
             cargo_metadata: true,
             copy_dlls: true,
+            default_features: true,
+            emit_rerun_if_changed: true,
             ..Default::default()
         }
     }
 
     fn get_target_triplet(&mut self) -> Result<VcpkgTriplet, Error> {
-        use crate::env_vars::vcpkg_rs::VCPKGRS_TRIPLET;
+        use crate::env_vars::vcpkg_rs::prefix::VCPKGRS_TRIPLET_;
+        use crate::env_vars::vcpkg_rs::{VCPKGRS_MUSL_FALLBACK, VCPKGRS_TRIPLET};
 
         if self.target.is_none() {
-            let target = if let Ok(triplet_str) = env::var(VCPKGRS_TRIPLET) {
-                triplet_str.into()
+            // VCPKGRS_TRIPLET_<RUST_TARGET> for builds of several targets at once
+            let target_triplet = env::var(TARGET).ok().and_then(|target| {
+                env::var(format!("{}{}", VCPKGRS_TRIPLET_, envify(&target))).ok()
+            });
+            let (target, source) = if let Some(triplet_str) = target_triplet {
+                (triplet_str.into(), "VCPKGRS_TRIPLET_<TARGET>")
+            } else if let Ok(triplet_str) = env::var(VCPKGRS_TRIPLET) {
+                (triplet_str.into(), VCPKGRS_TRIPLET)
+            } else if let Some(triplet_str) = self.mapped_triplet() {
+                (triplet_str.into(), "the target map")
             } else {
-                msvc_target()?
+                match self.preferred_triplet() {
+                    Err(Error::NotMSVC)
+                        if self.allow_musl_fallback
+                            || env::var_os(VCPKGRS_MUSL_FALLBACK).is_some() =>
+                    {
+                        let target = musl_fallback_target()?;
+                        self.used_musl_fallback = true;
+                        self.inferred_triplet = true;
+                        (target, "the musl fallback")
+                    }
+                    target => {
+                        self.inferred_triplet = true;
+                        (target?, "the default for the rust target")
+                    }
+                }
             };
+            verbose::log(format_args!(
+                "using vcpkg triplet {} from {}",
+                target.name, source
+            ));
             self.target = Some(target);
         }
 
-        Ok(self.target.as_ref().unwrap().clone())
+        let target = self.target.clone().unwrap();
+        Ok(self.resolve_triplet(target))
+    }
+
+    /// The default triplet for the rust target with the linkage of `linkage_preference`,
+    /// unless `VCPKGRS_DYNAMIC` or `VCPKGRS_STATIC` force one.
+    fn preferred_triplet(&self) -> Result<VcpkgTriplet, Error> {
+        match (forced_dynamic(), self.linkage_preference) {
+            (None, Some(preference)) => env_default_triplet(preference.prefers_dynamic()),
+            _ => msvc_target(),
+        }
+    }
+
+    /// The triplet that vcpkg builds tools for, which dependencies qualified with
+    /// it are built in, see `host_triplet`.
+    pub(crate) fn get_host_triplet(&self) -> Option<VcpkgTriplet> {
+        self.host
+            .clone()
+            .or_else(default_host_triplet)
+            .map(|triplet| self.resolve_triplet(triplet))
+    }
+
+    /// The settings of the triplet `name`, read from its triplet file in an overlay
+    /// triplet directory or the vcpkg root if there is one.
+    fn triplet_settings(&self, name: &str) -> TripletSettings {
+        let vcpkg_root = find_vcpkg_root(self).ok();
+        TripletSettings::resolve(name, vcpkg_root.as_ref().map(|root| root.as_path()))
+    }
+
+    /// Whether the triplet file of the triplet `name` can be found and sets its CRT
+    /// linkage.
+    fn crt_linkage_is_known(&self, name: &str) -> bool {
+        let vcpkg_root = find_vcpkg_root(self).ok();
+        TripletSettings::file_sets_crt_linkage(name, vcpkg_root.as_ref().map(|root| root.as_path()))
+    }
+
+    /// Take the linkage of a triplet from its triplet file if it can be found, as a
+    /// custom triplet may not follow the naming conventions of the vcpkg ones.
+    fn resolve_triplet(&self, triplet: VcpkgTriplet) -> VcpkgTriplet {
+        let vcpkg_root = find_vcpkg_root(self).ok();
+        match TripletSettings::from_file(
+            &triplet.name,
+            vcpkg_root.as_ref().map(|root| root.as_path()),
+        ) {
+            Some(settings) => triplet.with_settings(&settings),
+            None => triplet,
+        }
+    }
+
+    /// The triplet to find `port_name` in when resolving it for `purpose`, which is
+    /// the host triplet for `find_host_package`.
+    fn purpose_triplet(
+        &mut self,
+        port_name: &str,
+        purpose: Purpose,
+    ) -> Result<VcpkgTriplet, Error> {
+        if purpose == Purpose::Host {
+            return self
+                .get_host_triplet()
+                .ok_or_else(|| Error::RequiredEnvMissing(HOST.to_owned()));
+        }
+        self.get_package_triplet(port_name)
+    }
+
+    /// The triplet that `map_target` or `VCPKGRS_TARGET_MAP` gives for the rust target.
+    fn mapped_triplet(&self) -> Option<String> {
+        use crate::env_vars::vcpkg_rs::VCPKGRS_TARGET_MAP;
+
+        let target = env::var(TARGET).ok()?;
+        if let Some(triplet) = self.target_map.get(&target) {
+            return Some(triplet.clone());
+        }
+        env::var(VCPKGRS_TARGET_MAP)
+            .unwrap_or(String::new())
+            .split(|c| c == ',' || c == ';')
+            .filter_map(|entry| {
+                let eq = entry.find('=')?;
+                if entry[..eq].trim() == target {
+                    Some(entry[eq + 1..].trim().to_owned())
+                } else {
+                    None
+                }
+            })
+            .next()
+    }
+
+    /// The triplet to find `port_name` in, which `package_triplet` and
+    /// `VCPKGRS_TRIPLET_<PACKAGE>` override.
+    fn get_package_triplet(&mut self, port_name: &str) -> Result<VcpkgTriplet, Error> {
+        use crate::env_vars::vcpkg_rs::prefix::{VCPKGRS_DYNAMIC_, VCPKGRS_TRIPLET_};
+
+        let var_name = format!("{}{}", VCPKGRS_TRIPLET_, envify(port_name));
+        match env::var(&var_name) {
+            Ok(triplet_str) => {
+                verbose::log(format_args!(
+                    "finding {} in vcpkg triplet {} from {}",
+                    port_name, triplet_str, var_name
+                ));
+                Ok(self.resolve_triplet(triplet_str.into()))
+            }
+            Err(_) => match self.package_triplets.get(port_name).cloned() {
+                Some(triplet) => {
+                    verbose::log(format_args!(
+                        "finding {} in vcpkg triplet {} from Config::package_triplet",
+                        port_name, triplet.name
+                    ));
+                    Ok(self.resolve_triplet(triplet))
+                }
+                None => {
+                    let triplet = self.get_target_triplet()?;
+                    // VCPKGRS_DYNAMIC_<PACKAGE> swaps the default triplet for the dynamic one
+                    if dynamic_allowed(&[port_name]) {
+                        if let (Ok(default), Ok(dynamic)) =
+                            (env_default_triplet(false), env_default_triplet(true))
+                        {
+                            if triplet.name == default.name {
+                                verbose::log(format_args!(
+                                    "finding {} in vcpkg triplet {} as {}{} is set",
+                                    port_name,
+                                    dynamic.name,
+                                    VCPKGRS_DYNAMIC_,
+                                    envify(port_name)
+                                ));
+                                return Ok(self.resolve_triplet(dynamic));
+                            }
+                        }
+                    }
+                    Ok(triplet)
+                }
+            },
+        }
     }
 
     /// Find the package `port_name` in a Vcpkg tree.
@@ -65,12 +394,251 @@ impl Config {
     /// This will select the architecture and linkage based on environment
     /// variables and build flags as described in the module docs, and any configuration
     /// set on the builder.
+    ///
+    /// A port that only installs executables, such as `pkgconf`, has nothing to
+    /// link to. For such a port no metadata is emitted and the `Library` that is
+    /// returned only lists the executables in `tools`.
+    ///
+    /// `cargo:rerun-if-changed` metadata is emitted for the vcpkg status database
+    /// and the manifests of the ports, so that the crate is rebuilt when they are
+    /// installed, upgraded or removed. Like any `rerun-if-changed` metadata, this
+    /// stops cargo from rerunning the build script whenever a file in the package
+    /// changes, so a build script that relies on that should emit its own or turn
+    /// this off with `emit_rerun_if_changed(false)`.
     pub fn find_package(&mut self, port_name: &str) -> Result<Library, Error> {
+        self.find_packages(&[port_name])
+    }
+
+    /// Find several packages in a Vcpkg tree at once, as `find_package` does for
+    /// one of them.
+    ///
+    /// The status database is read once for all of the packages, and a single
+    /// `Library` is returned for the union of the packages and the packages they
+    /// depend on, so each library and search path appears once in the metadata.
+    /// The packages must all be found in the same triplet.
+    ///
+    /// ```no_run
+    /// let lib = vcpkg::Config::new()
+    ///     .find_packages(&["zlib", "libpng", "freetype"])
+    ///     .unwrap();
+    /// ```
+    pub fn find_packages(&mut self, port_names: &[&str]) -> Result<Library, Error> {
+        if port_names.is_empty() {
+            return Err(Error::LibNotFound {
+                port: String::new(),
+                triplet: String::new(),
+                path: None,
+                installed_triplets: Vec::new(),
+                install_command: None,
+                detail: "no packages were given".to_owned(),
+            });
+        }
+
+        #[cfg(feature = "bootstrap")]
+        {
+            if let Some(commit) = self.bootstrap_commit.clone() {
+                if find_vcpkg_root(self).is_err() {
+                    self.vcpkg_root = Some(bootstrap::bootstrap(&commit)?);
+                }
+            }
+        }
+
+        let result = match self.find_installed_package(port_names) {
+            Err(Error::LibNotFound { .. }) if self.install_missing => {
+                let vcpkg_root = find_vcpkg_root(self)?;
+                for port_name in port_names {
+                    let triplet = self.get_package_triplet(port_name)?;
+                    install::install_port(&vcpkg_root, port_name, &triplet.name)?;
+                }
+                self.find_installed_package(port_names)
+            }
+            Err(e @ Error::VcpkgNotFound(_)) | Err(e @ Error::LibNotFound { .. })
+                if self.binary_cache =>
+            {
+                let triplet = self.get_package_triplet(port_names[0])?;
+                let root = match binary_cache::restore_ports(port_names, &triplet.name)? {
+                    Some(root) => root,
+                    None => return Err(e),
+                };
+                let vcpkg_root = self.vcpkg_root.take();
+                self.vcpkg_root = Some(root);
+                let result = self.find_installed_package(port_names);
+                self.vcpkg_root = vcpkg_root;
+                result
+            }
+            result => result,
+        };
+        match result {
+            Err(e @ Error::LibNotFound { .. }) => {
+                self.find_in_fallback_triplets(port_names).unwrap_or(Err(e))
+            }
+            result => result,
+        }
+    }
+
+    /// The default triplet of the other linkage when `linkage_preference` falls back
+    /// to it and the triplet was inferred, followed by the triplets of
+    /// `triplet_fallbacks`, or of `VCPKGRS_TRIPLET_FALLBACKS`.
+    fn fallback_triplets(&self) -> Vec<VcpkgTriplet> {
+        use crate::env_vars::vcpkg_rs::VCPKGRS_TRIPLET_FALLBACKS;
+
+        let mut triplets = Vec::new();
+        if let (Some(preference), Some(target)) = (self.linkage_preference, self.target.as_ref()) {
+            if preference.falls_back() && self.inferred_triplet && forced_dynamic().is_none() {
+                match env_default_triplet(!preference.prefers_dynamic()) {
+                    Ok(other) if other.name != target.name => triplets.push(other),
+                    _ => {}
+                }
+            }
+        }
+        if !self.triplet_fallbacks.is_empty() {
+            triplets.extend(self.triplet_fallbacks.iter().cloned());
+            return triplets;
+        }
+        triplets.extend(
+            env::var(VCPKGRS_TRIPLET_FALLBACKS)
+                .unwrap_or(String::new())
+                .split(|c| c == ',' || c == ';')
+                .map(|triplet| triplet.trim())
+                .filter(|triplet| !triplet.is_empty())
+                .map(VcpkgTriplet::from),
+        );
+        triplets
+    }
+
+    /// Whether the packages may be found in a dynamic triplet, which has to be
+    /// opted in to with `VCPKGRS_DYNAMIC` or `linkage_preference`, and which
+    /// `VCPKGRS_STATIC` opts out of. Host packages are never linked, so they may
+    /// always be found in a dynamic triplet.
+    fn dynamic_allowed(&self, port_names: &[&str], purpose: Purpose) -> bool {
+        let preference_allows = match self.linkage_preference {
+            Some(preference) => preference.allows_dynamic() && forced_dynamic() != Some(false),
+            None => false,
+        };
+        purpose == Purpose::Host || preference_allows || dynamic_allowed(port_names)
+    }
+
+    /// Find the packages in the first of the fallback triplets that they are
+    /// installed in, or `None` if there is no such triplet.
+    fn find_in_fallback_triplets(&mut self, port_names: &[&str]) -> Option<Result<Library, Error>> {
+        let fallback_triplets = self.fallback_triplets();
+        let target = self.target.take();
+        let inferred_triplet = self.inferred_triplet;
+        self.inferred_triplet = false;
+        let mut result = None;
+        for triplet in fallback_triplets {
+            verbose::log(format_args!(
+                "trying the fallback vcpkg triplet {}",
+                triplet.name
+            ));
+            self.target = Some(triplet);
+            match self.find_installed_package(port_names) {
+                Err(Error::LibNotFound { .. }) => continue,
+                found => {
+                    result = Some(found);
+                    break;
+                }
+            }
+        }
+        self.target = target;
+        self.inferred_triplet = inferred_triplet;
+        result
+    }
+
+    /// Check whether the package `port_name` and the packages it depends on are
+    /// installed in the Vcpkg tree with all of their library files, without
+    /// emitting any metadata, copying DLLs or installing anything.
+    ///
+    /// Returns `Ok(false)` if something is missing, so that a -sys crate can fall
+    /// back to building the library from source. Other errors, such as not
+    /// finding a Vcpkg tree at all, are returned as they are.
+    pub fn is_installed(&mut self, port_name: &str) -> Result<bool, Error> {
+        match self.resolve(&[port_name], Purpose::Check) {
+            Ok(_) => Ok(true),
+            Err(Error::LibNotFound { .. }) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Find the package `port_name` in the host triplet, see `host_triplet`, rather
+    /// than the triplet of the target, for the tools that a build script runs and
+    /// the libraries it links to itself, such as a code generator, when cross
+    /// compiling.
+    ///
+    /// The paths of the package are returned in `tools`, `link_paths`, `found_libs`
+    /// and so on, but as the package is not for the target no metadata is emitted,
+    /// no DLLs are copied and the package is neither installed nor restored from
+    /// the binary cache if it is missing.
+    ///
+    /// ```no_run
+    /// let protobuf = vcpkg::Config::new().find_host_package("protobuf").unwrap();
+    /// let protoc = protobuf
+    ///     .tools
+    ///     .iter()
+    ///     .find(|tool| tool.file_stem().map_or(false, |stem| stem == "protoc"));
+    /// ```
+    pub fn find_host_package(&mut self, port_name: &str) -> Result<Library, Error> {
+        Ok(self.resolve(&[port_name], Purpose::Host)?.lib)
+    }
+
+    /// The settings of the triplet that `find_package` looks in for `TARGET`.
+    #[doc(hidden)]
+    pub fn selected_triplet(&mut self) -> Result<TripletSettings, Error> {
+        let triplet = self.get_target_triplet()?;
+        Ok(self.triplet_settings(&triplet.name))
+    }
+
+    /// Find the vcpkg tree and triplet that packages would be found in, and read
+    /// the ports installed there, to look up several packages without doing so
+    /// again for each of them.
+    pub fn installation(&mut self) -> Result<VcpkgInstallation, Error> {
+        let triplet = self.get_target_triplet()?;
+        let vcpkg_target = find_vcpkg_target(self, &triplet)?;
+        let ports = load_ports_cached(&vcpkg_target)?
+            .into_iter()
+            .map(|(name, port)| (name, port.version))
+            .collect();
+        Ok(VcpkgInstallation {
+            vcpkg_root: find_vcpkg_root(self).ok(),
+            installed_root: vcpkg_target.status_path.parent().unwrap().to_path_buf(),
+            triplet,
+            ports,
+        })
+    }
+
+    fn find_installed_package(&mut self, port_names: &[&str]) -> Result<Library, Error> {
+        let Resolved {
+            mut lib,
+            vcpkg_target,
+            links_nothing,
+        } = self.resolve(port_names, Purpose::Link)?;
+        if links_nothing {
+            self.write_metadata(&lib)?;
+        } else {
+            self.emit(&mut lib, &vcpkg_target)?;
+        }
+        Ok(lib)
+    }
+
+    /// Find the packages and the libraries to link to for them, without emitting
+    /// anything, copying DLLs or writing to `OUT_DIR`. Only `Purpose::Link` checks
+    /// that the triplet suits the rust target and looks for stale installations
+    /// and conflicting `-sys` crates.
+    fn resolve(&mut self, port_names: &[&str], purpose: Purpose) -> Result<Resolved, Error> {
         use crate::env_vars::vcpkg_rs::prelude::*;
 
         // determine the target type, bailing out if it is not some
         // kind of msvc
-        let msvc_target = self.get_target_triplet()?;
+        let msvc_target = self.purpose_triplet(port_names[0], purpose)?;
+        for port_name in &port_names[1..] {
+            let triplet = self.purpose_triplet(port_name, purpose)?;
+            if triplet.name != msvc_target.name {
+                return Err(Error::IncompatibleTriplet(format!(
+                    "package {} is to be found in vcpkg triplet {} but {} in {}",
+                    port_names[0], msvc_target.name, port_name, triplet.name
+                )));
+            }
+        }
 
         // bail out if requested to not try at all
         if env::var_os(VCPKGRS_DISABLE).is_some() {
@@ -82,32 +650,138 @@ impl Config {
             return Err(Error::DisabledByEnv(NO_VCPKG.to_owned()));
         }
 
-        // bail out if requested to skip this package
-        let abort_var_name = format!("{}{}", prefix::VCPKGRS_NO_, envify(port_name));
-        if env::var_os(&abort_var_name).is_some() {
-            return Err(Error::DisabledByEnv(abort_var_name));
-        }
+        for port_name in port_names {
+            // bail out if requested to skip this package
+            let abort_var_name = format!("{}{}", prefix::VCPKGRS_NO_, envify(port_name));
+            if env::var_os(&abort_var_name).is_some() {
+                return Err(Error::DisabledByEnv(abort_var_name));
+            }
 
-        // bail out if requested to skip this package (old)
-        let abort_var_name = format!("{}{}", envify(port_name), suffix::_NO_VCPKG);
-        if env::var_os(&abort_var_name).is_some() {
-            return Err(Error::DisabledByEnv(abort_var_name));
+            // bail out if requested to skip this package (old)
+            let abort_var_name = format!("{}{}", envify(port_name), suffix::_NO_VCPKG);
+            if env::var_os(&abort_var_name).is_some() {
+                return Err(Error::DisabledByEnv(abort_var_name));
+            }
         }
 
         let vcpkg_target = find_vcpkg_target(&self, &msvc_target)?;
+
+        // targets for the ports that are taken from other triplets
+        let mut port_targets = BTreeMap::new();
+        let target_settings = self.triplet_settings(&msvc_target.name);
+        let port_triplets = if purpose == Purpose::Host {
+            BTreeMap::new()
+        } else {
+            self.port_triplets.clone()
+        };
+        for (port, triplet) in &port_triplets {
+            let triplet = &self.resolve_triplet(triplet.clone());
+            check_mixable(&target_settings, &self.triplet_settings(&triplet.name)).map_err(|mismatch| {
+                Error::IncompatibleTriplet(format!(
+                    "port {} can't be taken from vcpkg triplet {} when linking with {} ({:?} mismatch)",
+                    port, triplet.name, msvc_target.name, mismatch
+                ))
+            })?;
+            if !triplet.is_static && !self.dynamic_allowed(&[port], purpose) {
+                return Err(Error::RequiredEnvMissing(VCPKGRS_DYNAMIC.to_owned()));
+            }
+            port_targets.insert(port.clone(), find_vcpkg_target(&self, triplet)?);
+        }
+        let mut lib_targets = BTreeMap::new();
+        let mut dll_targets = BTreeMap::new();
+        let mut required_libs = self.required_libs.clone();
+        let mut required_dlls = self.required_dlls.clone();
+
         let mut required_port_order = Vec::new();
+        let mut port_versions = BTreeMap::new();
+        let mut port_libs = BTreeMap::new();
+        let mut port_defines = Vec::new();
+        let mut port_include_dirs = Vec::new();
+        let mut port_pc_libs = BTreeMap::new();
+        let mut root_tools = Vec::new();
+        let mut watched_paths = status_paths(&vcpkg_target);
+        let mut warnings = Vec::new();
+        let mut tree_looks_stale = false;
+
+        // a triplet that was chosen explicitly may not suit the target
+        let target = env::var(TARGET).ok().filter(|_| purpose == Purpose::Link);
+        if let Some(target) = target {
+            let crt_static = env::var(CARGO_CFG_TARGET_FEATURE)
+                .unwrap_or(String::new())
+                .contains("crt-static");
+            if let Compatibility::Mismatched(mismatch) =
+                check_compatibility(&target, crt_static, &target_settings)
+            {
+                // libraries built against the other CRT link but crash at runtime. The
+                // CRT linkage of a triplet without a triplet file is only a guess from
+                // its name, which a custom triplet need not follow.
+                if mismatch == Mismatch::Linkage
+                    && env::var_os(VCPKGRS_ALLOW_CRT_MISMATCH).is_none()
+                    && self.crt_linkage_is_known(&msvc_target.name)
+                {
+                    return Err(Error::CrtMismatch(format!(
+                        "vcpkg triplet {} links the C runtime {}ally but rust target {} {} \
+                         crt-static, set {} to link anyway",
+                        msvc_target.name,
+                        if crt_static { "dynamic" } else { "static" },
+                        target,
+                        if crt_static { "has" } else { "does not have" },
+                        VCPKGRS_ALLOW_CRT_MISMATCH
+                    )));
+                }
+                warnings.push(format!(
+                    "cargo:warning=vcpkg triplet {} does not suit rust target {} ({:?} mismatch)",
+                    msvc_target.name, target, mismatch
+                ));
+            }
+        }
+        if self.used_musl_fallback && purpose == Purpose::Link {
+            warnings.push(format!(
+                "cargo:warning=linking musl target {} to libraries from vcpkg triplet {}, \
+                 which were built against glibc",
+                env::var(TARGET).unwrap_or(String::new()),
+                msvc_target.name
+            ));
+        }
+        let root_port_names = port_names;
 
         // if no overrides have been selected, then the Vcpkg port name
         // is the the .lib name and the .dll name
-        if self.required_libs.is_empty() {
-            let ports = load_ports(&vcpkg_target)?;
-
-            if ports.get(&port_name.to_owned()).is_none() {
-                return Err(Error::LibNotFound(format!(
-                    "package {} is not installed for vcpkg triplet {}",
-                    port_name.to_owned(),
-                    vcpkg_target.target_triplet.name
-                )));
+        if required_libs.is_empty() {
+            let mut ports = load_ports_cached(&vcpkg_target)?;
+            for (port_name, target) in &port_targets {
+                match load_ports_cached(target)?.remove(port_name) {
+                    Some(port) => ports.insert(port_name.clone(), port),
+                    None => ports.remove(port_name),
+                };
+            }
+
+            for &port_name in root_port_names {
+                let target = port_targets.get(port_name).unwrap_or(&vcpkg_target);
+                let triplet = &target.target_triplet.name;
+                if ports.get(&port_name.to_owned()).is_none() {
+                    return Err(port_not_installed(port_name, target));
+                }
+
+                // "core" is the port without any of its optional features
+                for feature in &self.features {
+                    if feature != "core" && !ports[port_name].features.contains_key(feature) {
+                        return Err(Error::LibNotFound {
+                            port: port_name.to_owned(),
+                            triplet: triplet.clone(),
+                            path: None,
+                            installed_triplets: Vec::new(),
+                            install_command: Some(format!(
+                                "vcpkg install {}[{}]:{}",
+                                port_name, feature, triplet
+                            )),
+                            detail: format!(
+                                "feature {} of package {} is not installed for vcpkg triplet {}",
+                                feature, port_name, triplet
+                            ),
+                        });
+                    }
+                }
             }
 
             // the complete set of ports required
@@ -115,7 +789,12 @@ impl Config {
             // working of ports that we need to include
             //        let mut ports_to_scan: BTreeSet<String> = BTreeSet::new();
             //        ports_to_scan.insert(port_name.to_owned());
-            let mut ports_to_scan = vec![port_name.to_owned()]; //: Vec<String> = BTreeSet::new();
+            // the ports are taken from the end, so the first package is scanned first
+            let mut ports_to_scan: Vec<String> = root_port_names
+                .iter()
+                .rev()
+                .map(|port_name| port_name.to_string())
+                .collect();
 
             while !ports_to_scan.is_empty() {
                 let port_name = ports_to_scan.pop().unwrap();
@@ -125,56 +804,252 @@ impl Config {
                 }
 
                 if let Some(port) = ports.get(&port_name) {
-                    for dep in &port.deps {
-                        ports_to_scan.push(dep.clone());
+                    let is_root = root_port_names.contains(&port_name.as_str());
+                    let features = if is_root {
+                        self.selected_features(port)
+                    } else {
+                        None
+                    };
+                    ports_to_scan
+                        .extend(port.deps_with_features(features.as_ref().map(|f| &f[..])));
+                    if !port.host_deps.is_empty() {
+                        verbose::log(format_args!(
+                            "port {} is built with host tools {:?}, which are not linked",
+                            port_name, port.host_deps
+                        ));
                     }
                     required_ports.insert(port_name.clone(), (*port).clone());
                     remove_item(&mut required_port_order, &port_name);
                     required_port_order.push(port_name);
-                } else {
-                    // what?
+                } else if let Some(target) = port_targets.get(&port_name) {
+                    return Err(port_not_installed(&port_name, target));
+                } else if let Some(err) = broken_port(&port_name, &vcpkg_target) {
+                    return Err(err);
+                } else if self.warn_on_fallback {
+                    warnings.push(format!(
+                        "cargo:warning=vcpkg port {} is a dependency of {} but is not installed \
+                         for triplet {}, so it is not linked",
+                        port_name,
+                        root_port_names.join(", "),
+                        vcpkg_target.target_triplet.name
+                    ));
+                }
+            }
+
+            // only the ports that are needed have their manifests read, as there
+            // may be hundreds of ports installed
+            let mut found_manual_link_libs = Vec::new();
+            for (port_name, port) in required_ports.iter_mut() {
+                let target = port_targets.get(port_name).unwrap_or(&vcpkg_target);
+                load_port_manifest_cached(port_name, port, target)?;
+                // manual-link libraries such as SDL2main come before the libraries
+                // of the port that they use
+                let manual_link_libs = port.subdir_libs.get("manual-link").cloned();
+                for manual_link_lib in manual_link_libs.iter().flatten().rev() {
+                    let name = target.link_name(&target.lib_stem(manual_link_lib));
+                    if self.include_manual_link || self.manual_link_libs.contains(&name) {
+                        port.libs
+                            .insert(0, format!("manual-link/{}", manual_link_lib));
+                        found_manual_link_libs.push(name);
+                    }
+                }
+                for subdir in &self.extra_lib_subdirs {
+                    let subdir_libs = port.subdir_libs.get(subdir).cloned();
+                    for lib in subdir_libs.iter().flatten() {
+                        let lib = format!("{}/{}", subdir, lib);
+                        if !port.libs.contains(&lib) {
+                            port.libs.push(lib);
+                        }
+                    }
+                }
+                if self.warn_on_fallback {
+                    warnings.extend(
+                        port.fallbacks
+                            .iter()
+                            .map(|fallback| format!("cargo:warning={}", fallback)),
+                    );
+                }
+            }
+
+            if let Some(name) = self
+                .manual_link_libs
+                .iter()
+                .find(|name| !found_manual_link_libs.contains(name))
+            {
+                return Err(Error::LibNotFound {
+                    port: root_port_names.join(", "),
+                    triplet: vcpkg_target.target_triplet.name.clone(),
+                    path: None,
+                    installed_triplets: Vec::new(),
+                    install_command: None,
+                    detail: format!(
+                        "manual-link library {} is not installed by {} or its dependencies",
+                        name,
+                        root_port_names.join(", ")
+                    ),
+                });
+            }
+
+            // ports such as vcpkg-cmake are only needed to build the others, and
+            // have nothing to link to or include
+            for (port_name, port) in &required_ports {
+                if port.is_build_tool() && !root_port_names.contains(&port_name.as_str()) {
+                    verbose::log(format_args!(
+                        "leaving out port {}, which installs no libraries or headers",
+                        port_name
+                    ));
+                    remove_item(&mut required_port_order, port_name);
+                }
+            }
+
+            let mut links_nothing = true;
+            for &root_port_name in root_port_names {
+                let root_target = port_targets.get(root_port_name).unwrap_or(&vcpkg_target);
+                let root_port = &required_ports[root_port_name];
+                root_tools.extend(
+                    root_port
+                        .tools
+                        .iter()
+                        .map(|tool| root_target.tool_location(tool)),
+                );
+                links_nothing &= root_port.libs.is_empty() && root_port.dlls.is_empty();
+            }
+
+            // a port such as pkgconf only provides executables, so there is
+            // nothing to link to and the tools are the result
+            if links_nothing && !root_tools.is_empty() {
+                let root_target = port_targets
+                    .get(root_port_names[0])
+                    .unwrap_or(&vcpkg_target);
+                let mut lib = Library::new(
+                    root_target.target_triplet.is_static,
+                    &root_target.target_triplet.name,
+                );
+                lib.triplet_settings =
+                    Some(self.triplet_settings(&root_target.target_triplet.name));
+                lib.ports.extend(
+                    root_port_names
+                        .iter()
+                        .map(|port_name| port_name.to_string()),
+                );
+                lib.tools = root_tools;
+                lib.cargo_metadata.extend(warnings);
+                return Ok(Resolved {
+                    lib,
+                    vcpkg_target,
+                    links_nothing: true,
+                });
+            }
+
+            if purpose == Purpose::Link {
+                let main_ports = required_ports
+                    .iter()
+                    .filter(|&(port_name, _)| !port_targets.contains_key(port_name))
+                    .map(|(port_name, port)| (port_name.clone(), port.clone()))
+                    .collect();
+                let mut stale_warnings =
+                    stale::stale_installation_warnings(&vcpkg_target, &main_ports);
+                for (port_name, target) in &port_targets {
+                    if let Some(port) = required_ports.get(port_name) {
+                        let mut ports = BTreeMap::new();
+                        ports.insert(port_name.clone(), port.clone());
+                        stale_warnings.extend(stale::stale_installation_warnings(target, &ports));
+                    }
+                }
+                tree_looks_stale = !stale_warnings.is_empty();
+                warnings.extend(stale_warnings);
+            }
+
+            watched_paths.extend(required_ports.values().map(|port| port.manifest.clone()));
+
+            let installed_path = vcpkg_target.status_path.parent().unwrap();
+            for port_name in required_port_order.clone() {
+                if self.conflict_policy == ConflictPolicy::Ignore || purpose != Purpose::Link {
+                    break;
+                }
+                let conflict = match conflicts::detect_conflict(&port_name, installed_path) {
+                    Some(conflict) => conflict,
+                    None => continue,
+                };
+                match self.conflict_policy {
+                    ConflictPolicy::Skip if !root_port_names.contains(&port_name.as_str()) => {
+                        remove_item(&mut required_port_order, &port_name);
+                    }
+                    ConflictPolicy::Skip | ConflictPolicy::Ignore => {}
+                    ConflictPolicy::Warn => warnings.push(conflict.warning()),
                 }
             }
 
-            // for port in ports {
-            //     println!("port {:?}", port);
-            // }
-            // println!("== Looking for port {}", port_name);
-            // for port in &required_port_order {
-            //     println!("ordered required port {:?}", port);
-            // }
-            // println!("=============================");
-            // for port in &required_ports {
-            //     println!("required port {:?}", port);
-            // }
+            verbose::log(format_args!(
+                "required ports in link order: {}",
+                required_port_order
+                    .iter()
+                    .map(|port_name| format!("{} {}", port_name, required_ports[port_name].version))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+            for port_name in &required_port_order {
+                let port = &required_ports[port_name];
+                port_versions.insert(port_name.clone(), port.version.clone());
+                let mut pc_libs = Vec::new();
+                if self.emit_system_libs {
+                    pc_libs.extend(port.system_libs.iter().cloned());
+                }
+                let target = port_targets.get(port_name).unwrap_or(&vcpkg_target);
+                port_libs.insert(
+                    port_name.clone(),
+                    port.libs
+                        .iter()
+                        .map(|lib| {
+                            let stem = target.debug_lib_stem(&target.lib_stem(lib));
+                            target.lib_path.join(target.lib_file_name(&stem))
+                        })
+                        .collect::<Vec<_>>(),
+                );
+                if target.target_triplet.is_static {
+                    pc_libs.extend(port.private_libs.iter().cloned());
+                }
+                port_pc_libs.insert(port_name.clone(), pc_libs);
+                for define in &port.defines {
+                    if !port_defines.contains(define) {
+                        port_defines.push(define.clone());
+                    }
+                }
+                for include_dir in &port.include_dirs {
+                    if !port_include_dirs.contains(include_dir) {
+                        port_include_dirs.push(include_dir.clone());
+                    }
+                }
+            }
 
             // if no overrides have been selected, then the Vcpkg port name
             // is the the .lib name and the .dll name
-            if self.required_libs.is_empty() {
+            if required_libs.is_empty() {
                 for port_name in &required_port_order {
                     let port = required_ports.get(port_name).unwrap();
-                    self.required_libs.extend(port.libs.iter().map(|s| {
-                        Path::new(&s)
-                            .file_stem()
-                            .unwrap()
-                            .to_string_lossy()
-                            .into_owned()
-                    }));
-                    self.required_dlls
-                        .extend(port.dlls.iter().cloned().map(|s| {
-                            Path::new(&s)
-                                .file_stem()
-                                .unwrap()
-                                .to_string_lossy()
-                                .into_owned()
-                        }));
+                    let target = port_targets.get(port_name);
+                    for lib in &port.libs {
+                        let stem = target.unwrap_or(&vcpkg_target).lib_stem(lib);
+                        if let Some(target) = target {
+                            lib_targets.insert(stem.clone(), target);
+                        }
+                        required_libs.push(stem);
+                    }
+                    for dll in &port.dlls {
+                        let stem = target.unwrap_or(&vcpkg_target).dll_stem(dll);
+                        if let Some(target) = target {
+                            dll_targets.insert(stem.clone(), target);
+                        }
+                        required_dlls.push(stem);
+                    }
                 }
             }
         }
         // require explicit opt-in before using dynamically linked
         // variants, otherwise cargo install of various things will
         // stop working if Vcpkg is installed.
-        if !vcpkg_target.target_triplet.is_static && !env::var_os(VCPKGRS_DYNAMIC).is_some() {
+        if !vcpkg_target.target_triplet.is_static && !self.dynamic_allowed(root_port_names, purpose)
+        {
             return Err(Error::RequiredEnvMissing(VCPKGRS_DYNAMIC.to_owned()));
         }
 
@@ -182,55 +1057,300 @@ impl Config {
             vcpkg_target.target_triplet.is_static,
             &vcpkg_target.target_triplet.name,
         );
+        lib.is_debug = vcpkg_target.is_debug;
+        lib.triplet_settings = Some(target_settings);
+
+        self.add_target_dirs(&mut lib, &vcpkg_target)?;
+        // subdirectories such as include/libxml2 that the .pc files point to
+        for include_dir in port_include_dirs {
+            if self.emit_includes {
+                lib.cargo_metadata
+                    .push(format!("cargo:include={}", cargo_path(&include_dir)?));
+            }
+            lib.include_paths.push(include_dir);
+        }
+        self.emit_defines(&mut lib, &port_defines);
+
+        for (port_name, target) in &port_targets {
+            if required_port_order.contains(port_name) {
+                self.add_target_dirs(&mut lib, target)?;
+            }
+        }
+
+        // rebuild after ports are installed, upgraded or removed
+        if self.emit_rerun_if_changed {
+            for path in &watched_paths {
+                lib.cargo_metadata
+                    .push(format!("cargo:rerun-if-changed={}", cargo_path(path)?));
+            }
+        }
+
+        lib.ports = required_port_order;
+        lib.tools = root_tools;
+        lib.port_versions = port_versions;
+        lib.port_libs = port_libs;
+        // the packages keep the headers of each port in its own layout, which may
+        // differ from the merged include directory
+        for port_name in &lib.ports {
+            let target = port_targets.get(port_name).unwrap_or(&vcpkg_target);
+            let include_dir = target
+                .packages_path
+                .join(format!("{}_{}", port_name, target.target_triplet.name))
+                .join("include");
+            if include_dir.is_dir() {
+                if self.emit_port_includes {
+                    lib.cargo_metadata
+                        .push(format!("cargo:include={}", cargo_path(&include_dir)?));
+                }
+                lib.port_include_paths
+                    .insert(port_name.clone(), include_dir);
+            }
+        }
+        lib.cargo_metadata.extend(warnings);
+
+        self.emit_libs(
+            &mut lib,
+            &vcpkg_target,
+            &required_libs,
+            &required_dlls,
+            &lib_targets,
+            &dll_targets,
+        )
+        .map_err(|e| match e {
+            // the warnings are not printed on failure, so mention the likely cause
+            Error::LibNotFound {
+                port,
+                triplet,
+                path,
+                installed_triplets,
+                install_command,
+                detail,
+            } if tree_looks_stale => Error::LibNotFound {
+                port,
+                triplet,
+                path,
+                installed_triplets,
+                install_command,
+                detail: format!(
+                    "{} (the vcpkg tree may be partially upgraded, try reinstalling the ports)",
+                    detail
+                ),
+            },
+            e => e,
+        })?;
+
+        // the system libraries come after all of the vcpkg libraries that use them,
+        // starting with those from the .pc files
+        for port_name in lib.ports.clone() {
+            for name in &port_pc_libs[&port_name] {
+                if lib.system_libs.contains(name)
+                    || lib.found_names.iter().any(|n| n.eq_ignore_ascii_case(name))
+                {
+                    continue;
+                }
+                lib.cargo_metadata
+                    .push(format!("cargo:rustc-link-lib={}", name));
+                lib.system_libs.push(name.clone());
+            }
+        }
+        for port_name in lib.ports.clone() {
+            let target = port_targets.get(&port_name).unwrap_or(&vcpkg_target);
+            for name in usage::port_system_libs(target, &port_name) {
+                if lib.system_libs.contains(&name) || usage::is_port_lib(&name, &lib) {
+                    continue;
+                }
+                if self.emit_usage_libs {
+                    lib.cargo_metadata
+                        .push(format!("cargo:rustc-link-lib={}", name));
+                }
+                lib.system_libs.push(name);
+            }
+        }
+        Ok(Resolved {
+            lib,
+            vcpkg_target,
+            links_nothing: false,
+        })
+    }
+
+    /// Emit everything for the libraries that were found with `resolve` or
+    /// `probe`: bundle them, add the link arguments and the `links` metadata, copy
+    /// the DLLs, write the probe JSON and finally the metadata itself.
+    fn emit(&mut self, lib: &mut Library, vcpkg_target: &VcpkgTarget) -> Result<(), Error> {
+        if vcpkg_target.target_triplet.lib_suffix == "lib" {
+            self.emit_crt(lib);
+        }
+        if let Some(ref name) = self.bundle {
+            if vcpkg_target.target_triplet.is_static {
+                bundle::bundle_libs(lib, name, &vcpkg_target.target_triplet)?;
+            }
+        }
+        self.emit_link_args(lib, vcpkg_target)?;
+        if self.emit_links_metadata {
+            emit_links_metadata(lib, vcpkg_target)?;
+        }
+
+        if self.copy_dlls || self.copy_dlls_to_profile_dir {
+            self.do_dll_copy(lib, vcpkg_target)?;
+        }
+
+        if self.write_probe_json {
+            probe_json::write_probe_json(lib);
+        }
+        self.write_metadata(lib)
+    }
+
+    /// Write out the metadata of `lib`, if `cargo_metadata` is set.
+    fn write_metadata(&mut self, lib: &Library) -> Result<(), Error> {
+        if self.cargo_metadata {
+            for line in &lib.cargo_metadata {
+                self.write_line(line)?;
+            }
+        }
+        Ok(())
+    }
 
+    /// Add the include, library and DLL directories of `target` to `lib`.
+    fn add_target_dirs(&self, lib: &mut Library, target: &VcpkgTarget) -> Result<(), Error> {
         if self.emit_includes {
             lib.cargo_metadata.push(format!(
                 "cargo:include={}",
-                vcpkg_target.include_path.display()
+                cargo_path(&target.include_path)?
             ));
         }
-        lib.include_paths.push(vcpkg_target.include_path.clone());
+        lib.include_paths.push(target.include_path.clone());
 
+        // cargo takes the rest of the line as the path and passes it on as a single
+        // argument, so paths with spaces such as `C:\Program Files\vcpkg` are emitted
+        // as they are: quotes would become part of the path
         lib.cargo_metadata.push(format!(
             "cargo:rustc-link-search=native={}",
-            vcpkg_target
-                .lib_path
-                .to_str()
-                .expect("failed to convert string type")
+            cargo_path(&target.lib_path)?
         ));
-        lib.link_paths.push(vcpkg_target.lib_path.clone());
-        if !vcpkg_target.target_triplet.is_static {
-            lib.cargo_metadata.push(format!(
-                "cargo:rustc-link-search=native={}",
-                vcpkg_target
-                    .bin_path
-                    .to_str()
-                    .expect("failed to convert string type")
-            ));
-            // this path is dropped by recent versions of cargo hence the copies to OUT_DIR below
-            lib.dll_paths.push(vcpkg_target.bin_path.clone());
+        lib.link_paths.push(target.lib_path.clone());
+        if !target.target_triplet.is_static {
+            let dll_dir = target.dll_dir();
+            if *dll_dir != target.lib_path {
+                lib.cargo_metadata.push(format!(
+                    "cargo:rustc-link-search=native={}",
+                    cargo_path(dll_dir)?
+                ));
+            }
+            // this path is dropped by recent versions of cargo hence the copies to OUT_DIR
+            lib.dll_paths.push(dll_dir.clone());
         }
+        Ok(())
+    }
 
-        lib.ports = required_port_order;
+    /// Find all of the packages listed in the `[package.metadata.vcpkg]` table of the
+    /// `Cargo.toml` of the crate being built, using this configuration for each of them.
+    ///
+    /// The table has the format used by [cargo-vcpkg](https://crates.io/crates/cargo-vcpkg),
+    /// including per-target dependency lists:
+    ///
+    /// ```toml
+    /// [package.metadata.vcpkg]
+    /// dependencies = ["sdl2", "zlib"]
+    ///
+    /// [package.metadata.vcpkg.target]
+    /// x86_64-pc-windows-msvc = { dependencies = ["sdl2", "zlib", "openssl"] }
+    /// ```
+    ///
+    /// The libraries are returned in the order the packages are listed.
+    pub fn find_all(&mut self) -> Result<Vec<Library>, Error> {
+        let manifest_dir = env::var_os(CARGO_MANIFEST_DIR)
+            .ok_or_else(|| Error::RequiredEnvMissing(CARGO_MANIFEST_DIR.to_owned()))?;
+        self.find_all_in(&Path::new(&manifest_dir).join("Cargo.toml"))
+    }
 
-        self.emit_libs(&mut lib, &vcpkg_target)?;
+    /// Like `find_all`, reading the packages from the `Cargo.toml` at `manifest_path`.
+    pub fn find_all_in(&mut self, manifest_path: &Path) -> Result<Vec<Library>, Error> {
+        let manifest = fs::read_to_string(manifest_path).map_err(|e| {
+            Error::InvalidMetadata(format!("could not read {}: {}", manifest_path.display(), e))
+        })?;
+        let target = env::var(TARGET).unwrap_or(String::new());
+        let packages = metadata::vcpkg_dependencies(&manifest, &target)
+            .map_err(|e| Error::InvalidMetadata(format!("{}: {}", manifest_path.display(), e)))?;
+
+        let mut libs = Vec::new();
+        for package in packages {
+            libs.push(self.find_package(&package)?);
+        }
+        Ok(libs)
+    }
+
+    /// Define whether metadata should be emitted for cargo allowing it to
+    /// automatically link the binary. Defaults to `true`.
+    pub fn cargo_metadata(&mut self, cargo_metadata: bool) -> &mut Config {
+        self.cargo_metadata = cargo_metadata;
+        self
+    }
+
+    /// Write the cargo metadata, and the messages about copied DLLs, to `writer`
+    /// rather than printing them to stdout, for tools that embed vcpkg-rs and
+    /// want to capture them. If `writer` fails, finding the package fails with
+    /// `Error::Io`.
+    ///
+    /// ```rust,no_run
+    /// use std::fs::File;
+    ///
+    /// let metadata = File::create("vcpkg-metadata.txt").unwrap();
+    /// vcpkg::Config::new()
+    ///     .metadata_writer(metadata)
+    ///     .find_package("zlib")
+    ///     .unwrap();
+    /// ```
+    pub fn metadata_writer<W>(&mut self, writer: W) -> &mut Config
+    where
+        W: Write + Send + 'static,
+    {
+        self.metadata_writer = Some(Box::new(writer));
+        self
+    }
 
-        if self.copy_dlls {
-            self.do_dll_copy(&mut lib)?;
+    /// The features of a port being found whose dependencies and libraries are
+    /// linked, or `None` for all of its installed features. Selecting `"core"`
+    /// turns off the default features, as it does for `vcpkg install`.
+    fn selected_features(&self, port: &Port) -> Option<Vec<String>> {
+        if self.features.is_empty() && self.default_features {
+            return None;
+        }
+        let mut features = self.features.clone();
+        if self.default_features && !features.iter().any(|feature| feature == "core") {
+            for feature in &port.default_features {
+                if port.features.contains_key(feature) && !features.contains(feature) {
+                    features.push(feature.clone());
+                }
+            }
         }
+        Some(features)
+    }
 
-        if self.cargo_metadata {
-            for line in &lib.cargo_metadata {
+    /// Write a line of output to the metadata writer, or print it to stdout if
+    /// there is none.
+    fn write_line(&mut self, line: &str) -> Result<(), Error> {
+        match self.metadata_writer {
+            Some(ref mut writer) => {
+                writeln!(writer, "{}", line).map_err(|e| Error::io(PathBuf::new(), e))
+            }
+            None => {
                 println!("{}", line);
+                Ok(())
             }
         }
-        Ok(lib)
     }
 
-    /// Define whether metadata should be emitted for cargo allowing it to
-    /// automatically link the binary. Defaults to `true`.
-    pub fn cargo_metadata(&mut self, cargo_metadata: bool) -> &mut Config {
-        self.cargo_metadata = cargo_metadata;
+    /// Link the debug variants of the libraries, which vcpkg installs under
+    /// `installed/<triplet>/debug`, when cargo runs the build script with
+    /// `PROFILE=debug`. With MSVC this avoids mixing the debug and release C
+    /// runtimes when C code of the crate is also built in debug mode. Release
+    /// builds always use the release libraries. Defaults to `false`.
+    ///
+    /// The DLLs are taken from `installed/<triplet>/debug/bin` too, so the ones
+    /// copied to OUT_DIR match the import libraries. `Library::is_debug` tells
+    /// which were used, as release-only triplets have no debug variants.
+    pub fn use_debug_profile(&mut self, use_debug_profile: bool) -> &mut Config {
+        self.use_debug_profile = use_debug_profile;
         self
     }
 
@@ -240,13 +1360,220 @@ impl Config {
         self
     }
 
+    /// Emit `cargo:include` metadata for the include directory of each port in the
+    /// `packages/<port>_<triplet>` directory vcpkg built it into, for ports whose
+    /// headers are laid out differently there than in the merged
+    /// `installed/<triplet>/include`. The directories are recorded in
+    /// `Library::port_include_paths` regardless. Defaults to `false`.
+    pub fn emit_port_includes(&mut self, emit_port_includes: bool) -> &mut Config {
+        self.emit_port_includes = emit_port_includes;
+        self
+    }
+
+    /// Emit `cargo:root`, `cargo:include`, `cargo:lib` and `cargo:libs` metadata
+    /// for a crate with a `links = "foo"` key, which cargo passes to the build
+    /// scripts of dependent crates as `DEP_FOO_ROOT`, `DEP_FOO_INCLUDE`,
+    /// `DEP_FOO_LIB` and `DEP_FOO_LIBS`, like crates using pkg-config or cmake do.
+    ///
+    /// `root` is the `installed/<triplet>` directory, `include` and `lib` the
+    /// directories of the headers and libraries (include directories are joined
+    /// like `PATH`), and `libs` the comma separated names of the libraries that
+    /// were linked. Defaults to `false`.
+    pub fn emit_links_metadata(&mut self, emit_links_metadata: bool) -> &mut Config {
+        self.emit_links_metadata = emit_links_metadata;
+        self
+    }
+
+    /// Emit `cargo:rerun-if-changed` metadata for the vcpkg status database and
+    /// the manifests of the ports that were found, see `find_package`. Turning it
+    /// off leaves cargo to rerun the build script whenever a file in the package
+    /// changes, as it does for a build script without any `rerun-if-changed`
+    /// metadata. Defaults to `true`.
+    pub fn emit_rerun_if_changed(&mut self, emit_rerun_if_changed: bool) -> &mut Config {
+        self.emit_rerun_if_changed = emit_rerun_if_changed;
+        self
+    }
+
+    /// Define what happens to dependencies that are also provided by a Rust `-sys` crate
+    /// in the build, such as `zlib` and `libz-sys`. Only `-sys` crates that the crate
+    /// being built depends on directly can be detected, see `ConflictPolicy`. Defaults
+    /// to `ConflictPolicy::Ignore`, which does nothing.
+    pub fn conflict_policy(&mut self, conflict_policy: ConflictPolicy) -> &mut Config {
+        self.conflict_policy = conflict_policy;
+        self
+    }
+
+    /// Remove default libraries such as `libcmt` from the link by emitting
+    /// `cargo:rustc-link-arg=/NODEFAULTLIB:<name>` for each of them.
+    ///
+    /// This is a way to resolve link errors caused by vcpkg libraries that were built
+    /// against a different C runtime than the one rustc is using, see `inspect_crt`.
+    /// It only has an effect for MSVC triplets.
+    pub fn suppress_default_libs(&mut self, default_libs: &[&str]) -> &mut Config {
+        self.suppressed_default_libs
+            .extend(default_libs.iter().map(|&s| s.to_owned()));
+        self
+    }
+
+    /// Read the `/DEFAULTLIB` directives of the MSVC libraries that are found to
+    /// fill in `Library::crt_demands`, and emit a `cargo:warning` listing those
+    /// that request a different C runtime than the one rustc links. Defaults to
+    /// `false`.
+    ///
+    /// Only the headers and the `.drectve` sections of the objects in each
+    /// library are read, but this still opens every library that is found.
+    pub fn inspect_crt(&mut self, inspect_crt: bool) -> &mut Config {
+        self.inspect_crt = inspect_crt;
+        self
+    }
+
+    /// Merge the static libraries that were found into a single archive named
+    /// `name` in OUT_DIR and link only that archive.
+    ///
+    /// The archive is created with `lib.exe` for MSVC triplets, `libtool` for
+    /// Apple triplets and `ar` (or the archiver named by `AR`) otherwise. This
+    /// has no effect for dynamic triplets. Defaults to `None`.
+    ///
+    /// `lib.exe` is taken from `VCPKGRS_LIB` or the `PATH`, or with the `cc`
+    /// feature it is found in the Visual Studio installation like `cc` finds the
+    /// compiler. Otherwise finding the package fails with `Error::CommandFailed`.
+    pub fn bundle(&mut self, name: &str) -> &mut Config {
+        self.bundle = Some(name.to_owned());
+        self
+    }
+
+    /// Link to libraries by their exact file names, such as `libfoo.a`, rather than
+    /// by a name derived from the file name. Defaults to `false`.
+    ///
+    /// This emits `cargo:rustc-link-lib=static:+verbatim=libfoo.a`, which becomes
+    /// `-l:libfoo.a` on the linker command line, and is useful for ports whose archives
+    /// do not follow the `lib<name>.a` convention. It requires Rust 1.61 or later
+    /// and only has an effect for triplets that strip the `lib` prefix, see
+    /// `strip_lib_prefix`. Archives whose names do not start with `lib` at all are
+    /// always linked this way, as the linker could not find them otherwise.
+    pub fn verbatim_link_names(&mut self, verbatim_link_names: bool) -> &mut Config {
+        self.verbatim_link_names = verbatim_link_names;
+        self
+    }
+
+    /// Give the kind of each library in the link metadata, as in
+    /// `cargo:rustc-link-lib=static=zlib` for a static triplet and
+    /// `cargo:rustc-link-lib=dylib=zlib` for a dynamic one, rather than leaving
+    /// it to rustc. This makes sure that the static library is linked when the
+    /// import library of a DLL of the same name is on the search path too.
+    /// Defaults to `false`.
+    pub fn explicit_link_kind(&mut self, explicit_link_kind: bool) -> &mut Config {
+        self.explicit_link_kind = explicit_link_kind;
+        self
+    }
+
+    /// Whether to take the `lib` prefix off the file names of libraries to get the
+    /// names they are linked with, such as `png16` for `libpng16.a`, as the linker
+    /// adds it back. By default this is done for all triplets other than the MSVC
+    /// ones, which link to `foo.lib` by the name `foo`. This is for custom triplets
+    /// whose toolchain does not follow the convention of their platform.
+    pub fn strip_lib_prefix(&mut self, strip_lib_prefix: bool) -> &mut Config {
+        self.strip_lib_prefix = Some(strip_lib_prefix);
+        self
+    }
+
+    /// Write the result of a successful probe, including the libraries, paths, port
+    /// versions and cargo metadata that was emitted, to `OUT_DIR/vcpkg-probe.json`
+    /// for use by external tools. Defaults to `false`.
+    pub fn write_probe_json(&mut self, write_probe_json: bool) -> &mut Config {
+        self.write_probe_json = write_probe_json;
+        self
+    }
+
+    /// Add a preprocessor define that C code using the libraries must be compiled
+    /// with, such as `CURL_STATICLIB` for static builds of curl. The defines are
+    /// recorded in `Library::defines` and emitted as `cargo:defines=NAME,NAME=VALUE`,
+    /// which cargo passes to the build scripts of dependent crates as
+    /// `DEP_<links>_DEFINES` when the crate has a `links` key.
+    ///
+    /// The `-D` flags in the `Cflags` of the pkg-config files installed by the
+    /// ports are added too, unless a define of the same name was given here.
+    pub fn define(&mut self, name: &str, value: Option<&str>) -> &mut Config {
+        self.defines
+            .push((name.to_owned(), value.map(|value| value.to_owned())));
+        self
+    }
+
+    /// Pass an argument to the linker when linking to the libraries, such as
+    /// `/DELAYLOAD:zlib1.dll` or `-Wl,--allow-multiple-definition`. The argument is
+    /// emitted as `cargo:rustc-link-arg=ARG` after the libraries are linked.
+    ///
+    /// `{lib_dir}`, `{dll_dir}` and `{include_dir}` in the argument are replaced with
+    /// the directories the libraries were found in, for example
+    /// `-Wl,-rpath,{lib_dir}` for dynamic libraries on Linux.
+    pub fn link_arg(&mut self, arg: &str) -> &mut Config {
+        self.link_args.push(arg.to_owned());
+        self
+    }
+
+    /// Add the directories of the shared libraries to the rpath of the binaries
+    /// linked to them, so that executables and tests run from the target directory
+    /// find them without `LD_LIBRARY_PATH` or `DYLD_LIBRARY_PATH`. Only the dynamic
+    /// triplets other than the Windows ones, such as `x64-linux-dynamic`, have an
+    /// rpath; by default none is added.
+    ///
+    /// `Rpath::Origin` adds paths relative to the binaries, which only work as long
+    /// as the target directory and the vcpkg tree stay where they are relative to
+    /// each other.
+    pub fn rpath(&mut self, rpath: Rpath) -> &mut Config {
+        self.rpath = Some(rpath);
+        self
+    }
+
     /// Should DLLs be copied to OUT_DIR?
-    /// Defaults to `true`.
+    /// Defaults to `true`. The shared libraries of dynamic triplets on other
+    /// platforms, such as `x64-linux-dynamic`, are never copied there.
     pub fn copy_dlls(&mut self, copy_dlls: bool) -> &mut Config {
         self.copy_dlls = copy_dlls;
         self
     }
 
+    /// Copy the DLLs, the default, or make hard or symbolic links to them in the
+    /// vcpkg tree instead. A DLL is copied if the link can't be made.
+    pub fn dll_copy_mode(&mut self, dll_copy_mode: CopyMode) -> &mut Config {
+        self.dll_copy_mode = dll_copy_mode;
+        self
+    }
+
+    /// Should DLLs also be copied next to the executables that cargo builds, in
+    /// `target/<profile>` and `target/<profile>/deps`, so that `cargo run` and
+    /// `cargo test` find them without adding the vcpkg tree to `PATH`? The
+    /// directories are found by going up from OUT_DIR. Defaults to `false`.
+    ///
+    /// Use `copy_dlls(false)` as well to only copy them there.
+    pub fn copy_dlls_to_profile_dir(&mut self, copy_dlls_to_profile_dir: bool) -> &mut Config {
+        self.copy_dlls_to_profile_dir = copy_dlls_to_profile_dir;
+        self
+    }
+
+    /// Only copy the DLLs for which `filter` returns `true` to OUT_DIR, for
+    /// example to avoid copying all of a large closure such as qt on every
+    /// build. The DLLs that are not copied are still listed in
+    /// `Library::found_dlls`.
+    ///
+    /// ```rust,no_run
+    /// let needed = ["Qt5Core.dll", "Qt5Gui.dll"];
+    /// vcpkg::Config::new()
+    ///     .copy_dlls_filter(move |dll| {
+    ///         let name = dll.file_name().unwrap().to_string_lossy();
+    ///         needed.iter().any(|needed| name.eq_ignore_ascii_case(needed))
+    ///     })
+    ///     .find_package("qt5-base")
+    ///     .unwrap();
+    /// ```
+    pub fn copy_dlls_filter<F>(&mut self, filter: F) -> &mut Config
+    where
+        F: Fn(&Path) -> bool + Send + Sync + 'static,
+    {
+        self.copy_dlls_filter = Some(Box::new(filter));
+        self
+    }
+
     /// Define which path to use as vcpkg root overriding the VCPKG_ROOT environment variable
     /// Default to `None`, which means use VCPKG_ROOT or try to find out automatically
     pub fn vcpkg_root(&mut self, vcpkg_root: PathBuf) -> &mut Config {
@@ -254,6 +1581,218 @@ impl Config {
         self
     }
 
+    /// Look for the installed packages in `installed_root` rather than in the
+    /// `installed` directory of the vcpkg root, as vcpkg does when given
+    /// `--x-install-root`. This overrides the `VCPKG_INSTALLED_DIR` environment
+    /// variable. Defaults to `None`.
+    ///
+    /// The vcpkg root is not needed to find packages in such a directory.
+    pub fn installed_root(&mut self, installed_root: PathBuf) -> &mut Config {
+        self.installed_root = Some(installed_root);
+        self
+    }
+
+    /// Treat the vcpkg root as a tree made by `vcpkg export --raw`, which only
+    /// holds the `installed` directory, rather than a complete vcpkg checkout.
+    /// Defaults to `false`.
+    ///
+    /// Exported trees are recognized without this when they contain the status
+    /// database at `installed/vcpkg/status`, as those made by recent versions of
+    /// vcpkg do.
+    pub fn export_tree(&mut self, export_tree: bool) -> &mut Config {
+        self.export_tree = export_tree;
+        self
+    }
+
+    /// Link to the system libraries, such as `ws2_32` or `bcrypt`, that the
+    /// `share/<port>/usage` files of the ports ask for. Defaults to `false`.
+    ///
+    /// The libraries are recorded in `Library::system_libs` either way. Usage
+    /// files are free text, so they are only read for the plain library names
+    /// given to `target_link_libraries`, `-lfoo` flags and `foo.lib` file names.
+    /// Names that are libraries of the ports themselves once the case, a `lib`
+    /// prefix and the file extension are set aside, such as `CURL` for
+    /// `libcurl.lib`, are left out.
+    pub fn emit_usage_libs(&mut self, emit_usage_libs: bool) -> &mut Config {
+        self.emit_usage_libs = emit_usage_libs;
+        self
+    }
+
+    /// Use the vcpkg triplet `triplet` when building for the rust target `rust_target`,
+    /// for example `riscv64-linux` for `riscv64gc-unknown-linux-gnu`, which has no
+    /// default triplet, or a custom triplet for a target that has one.
+    ///
+    /// The mapping can also be given in the `VCPKGRS_TARGET_MAP` environment variable
+    /// as `rust-target=triplet` entries separated by commas or semicolons. A triplet
+    /// chosen with `target_triplet` or `VCPKGRS_TRIPLET` takes precedence.
+    pub fn map_target<S: AsRef<str>>(&mut self, rust_target: &str, triplet: S) -> &mut Config {
+        self.target_map
+            .insert(rust_target.to_owned(), triplet.as_ref().to_owned());
+        self
+    }
+
+    /// For musl targets such as `x86_64-unknown-linux-musl`, which have no vcpkg
+    /// triplet, use the static triplet of the corresponding glibc target, such as
+    /// `x64-linux`, with a cargo warning. Static libraries built against glibc
+    /// often link fine with musl, but not always. Defaults to `false`, and can also
+    /// be turned on by setting `VCPKGRS_MUSL_FALLBACK`.
+    pub fn allow_musl_fallback(&mut self, allow_musl_fallback: bool) -> &mut Config {
+        self.allow_musl_fallback = allow_musl_fallback;
+        self
+    }
+
+    /// Link to the libraries named by `-l` flags in the `Libs` of the pkg-config
+    /// files of the ports that are not installed by vcpkg, such as `pthread`,
+    /// `dl` or `ws2_32`. Defaults to `false`.
+    ///
+    /// The libraries are recorded in `Library::system_libs`. The `Libs.private`
+    /// of static triplets are linked regardless of this setting.
+    pub fn emit_system_libs(&mut self, emit_system_libs: bool) -> &mut Config {
+        self.emit_system_libs = emit_system_libs;
+        self
+    }
+
+    /// Emit a `cargo:warning` whenever vcpkg-rs has to guess how to link a port,
+    /// such as when a port has no .pc files to order its libraries by, ordering
+    /// them by the .pc files was given up on, or a dependency of a port is not
+    /// installed. Defaults to `false`.
+    pub fn warn_on_fallback(&mut self, warn_on_fallback: bool) -> &mut Config {
+        self.warn_on_fallback = warn_on_fallback;
+        self
+    }
+
+    /// Only link to the dependencies of the package being found that its
+    /// installed feature `feature` needs, rather than those of all of its
+    /// installed features. May be called more than once to select several
+    /// features, and `"core"` selects none of them. The default features that
+    /// the package was installed with are selected as well unless `"core"` is
+    /// selected, see `default_features`.
+    ///
+    /// Finding the package fails if a selected feature is not installed. The
+    /// dependencies of the package are linked with all of their installed features.
+    /// With `find_packages` the features apply to each of the packages named.
+    pub fn feature(&mut self, feature: &str) -> &mut Config {
+        self.features.push(feature.to_owned());
+        self
+    }
+
+    /// Select several features of the package being found, see `feature`.
+    pub fn features(&mut self, features: &[&str]) -> &mut Config {
+        for feature in features {
+            self.feature(feature);
+        }
+        self
+    }
+
+    /// Whether the default features that the package being found was installed
+    /// with are linked along with those selected with `feature`, as in a vcpkg
+    /// manifest. Defaults to true.
+    ///
+    /// With `default_features(false)` only the selected features are linked, and
+    /// if none are selected only the port without any of its optional features,
+    /// so that a port installed with many features, such as ffmpeg or opencv, can
+    /// be linked with the fewest dependencies. When no features are selected and
+    /// the default features are on, all of the installed features are linked.
+    pub fn default_features(&mut self, default_features: bool) -> &mut Config {
+        self.default_features = default_features;
+        self
+    }
+
+    /// Link the libraries that the ports being found install in `lib/manual-link`,
+    /// which are skipped by default. vcpkg puts libraries there that only some
+    /// consumers want, such as `SDL2main`, which provides the `main` function.
+    /// Defaults to false.
+    pub fn include_manual_link(&mut self, include_manual_link: bool) -> &mut Config {
+        self.include_manual_link = include_manual_link;
+        self
+    }
+
+    /// Link the library `name` from `lib/manual-link`, without the others there,
+    /// see `include_manual_link`. The name is the one the library is linked with,
+    /// such as `SDL2main`. Finding the package fails if neither it nor one of the
+    /// ports it depends on installs the library.
+    pub fn manual_link_lib(&mut self, name: &str) -> &mut Config {
+        self.manual_link_libs.push(name.to_owned());
+        self
+    }
+
+    /// Link the libraries that the ports being found install in the given
+    /// subdirectories of `lib`, such as `ossl-modules` or `engines-3` of openssl,
+    /// after their other libraries, and add the subdirectories to the link search
+    /// path. Only the libraries directly in a subdirectory are linked.
+    pub fn extra_lib_subdirs(&mut self, subdirs: &[&str]) -> &mut Config {
+        for subdir in subdirs {
+            let subdir = subdir.trim_matches('/').to_owned();
+            if !self.extra_lib_subdirs.contains(&subdir) {
+                self.extra_lib_subdirs.push(subdir);
+            }
+        }
+        self
+    }
+
+    /// Run `vcpkg install <port>:<triplet>` with the vcpkg executable in the vcpkg
+    /// root if a package can't be found, and then look for it again. Defaults to
+    /// `false`.
+    ///
+    /// This is tried before the binary cache, see `binary_cache`.
+    pub fn install_missing(&mut self, install_missing: bool) -> &mut Config {
+        self.install_missing = install_missing;
+        self
+    }
+
+    /// Restore ports that are not installed in the vcpkg tree, and the ports they
+    /// depend on, from the vcpkg binary cache into OUT_DIR and link to them from
+    /// there. Defaults to `false`.
+    ///
+    /// This lets a CI build that restores the binary cache work without running
+    /// `vcpkg install` first, and without a vcpkg tree at all. The cache is read
+    /// from `VCPKG_DEFAULT_BINARY_CACHE` or the default location used by vcpkg,
+    /// and the archives are extracted with `unzip` or `tar`. What each archive
+    /// contains is remembered in OUT_DIR, and the ports are only extracted again
+    /// when the archives change. If the cache has a port but not one of its
+    /// dependencies, an `Error::VcpkgInstallation` is returned.
+    pub fn binary_cache(&mut self, binary_cache: bool) -> &mut Config {
+        self.binary_cache = binary_cache;
+        self
+    }
+
+    /// Clone vcpkg at `commit` into the cargo target directory and build it, if
+    /// no vcpkg installation is found. See the `bootstrap` module.
+    #[cfg(feature = "bootstrap")]
+    pub fn bootstrap(&mut self, commit: &str) -> &mut Config {
+        self.bootstrap_commit = Some(commit.to_owned());
+        self
+    }
+
+    /// Find the package `port_name` and its dependencies in the vcpkg triplet
+    /// `triplet` rather than the one used for other packages, for example to take
+    /// `icu` from `x64-windows-static-md` while the other packages found by the
+    /// build script come from a custom triplet. `VCPKGRS_TRIPLET_<PACKAGE>`
+    /// takes precedence.
+    ///
+    /// Unlike `port_triplet`, this applies to the package being found, so the
+    /// libraries of its dependencies are taken from `triplet` too.
+    pub fn package_triplet<S: AsRef<str>>(&mut self, port_name: &str, triplet: S) -> &mut Config {
+        self.package_triplets
+            .insert(port_name.to_owned(), triplet.as_ref().into());
+        self
+    }
+
+    /// Take the libraries of the port `port_name` from the vcpkg triplet `triplet`
+    /// rather than the triplet used for the rest of the ports, for example when a
+    /// port is only installed as a static library. Its dependencies are still
+    /// taken from the main triplet unless they are overridden too.
+    ///
+    /// The triplets must be for the same architecture and operating system, and
+    /// on Windows use the same C runtime linkage, otherwise `find_package` fails
+    /// with `Error::IncompatibleTriplet`. A dynamic `triplet` needs `VCPKGRS_DYNAMIC`
+    /// to be set like a dynamic main triplet does.
+    pub fn port_triplet<S: AsRef<str>>(&mut self, port_name: &str, triplet: S) -> &mut Config {
+        self.port_triplets
+            .insert(port_name.to_owned(), triplet.as_ref().into());
+        self
+    }
+
     /// Specify target triplet. When triplet is not specified, inferred triplet from rust target is used.
     ///
     /// Specifying a triplet using `target_triplet` will override the default triplet for this crate. This
@@ -265,6 +1804,43 @@ impl Config {
         self
     }
 
+    /// Choose between the static and the dynamic default triplet for the rust
+    /// target, and whether to look in the other one when the packages are not
+    /// installed in the first, see `LinkagePreference`. Allowing the dynamic
+    /// triplet here opts in to dynamic linking as `VCPKGRS_DYNAMIC` does.
+    ///
+    /// By default the static triplet is used, unless `VCPKGRS_DYNAMIC` is set.
+    pub fn linkage_preference(&mut self, linkage_preference: LinkagePreference) -> &mut Config {
+        self.linkage_preference = Some(linkage_preference);
+        self
+    }
+
+    /// Triplets to find the packages in, in order, when they are not installed
+    /// for the triplet that would otherwise be used, rather than taking them from
+    /// `VCPKGRS_TRIPLET_FALLBACKS`. `Library::vcpkg_triplet` is the triplet that
+    /// the packages were found in.
+    ///
+    /// ```no_run
+    /// let lib = vcpkg::Config::new()
+    ///     .triplet_fallbacks(&["x64-windows-static-md", "x64-windows-static"])
+    ///     .find_package("zlib")
+    ///     .unwrap();
+    /// ```
+    pub fn triplet_fallbacks(&mut self, triplets: &[&str]) -> &mut Config {
+        self.triplet_fallbacks
+            .extend(triplets.iter().map(|triplet| VcpkgTriplet::from(*triplet)));
+        self
+    }
+
+    /// Specify the host triplet, which vcpkg builds the tools that ports are built
+    /// with for, rather than taking it from `VCPKG_DEFAULT_HOST_TRIPLET` or inferring
+    /// it from the rust host. Dependencies that are qualified with the host triplet
+    /// in the status database are tools, which are not linked.
+    pub fn host_triplet<S: AsRef<str>>(&mut self, triplet: S) -> &mut Config {
+        self.host = Some(triplet.into());
+        self
+    }
+
     /// Find the library `port_name` in a Vcpkg tree.
     ///
     /// This will use all configuration previously set to select the
@@ -276,7 +1852,7 @@ impl Config {
 
         // determine the target type, bailing out if it is not some
         // kind of msvc
-        let msvc_target = self.get_target_triplet()?;
+        let msvc_target = self.get_package_triplet(port_name)?;
 
         // bail out if requested to not try at all
         if env::var_os(VCPKGRS_DISABLE).is_some() {
@@ -302,17 +1878,18 @@ impl Config {
 
         // if no overrides have been selected, then the Vcpkg port name
         // is the the .lib name and the .dll name
-        if self.required_libs.is_empty() {
-            self.required_libs.push(port_name.to_owned());
-            self.required_dlls.push(port_name.to_owned());
-        }
+        let (required_libs, required_dlls) = if self.required_libs.is_empty() {
+            (vec![port_name.to_owned()], vec![port_name.to_owned()])
+        } else {
+            (self.required_libs.clone(), self.required_dlls.clone())
+        };
 
         let vcpkg_target = find_vcpkg_target(&self, &msvc_target)?;
 
         // require explicit opt-in before using dynamically linked
         // variants, otherwise cargo install of various things will
         // stop working if Vcpkg is installed.
-        if !vcpkg_target.target_triplet.is_static && !env::var_os(VCPKGRS_DYNAMIC).is_some() {
+        if !vcpkg_target.target_triplet.is_static && !dynamic_allowed(&[port_name]) {
             return Err(Error::RequiredEnvMissing(VCPKGRS_DYNAMIC.to_owned()));
         }
 
@@ -320,122 +1897,289 @@ impl Config {
             vcpkg_target.target_triplet.is_static,
             &vcpkg_target.target_triplet.name,
         );
+        lib.is_debug = vcpkg_target.is_debug;
+        lib.triplet_settings = Some(self.triplet_settings(&msvc_target.name));
+
+        self.add_target_dirs(&mut lib, &vcpkg_target)?;
+        self.emit_defines(&mut lib, &[]);
+
+        self.emit_libs(
+            &mut lib,
+            &vcpkg_target,
+            &required_libs,
+            &required_dlls,
+            &BTreeMap::new(),
+            &BTreeMap::new(),
+        )?;
+        self.emit(&mut lib, &vcpkg_target)?;
+        Ok(lib)
+    }
 
-        if self.emit_includes {
-            lib.cargo_metadata.push(format!(
-                "cargo:include={}",
-                vcpkg_target.include_path.display()
-            ));
-        }
-        lib.include_paths.push(vcpkg_target.include_path.clone());
-
-        lib.cargo_metadata.push(format!(
-            "cargo:rustc-link-search=native={}",
-            vcpkg_target
-                .lib_path
-                .to_str()
-                .expect("failed to convert string type")
-        ));
-        lib.link_paths.push(vcpkg_target.lib_path.clone());
-        if !vcpkg_target.target_triplet.is_static {
-            lib.cargo_metadata.push(format!(
-                "cargo:rustc-link-search=native={}",
-                vcpkg_target
-                    .bin_path
-                    .to_str()
-                    .expect("failed to convert string type")
-            ));
-            // this path is dropped by recent versions of cargo hence the copies to OUT_DIR below
-            lib.dll_paths.push(vcpkg_target.bin_path.clone());
+    /// Emit the defines given with `define` followed by `port_defines`, the
+    /// definitions from the Cflags of the .pc files of the ports.
+    fn emit_defines(&self, lib: &mut Library, port_defines: &[(String, Option<String>)]) {
+        let mut all_defines = self.defines.clone();
+        for define in port_defines {
+            if !all_defines.iter().any(|&(ref name, _)| *name == define.0) {
+                all_defines.push(define.clone());
+            }
         }
-
-        self.emit_libs(&mut lib, &vcpkg_target)?;
-
-        if self.copy_dlls {
-            self.do_dll_copy(&mut lib)?;
+        if all_defines.is_empty() {
+            return;
         }
+        let defines: Vec<String> = all_defines
+            .iter()
+            .map(|&(ref name, ref value)| match *value {
+                Some(ref value) => format!("{}={}", name, value),
+                None => name.clone(),
+            })
+            .collect();
+        lib.cargo_metadata
+            .push(format!("cargo:defines={}", defines.join(",")));
+        lib.defines = all_defines;
+    }
 
-        if self.cargo_metadata {
-            for line in &lib.cargo_metadata {
-                println!("{}", line);
+    /// Emit the arguments given with `link_arg`, filling in the directories of
+    /// `vcpkg_target`.
+    fn emit_link_args(&self, lib: &mut Library, vcpkg_target: &VcpkgTarget) -> Result<(), Error> {
+        let triplet = &vcpkg_target.target_triplet;
+        // DLLs are found on the PATH rather than through an rpath
+        match self.rpath {
+            Some(rpath) if !triplet.is_static && !triplet.is_windows() => {
+                let out_dir = env::var_os(OUT_DIR).map(PathBuf::from);
+                let profile_dir = out_dir.as_ref().and_then(|out_dir| profile_dir(out_dir));
+                let is_macos = triplet.lib_suffix == "dylib";
+                for entry in rpath::rpath_entries(
+                    rpath,
+                    &lib.dll_paths,
+                    profile_dir.as_ref().map(PathBuf::as_path),
+                    is_macos,
+                ) {
+                    lib.cargo_metadata.push(format!(
+                        "cargo:rustc-link-arg=-Wl,-rpath,{}",
+                        cargo_path(&entry)?
+                    ));
+                }
             }
+            _ => {}
         }
-        Ok(lib)
+        for arg in &self.link_args {
+            let arg = arg
+                .replace("{lib_dir}", cargo_path(&vcpkg_target.lib_path)?)
+                .replace("{dll_dir}", cargo_path(vcpkg_target.dll_dir())?)
+                .replace("{include_dir}", cargo_path(&vcpkg_target.include_path)?);
+            lib.cargo_metadata
+                .push(format!("cargo:rustc-link-arg={}", arg));
+        }
+        Ok(())
     }
 
-    fn emit_libs(&mut self, lib: &mut Library, vcpkg_target: &VcpkgTarget) -> Result<(), Error> {
-        for required_lib in &self.required_libs {
+    /// Link to `required_libs` and find `required_dlls`, failing if any of them are
+    /// missing. `lib_targets` and `dll_targets` give the targets of the libraries
+    /// and DLLs that are taken from other triplets, see `port_triplet`.
+    fn emit_libs(
+        &self,
+        lib: &mut Library,
+        vcpkg_target: &VcpkgTarget,
+        required_libs: &[String],
+        required_dlls: &[String],
+        lib_targets: &BTreeMap<String, &VcpkgTarget>,
+        dll_targets: &BTreeMap<String, &VcpkgTarget>,
+    ) -> Result<(), Error> {
+        verbose::log(format_args!(
+            "linking libraries in order: {:?}",
+            required_libs
+        ));
+        for required_lib in required_libs {
             // this could use static-nobundle= for static libraries but it is apparently
             // not necessary to make the distinction for windows-msvc.
 
-            let link_name = match vcpkg_target.target_triplet.strip_lib_prefix {
-                true => required_lib.trim_left_matches("lib"),
-                false => required_lib,
+            let vcpkg_target = lib_targets
+                .get(required_lib)
+                .cloned()
+                .unwrap_or(vcpkg_target);
+            // the debug variant may be named zlibd.lib rather than zlib.lib
+            let required_lib = &vcpkg_target.debug_lib_stem(required_lib);
+            let file_name = vcpkg_target.lib_file_name(required_lib);
+            // libraries in lib/manual-link are found in their own directory
+            let file_name = match file_name.rfind('/') {
+                Some(pos) => file_name[pos + 1..].to_owned(),
+                None => file_name,
             };
-
-            lib.cargo_metadata
-                .push(format!("cargo:rustc-link-lib={}", link_name));
-
-            lib.found_names.push(String::from(link_name));
+            let (required_lib, lib_dir) = match required_lib.find('/') {
+                Some(pos) => (
+                    &required_lib[pos + 1..],
+                    vcpkg_target.lib_path.join(&required_lib[..pos]),
+                ),
+                None => (&required_lib[..], vcpkg_target.lib_path.clone()),
+            };
+            if !lib.link_paths.contains(&lib_dir) {
+                lib.cargo_metadata.push(format!(
+                    "cargo:rustc-link-search=native={}",
+                    cargo_path(&lib_dir)?
+                ));
+                lib.link_paths.push(lib_dir.clone());
+            }
+            let link_name = vcpkg_target.link_name(required_lib);
+            let link_name = &link_name[..];
+
+            // static archives that mingw dynamic triplets install next to the import
+            // libraries are linked statically, so that the linker takes them
+            let is_static_archive = vcpkg_target.is_static_archive(&file_name);
+
+            // a versioned shared object can only be linked by its file name
+            let verbatim = self.verbatim_link_names
+                || is_versioned_shared_lib(required_lib)
+                || vcpkg_target.needs_verbatim(required_lib);
+            if verbatim && vcpkg_target.target_triplet.strip_lib_prefix {
+                let kind = if vcpkg_target.target_triplet.is_static || is_static_archive {
+                    "static"
+                } else {
+                    "dylib"
+                };
+                lib.cargo_metadata.push(format!(
+                    "cargo:rustc-link-lib={}:+verbatim={}",
+                    kind, file_name
+                ));
+                lib.found_names.push(file_name.clone());
+            } else if is_static_archive {
+                lib.cargo_metadata
+                    .push(format!("cargo:rustc-link-lib=static={}", link_name));
+                lib.found_names.push(String::from(link_name));
+            } else if self.explicit_link_kind {
+                let kind = if vcpkg_target.target_triplet.is_static {
+                    "static"
+                } else {
+                    "dylib"
+                };
+                lib.cargo_metadata
+                    .push(format!("cargo:rustc-link-lib={}={}", kind, link_name));
+                lib.found_names.push(String::from(link_name));
+            } else {
+                lib.cargo_metadata
+                    .push(format!("cargo:rustc-link-lib={}", link_name));
+                lib.found_names.push(String::from(link_name));
+            }
 
             // verify that the library exists
-            let mut lib_location = vcpkg_target.lib_path.clone();
-            lib_location.push(required_lib.clone() + "." + &vcpkg_target.target_triplet.lib_suffix);
+            let lib_location = lib_dir.join(&file_name);
 
             if !lib_location.exists() {
-                return Err(Error::LibNotFound(lib_location.display().to_string()));
+                return Err(missing_file(
+                    lib,
+                    lib_location,
+                    &vcpkg_target.target_triplet.name,
+                ));
             }
             lib.found_libs.push(lib_location);
         }
-
-        if !vcpkg_target.target_triplet.is_static {
-            for required_dll in &self.required_dlls {
-                let mut dll_location = vcpkg_target.bin_path.clone();
-                dll_location.push(required_dll.clone() + ".dll");
-
-                // verify that the DLL exists
-                if !dll_location.exists() {
-                    return Err(Error::LibNotFound(dll_location.display().to_string()));
-                }
-                lib.found_dlls.push(dll_location);
+        rpath::detect_rpath_install_names(lib);
+
+        for required_dll in required_dlls {
+            let vcpkg_target = dll_targets
+                .get(required_dll)
+                .cloned()
+                .unwrap_or(vcpkg_target);
+            if vcpkg_target.target_triplet.is_static {
+                continue;
+            }
+            let dll_location =
+                vcpkg_target.dll_location(&vcpkg_target.debug_dll_stem(required_dll));
+
+            // verify that the DLL exists
+            if !dll_location.exists() {
+                return Err(missing_file(
+                    lib,
+                    dll_location,
+                    &vcpkg_target.target_triplet.name,
+                ));
             }
+            lib.found_dlls.push(dll_location);
         }
 
         Ok(())
     }
 
-    fn do_dll_copy(&mut self, lib: &mut Library) -> Result<(), Error> {
+    /// Report the C runtimes that the MSVC libraries of `lib` were built against,
+    /// if `inspect_crt` is set, and suppress the default libraries given with
+    /// `suppress_default_libs`.
+    fn emit_crt(&self, lib: &mut Library) {
+        if self.inspect_crt {
+            crt::inspect_libs(lib);
+            let crt_static = env::var(CARGO_CFG_TARGET_FEATURE)
+                .unwrap_or(String::new())
+                .contains("crt-static");
+            let warning =
+                crt::crt_warning(&lib.crt_demands, crt_static, &self.suppressed_default_libs);
+            lib.cargo_metadata.extend(warning);
+        }
+
+        for default_lib in &self.suppressed_default_libs {
+            lib.cargo_metadata.push(format!(
+                "cargo:rustc-link-arg=/NODEFAULTLIB:{}",
+                default_lib
+            ));
+        }
+    }
+
+    fn do_dll_copy(&mut self, lib: &mut Library, vcpkg_target: &VcpkgTarget) -> Result<(), Error> {
+        // shared objects are not looked for next to the executable, or in the
+        // directories cargo adds to LD_LIBRARY_PATH unless they are linked from there
+        let copy_to_out_dir = self.copy_dlls && vcpkg_target.target_triplet.is_windows();
         if let Some(target_dir) = env::var_os(OUT_DIR) {
             if !lib.found_dlls.is_empty() {
+                let mut dest_dirs = Vec::new();
+                if copy_to_out_dir {
+                    dest_dirs.push(PathBuf::from(&target_dir));
+                }
+                if self.copy_dlls_to_profile_dir {
+                    let profile_dir = profile_dir(Path::new(&target_dir)).ok_or_else(|| {
+                        Error::io(
+                            &target_dir,
+                            io::Error::new(
+                                io::ErrorKind::NotFound,
+                                "can't find the cargo profile directory above it",
+                            ),
+                        )
+                    })?;
+                    // deps may not have been created yet by the first build
+                    let _ = fs::create_dir_all(profile_dir.join("deps"));
+                    dest_dirs.push(profile_dir.join("deps"));
+                    dest_dirs.push(profile_dir);
+                }
+                let mut copied = Vec::new();
                 for file in &lib.found_dlls {
-                    let mut dest_path = Path::new(target_dir.as_os_str()).to_path_buf();
-                    dest_path.push(Path::new(file.file_name().unwrap()));
+                    if let Some(ref filter) = self.copy_dlls_filter {
+                        if !filter(file) {
+                            continue;
+                        }
+                    }
+                    for dest_dir in &dest_dirs {
+                        let dest_path = dest_dir.join(file.file_name().unwrap());
 
-                    fs::copy(file, &dest_path).map_err(|_| {
-                        Error::LibNotFound(format!(
-                            "Can't copy file {} to {}",
+                        copy_mode::copy_file(file, &dest_path, self.dll_copy_mode)
+                            .map_err(|e| Error::io(&dest_path, e))?;
+                        copied.push(format!(
+                            "vcpkg build helper copied {} to {}",
                             file.to_string_lossy(),
                             dest_path.to_string_lossy()
-                        ))
-                    })?;
-                    println!(
-                        "vcpkg build helper copied {} to {}",
-                        file.to_string_lossy(),
-                        dest_path.to_string_lossy()
-                    );
+                        ));
+                    }
+                }
+                for line in &copied {
+                    self.write_line(line)?;
+                }
+                if copy_to_out_dir {
+                    let target_dir = cargo_path(Path::new(&target_dir))?;
+                    lib.cargo_metadata
+                        .push(format!("cargo:rustc-link-search=native={}", target_dir));
+                    // work around https://github.com/rust-lang/cargo/issues/3957
+                    lib.cargo_metadata
+                        .push(format!("cargo:rustc-link-search={}", target_dir));
                 }
-                lib.cargo_metadata.push(format!(
-                    "cargo:rustc-link-search=native={}",
-                    env::var(OUT_DIR).unwrap()
-                ));
-                // work around https://github.com/rust-lang/cargo/issues/3957
-                lib.cargo_metadata.push(format!(
-                    "cargo:rustc-link-search={}",
-                    env::var(OUT_DIR).unwrap()
-                ));
             }
         } else {
-            return Err(Error::LibNotFound(format!("Unable to get {}", OUT_DIR)));
+            return Err(Error::RequiredEnvMissing(OUT_DIR.to_owned()));
         }
         Ok(())
     }
@@ -470,3 +2214,91 @@ impl Config {
         self
     }
 }
+
+/// The directory cargo puts the executables of the profile in, such as
+/// `target/debug`, given an OUT_DIR of the form `target/debug/build/<pkg>-<hash>/out`.
+fn profile_dir(out_dir: &Path) -> Option<PathBuf> {
+    let build_dir = out_dir.parent()?.parent()?;
+    if build_dir.file_name()? != "build" {
+        return None;
+    }
+    build_dir.parent().map(|dir| dir.to_path_buf())
+}
+
+/// Emit the metadata that `Config::emit_links_metadata` describes.
+fn emit_links_metadata(lib: &mut Library, vcpkg_target: &VcpkgTarget) -> Result<(), Error> {
+    if let Some(root) = vcpkg_target.include_path.parent() {
+        lib.cargo_metadata
+            .push(format!("cargo:root={}", cargo_path(root)?));
+    }
+    if let Ok(include) = env::join_paths(&lib.include_paths) {
+        let include = PathBuf::from(include);
+        lib.cargo_metadata
+            .push(format!("cargo:include={}", cargo_path(&include)?));
+    }
+    lib.cargo_metadata
+        .push(format!("cargo:lib={}", cargo_path(&vcpkg_target.lib_path)?));
+    lib.cargo_metadata
+        .push(format!("cargo:libs={}", lib.found_names.join(",")));
+    Ok(())
+}
+
+/// The error for a port that is not installed because vcpkg was interrupted while
+/// installing or removing it, if it was.
+fn broken_port(port_name: &str, target: &VcpkgTarget) -> Option<Error> {
+    let triplet = &target.target_triplet.name;
+    match port_statuses(&target.status_path, port_name).remove(triplet) {
+        Some(ref status) if is_broken_status(status) => Some(Error::BrokenPort {
+            port: port_name.to_owned(),
+            triplet: triplet.clone(),
+            status: status.clone(),
+        }),
+        _ => None,
+    }
+}
+
+/// The error for a library or DLL file of a port that is missing from the tree.
+fn missing_file(lib: &Library, path: PathBuf, triplet: &str) -> Error {
+    let port = lib
+        .port_libs
+        .iter()
+        .find(|&(_, libs)| libs.contains(&path))
+        .map(|(port, _)| port)
+        .or(lib.ports.first());
+    Error::LibNotFound {
+        port: port.cloned().unwrap_or_default(),
+        triplet: triplet.to_owned(),
+        detail: path.display().to_string(),
+        path: Some(path),
+        installed_triplets: Vec::new(),
+        install_command: None,
+    }
+}
+
+/// The error for a port that is not installed for the triplet of `target`, which
+/// mentions the triplets it is installed for, as that is the usual mistake.
+fn port_not_installed(port_name: &str, target: &VcpkgTarget) -> Error {
+    if let Some(err) = broken_port(port_name, target) {
+        return err;
+    }
+    let triplet = &target.target_triplet.name;
+    let installed_triplets = installed_triplets(&target.status_path, port_name);
+    let mut detail = format!(
+        "package {} is not installed for vcpkg triplet {}",
+        port_name, triplet
+    );
+    if !installed_triplets.is_empty() {
+        detail.push_str(&format!(
+            ", but is installed for triplets {}",
+            installed_triplets.join(", ")
+        ));
+    }
+    Error::LibNotFound {
+        port: port_name.to_owned(),
+        triplet: triplet.clone(),
+        path: None,
+        installed_triplets,
+        install_command: Some(format!("vcpkg install {}:{}", port_name, triplet)),
+        detail,
+    }
+}
@@ -0,0 +1,166 @@
+use std::env;
+use std::path::Path;
+
+/// What to do when a port in the dependency closure is also provided by a
+/// Rust `-sys` crate that is part of the build.
+///
+/// Linking the same native library twice, once from vcpkg and once from a
+/// `-sys` crate that built or found its own copy, is one of the most common
+/// causes of duplicate symbol errors.
+///
+/// A `-sys` crate is detected by the `DEP_<links>_*` variables that cargo sets
+/// for its metadata, and cargo only passes those to the build scripts of the
+/// crates that depend on it directly, so one that is further away in the
+/// dependency graph goes unnoticed. A `-sys` crate whose `DEP_<links>_ROOT` or
+/// `DEP_<links>_INCLUDE` is in the same vcpkg installed tree, such as one that
+/// found its library with vcpkg too, is not taken as a conflict.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Link the port anyway, but emit a `cargo:warning`.
+    Warn,
+    /// Do not link the libraries of a conflicting dependency. The port that
+    /// was asked for is always linked.
+    Skip,
+    /// Link the port without any diagnostics. This is the default.
+    Ignore,
+}
+
+impl Default for ConflictPolicy {
+    fn default() -> ConflictPolicy {
+        ConflictPolicy::Ignore
+    }
+}
+
+/// A vcpkg port that is frequently also provided by a `-sys` crate.
+pub(crate) struct KnownConflict {
+    /// vcpkg port name
+    pub(crate) port: &'static str,
+    /// the `links` key of the `-sys` crate, as it appears in `DEP_<links>_*`
+    pub(crate) links: &'static str,
+    /// name of the `-sys` crate
+    pub(crate) sys_crate: &'static str,
+}
+
+pub(crate) const KNOWN_CONFLICTS: &'static [KnownConflict] = &[
+    KnownConflict {
+        port: "zlib",
+        links: "Z",
+        sys_crate: "libz-sys",
+    },
+    KnownConflict {
+        port: "openssl",
+        links: "OPENSSL",
+        sys_crate: "openssl-sys",
+    },
+    KnownConflict {
+        port: "sqlite3",
+        links: "SQLITE3",
+        sys_crate: "libsqlite3-sys",
+    },
+    KnownConflict {
+        port: "curl",
+        links: "CURL",
+        sys_crate: "curl-sys",
+    },
+];
+
+impl KnownConflict {
+    /// Cargo sets `DEP_<links>_<key>` for every piece of metadata emitted by a
+    /// direct dependency with a `links` key, so any such variable means the
+    /// `-sys` crate is in the build, unless its root or include directory is in
+    /// the vcpkg installed tree `installed_path`.
+    fn is_detected(&self, installed_path: &Path) -> bool {
+        let prefix = format!("DEP_{}_", self.links);
+        let installed_path = installed_path
+            .canonicalize()
+            .unwrap_or_else(|_| installed_path.to_path_buf());
+        let mut detected = false;
+        for (key, value) in env::vars_os() {
+            let key = match key.to_str() {
+                Some(key) if key.starts_with(&prefix) => key[prefix.len()..].to_owned(),
+                _ => continue,
+            };
+            detected = true;
+            if key != "ROOT" && key != "INCLUDE" {
+                continue;
+            }
+            let from_vcpkg = env::split_paths(&value).any(|dir| {
+                dir.canonicalize()
+                    .unwrap_or(dir)
+                    .starts_with(&installed_path)
+            });
+            if from_vcpkg {
+                return false;
+            }
+        }
+        detected
+    }
+
+    pub(crate) fn warning(&self) -> String {
+        format!(
+            "cargo:warning=vcpkg port {} is also provided by the {} crate (DEP_{}_* is set), \
+             linking both may cause duplicate symbols",
+            self.port, self.sys_crate, self.links
+        )
+    }
+}
+
+/// Find a known conflict for `port` that is present in the current build and
+/// does not use the vcpkg installed tree `installed_path`.
+pub(crate) fn detect_conflict(port: &str, installed_path: &Path) -> Option<&'static KnownConflict> {
+    KNOWN_CONFLICTS
+        .iter()
+        .find(|conflict| conflict.port == port && conflict.is_detected(installed_path))
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use self::tempfile::tempdir;
+    use super::*;
+    use crate::tests::LOCK;
+
+    #[test]
+    fn detected_conflicts() {
+        let _g = LOCK.lock();
+        let tmp_dir = tempdir().unwrap();
+        let installed = tmp_dir.path().join("installed");
+        let include = installed.join("x64-linux").join("include");
+        let elsewhere = tmp_dir.path().join("libz-sys").join("include");
+
+        for &(var, value, conflict) in &[
+            ("DEP_Z_INCLUDE", Some(&elsewhere), Some("libz-sys")),
+            ("DEP_Z_ROOT", Some(&elsewhere), Some("libz-sys")),
+            ("DEP_Z_STATIC", None, Some("libz-sys")),
+            ("DEP_Z_INCLUDE", Some(&include), None),
+            ("DEP_OPENSSL_INCLUDE", Some(&elsewhere), None),
+        ] {
+            match value {
+                Some(value) => env::set_var(var, value),
+                None => env::set_var(var, "1"),
+            }
+            let found = detect_conflict("zlib", &installed).map(|conflict| conflict.sys_crate);
+            env::remove_var(var);
+            assert_eq!(found, conflict, "{}={:?}", var, value);
+        }
+        assert!(detect_conflict("zlib", &installed).is_none());
+
+        env::set_var("DEP_Z_INCLUDE", &elsewhere);
+        assert!(detect_conflict("bzip2", &installed).is_none());
+        env::remove_var("DEP_Z_INCLUDE");
+    }
+
+    #[test]
+    fn warning() {
+        let conflict = KNOWN_CONFLICTS
+            .iter()
+            .find(|c| c.port == "openssl")
+            .unwrap();
+        assert_eq!(
+            conflict.warning(),
+            "cargo:warning=vcpkg port openssl is also provided by the openssl-sys crate \
+             (DEP_OPENSSL_* is set), linking both may cause duplicate symbols"
+        );
+    }
+}
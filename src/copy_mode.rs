@@ -0,0 +1,56 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// How DLLs are put in OUT_DIR and the other directories they are copied to.
+///
+/// Linking rather than copying saves time and disk space for large ports such
+/// as ICU or Qt. If a link can't be made, for example because the directories
+/// are on different volumes or symbolic links are not permitted, the DLL is
+/// copied instead.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CopyMode {
+    /// Copy the DLLs. This is the default.
+    Copy,
+    /// Make hard links to the DLLs in the vcpkg tree.
+    Hardlink,
+    /// Make symbolic links to the DLLs in the vcpkg tree.
+    Symlink,
+}
+
+impl Default for CopyMode {
+    fn default() -> CopyMode {
+        CopyMode::Copy
+    }
+}
+
+/// Put `from` at `to` as `mode` says, falling back to copying it.
+pub(crate) fn copy_file(from: &Path, to: &Path, mode: CopyMode) -> io::Result<()> {
+    // a link from an earlier build can't be linked over, and copying onto it
+    // would write through it into the vcpkg tree
+    let _ = fs::remove_file(to);
+    let linked = match mode {
+        CopyMode::Copy => return fs::copy(from, to).map(|_| ()),
+        CopyMode::Hardlink => fs::hard_link(from, to),
+        CopyMode::Symlink => symlink(from, to),
+    };
+    linked.or_else(|_| fs::copy(from, to).map(|_| ()))
+}
+
+#[cfg(unix)]
+fn symlink(from: &Path, to: &Path) -> io::Result<()> {
+    ::std::os::unix::fs::symlink(from, to)
+}
+
+#[cfg(windows)]
+fn symlink(from: &Path, to: &Path) -> io::Result<()> {
+    ::std::os::windows::fs::symlink_file(from, to)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn symlink(_from: &Path, _to: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "symbolic links are not supported",
+    ))
+}
@@ -0,0 +1,376 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::str;
+
+use crate::Library;
+
+/// A C runtime library that an MSVC static or import library asks the linker
+/// for with a `/DEFAULTLIB` directive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Crt {
+    /// `libcmt.lib`, the static release CRT
+    StaticRelease,
+    /// `libcmtd.lib`, the static debug CRT
+    StaticDebug,
+    /// `msvcrt.lib`, the dynamic release CRT
+    DynamicRelease,
+    /// `msvcrtd.lib`, the dynamic debug CRT
+    DynamicDebug,
+}
+
+impl Crt {
+    /// The CRT that rustc links against for a given `crt-static` setting.
+    pub(crate) fn for_rust(crt_static: bool) -> Crt {
+        if crt_static {
+            Crt::StaticRelease
+        } else {
+            Crt::DynamicRelease
+        }
+    }
+
+    /// Name of the import or static library for this CRT, without extension.
+    pub fn lib_name(&self) -> &'static str {
+        match *self {
+            Crt::StaticRelease => "libcmt",
+            Crt::StaticDebug => "libcmtd",
+            Crt::DynamicRelease => "msvcrt",
+            Crt::DynamicDebug => "msvcrtd",
+        }
+    }
+
+    fn from_default_lib(name: &str) -> Option<Crt> {
+        let name = name.to_ascii_lowercase();
+        let name = name.trim_end_matches(".lib");
+        [
+            Crt::StaticRelease,
+            Crt::StaticDebug,
+            Crt::DynamicRelease,
+            Crt::DynamicDebug,
+        ]
+        .iter()
+        .cloned()
+        .find(|crt| crt.lib_name() == name)
+    }
+}
+
+/// Extract the libraries named by `/DEFAULTLIB:` (or `-defaultlib:`) linker
+/// directives in the contents of a `.drectve` section.
+///
+/// The section holds the directives as text separated by spaces, and the
+/// argument of a directive may be quoted.
+pub(crate) fn default_libs(directives: &[u8]) -> Vec<String> {
+    const DIRECTIVE: &'static str = "defaultlib:";
+
+    let text = String::from_utf8_lossy(directives);
+    let is_separator = |c: char| c.is_whitespace() || c == '\0' || c == '\u{feff}';
+    let mut libs = Vec::new();
+    let mut rest = &text[..];
+    loop {
+        rest = rest.trim_start_matches(is_separator);
+        if rest.is_empty() {
+            break;
+        }
+        // a directive runs to the next separator outside of quotes
+        let mut quoted = false;
+        let end = rest
+            .char_indices()
+            .find(|&(_, c)| {
+                if c == '"' {
+                    quoted = !quoted;
+                }
+                !quoted && is_separator(c)
+            })
+            .map(|(pos, _)| pos)
+            .unwrap_or(rest.len());
+        let directive = &rest[..end];
+        rest = &rest[end..];
+
+        let is_default_lib = (directive.starts_with('/') || directive.starts_with('-'))
+            && directive
+                .get(1..DIRECTIVE.len() + 1)
+                .map_or(false, |name| name.eq_ignore_ascii_case(DIRECTIVE));
+        if is_default_lib {
+            let lib = directive[DIRECTIVE.len() + 1..].trim_matches('"');
+            if !lib.is_empty() {
+                libs.push(lib.to_owned());
+            }
+        }
+    }
+    libs
+}
+
+fn u16_at(bytes: &[u8], pos: usize) -> u16 {
+    u16::from(bytes[pos]) | u16::from(bytes[pos + 1]) << 8
+}
+
+fn u32_at(bytes: &[u8], pos: usize) -> u32 {
+    u32::from(u16_at(bytes, pos)) | u32::from(u16_at(bytes, pos + 2)) << 16
+}
+
+/// Read the contents of the `.drectve` sections of the objects in a COFF
+/// archive, or of a single COFF object. Only the headers and those sections are
+/// read, so that large libraries are not read completely.
+pub(crate) fn drectve_sections<R: Read + Seek>(reader: &mut R) -> io::Result<Vec<Vec<u8>>> {
+    let len = reader.seek(SeekFrom::End(0))?;
+    let mut sections = Vec::new();
+    let mut magic = [0; 8];
+    if len >= 8 {
+        reader.seek(SeekFrom::Start(0))?;
+        reader.read_exact(&mut magic)?;
+    }
+    if &magic != b"!<arch>\n" {
+        object_drectve_sections(reader, 0, len, &mut sections)?;
+        return Ok(sections);
+    }
+
+    let mut offset = 8;
+    while offset + 60 <= len {
+        let mut header = [0; 60];
+        reader.seek(SeekFrom::Start(offset))?;
+        reader.read_exact(&mut header)?;
+        let size = match str::from_utf8(&header[48..58])
+            .ok()
+            .and_then(|size| size.trim().parse::<u64>().ok())
+        {
+            Some(size) => size,
+            None => break,
+        };
+        let start = offset + 60;
+        // the linker members and the long names member are not objects
+        let name = &header[..16];
+        if !name.starts_with(b"/ ") && !name.starts_with(b"//") && !name.starts_with(b"/<") {
+            object_drectve_sections(reader, start, size.min(len - start), &mut sections)?;
+        }
+        // members are aligned to two bytes
+        offset = start + size + (size & 1);
+    }
+    Ok(sections)
+}
+
+/// Append the `.drectve` sections of the COFF object of `size` bytes at `start`.
+fn object_drectve_sections<R: Read + Seek>(
+    reader: &mut R,
+    start: u64,
+    size: u64,
+    sections: &mut Vec<Vec<u8>>,
+) -> io::Result<()> {
+    let mut header = [0; 56];
+    let header_len = size.min(header.len() as u64) as usize;
+    if header_len < 20 {
+        return Ok(());
+    }
+    reader.seek(SeekFrom::Start(start))?;
+    reader.read_exact(&mut header[..header_len])?;
+
+    let (section_count, section_table) = if u16_at(&header, 0) == 0 && u16_at(&header, 2) == 0xffff
+    {
+        // import objects, version 0, have no sections, later versions are bigobj
+        if u16_at(&header, 4) == 0 || header_len < 56 {
+            return Ok(());
+        }
+        (u64::from(u32_at(&header, 44)), 56)
+    } else {
+        // the section table follows the optional header, which objects lack
+        (
+            u64::from(u16_at(&header, 2)),
+            20 + u64::from(u16_at(&header, 16)),
+        )
+    };
+
+    for index in 0..section_count {
+        let pos = section_table + index * 40;
+        if pos + 40 > size {
+            break;
+        }
+        let mut section = [0; 40];
+        reader.seek(SeekFrom::Start(start + pos))?;
+        reader.read_exact(&mut section)?;
+        if &section[..8] != b".drectve" {
+            continue;
+        }
+        let data_size = u64::from(u32_at(&section, 16));
+        let data_pos = u64::from(u32_at(&section, 20));
+        if data_pos + data_size > size {
+            continue;
+        }
+        let mut data = vec![0; data_size as usize];
+        reader.seek(SeekFrom::Start(start + data_pos))?;
+        reader.read_exact(&mut data)?;
+        sections.push(data);
+    }
+    Ok(())
+}
+
+/// Record the CRTs demanded by each of the found libraries, see
+/// `Config::inspect_crt`.
+pub(crate) fn inspect_libs(lib: &mut Library) {
+    for path in &lib.found_libs {
+        // a library that can't be read will fail at link time with a better error
+        let sections = match File::open(path).and_then(|mut file| drectve_sections(&mut file)) {
+            Ok(sections) => sections,
+            Err(_) => continue,
+        };
+        let mut crts: Vec<Crt> = sections
+            .iter()
+            .flat_map(|section| default_libs(section))
+            .filter_map(|name| Crt::from_default_lib(&name))
+            .collect();
+        crts.sort();
+        crts.dedup();
+        if !crts.is_empty() {
+            lib.crt_demands.insert(path.clone(), crts);
+        }
+    }
+}
+
+/// Produce a single `cargo:warning` line listing the libraries that demand a C
+/// runtime other than the one rustc will link, which also covers libraries that
+/// conflict with each other. Demands for libraries in `suppressed` have been
+/// dealt with by `/NODEFAULTLIB` and are not reported.
+pub(crate) fn crt_warning(
+    crt_demands: &BTreeMap<PathBuf, Vec<Crt>>,
+    crt_static: bool,
+    suppressed: &[String],
+) -> Option<String> {
+    let is_suppressed = |crt: &Crt| {
+        suppressed
+            .iter()
+            .any(|name| Crt::from_default_lib(name) == Some(*crt))
+    };
+
+    let rust_crt = Crt::for_rust(crt_static);
+    let mut mismatched = Vec::new();
+    for (path, crts) in crt_demands {
+        for crt in crts
+            .iter()
+            .filter(|&crt| *crt != rust_crt && !is_suppressed(crt))
+        {
+            let name = path.file_name().unwrap_or(path.as_os_str());
+            mismatched.push(format!("{} ({})", name.to_string_lossy(), crt.lib_name()));
+        }
+    }
+    if mismatched.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "cargo:warning=vcpkg libraries request a C runtime other than the {} rustc links: {}",
+        rust_crt.lib_name(),
+        mismatched.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// An x64 COFF object with the given sections.
+    fn coff_object(sections: &[(&[u8; 8], &[u8])]) -> Vec<u8> {
+        let mut object = vec![0x64, 0x86, sections.len() as u8, 0];
+        object.resize(20, 0);
+        let mut data_pos = 20 + 40 * sections.len();
+        for &(name, data) in sections {
+            let mut header = name.to_vec();
+            header.resize(16, 0);
+            header.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            header.extend_from_slice(&(data_pos as u32).to_le_bytes());
+            header.resize(40, 0);
+            object.extend(header);
+            data_pos += data.len();
+        }
+        for &(_, data) in sections {
+            object.extend_from_slice(data);
+        }
+        object
+    }
+
+    fn archive(members: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut archive = b"!<arch>\n".to_vec();
+        for &(ref name, ref data) in members {
+            let header = format!(
+                "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",
+                name,
+                0,
+                "",
+                "",
+                0,
+                data.len()
+            );
+            archive.extend_from_slice(header.as_bytes());
+            archive.extend_from_slice(data);
+            if data.len() % 2 == 1 {
+                archive.push(b'\n');
+            }
+        }
+        archive
+    }
+
+    #[test]
+    fn default_lib_directives() {
+        assert_eq!(
+            default_libs(
+                b"\xef\xbb\xbf /DEFAULTLIB:\"LIBCMT\" /DEFAULTLIB:\"OLDNAMES\" \
+                           /FAILIFMISMATCH:\"_MSC_VER=1900\" -defaultlib:msvcrtd.lib\x00"
+            ),
+            vec!["LIBCMT", "OLDNAMES", "msvcrtd.lib"]
+        );
+        assert!(default_libs(b"/defaultlib: /include:defaultlib:libcmt").is_empty());
+    }
+
+    #[test]
+    fn archive_drectve_sections() {
+        let object = coff_object(&[
+            (b".text\0\0\0", b"/defaultlib:libcmt in plain data"),
+            (b".drectve", b" /DEFAULTLIB:\"MSVCRT\" "),
+        ]);
+        // an import object, which has no sections
+        let import = vec![
+            0, 0, 0xff, 0xff, 0, 0, 0x64, 0x86, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ];
+        let lib = archive(&[
+            ("/", vec![0; 7]),
+            ("//", b"long_object_name.obj/\n".to_vec()),
+            ("/0", object.clone()),
+            ("zlib1.dll/", import),
+            (
+                "b.obj/",
+                coff_object(&[(b".drectve", b"-defaultlib:libcmtd")]),
+            ),
+        ]);
+        let sections = drectve_sections(&mut Cursor::new(lib)).unwrap();
+        let libs: Vec<String> = sections.iter().flat_map(|s| default_libs(s)).collect();
+        assert_eq!(libs, vec!["MSVCRT", "libcmtd"]);
+
+        // a single object
+        let sections = drectve_sections(&mut Cursor::new(object)).unwrap();
+        assert_eq!(sections, vec![b" /DEFAULTLIB:\"MSVCRT\" ".to_vec()]);
+        assert!(drectve_sections(&mut Cursor::new(Vec::new()))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn crt_warnings() {
+        let mut demands = BTreeMap::new();
+        demands.insert(PathBuf::from("a.lib"), vec![Crt::DynamicRelease]);
+        assert_eq!(crt_warning(&demands, false, &[]), None);
+        // a single library that disagrees with rustc
+        assert!(crt_warning(&demands, true, &[]).is_some());
+
+        demands.insert(PathBuf::from("b.lib"), vec![Crt::StaticRelease]);
+        demands.insert(PathBuf::from("c.lib"), vec![Crt::StaticRelease]);
+        assert_eq!(
+            crt_warning(&demands, false, &[]).unwrap(),
+            "cargo:warning=vcpkg libraries request a C runtime other than the msvcrt rustc \
+             links: b.lib (libcmt), c.lib (libcmt)"
+        );
+
+        // /NODEFAULTLIB:libcmt resolves the conflict
+        let suppressed = vec!["LIBCMT.lib".to_owned()];
+        assert_eq!(crt_warning(&demands, false, &suppressed), None);
+    }
+}
@@ -0,0 +1,138 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::Error;
+
+/// Kinds that `cargo:rustc-link-lib` accepts before the library name.
+const LINK_LIB_KINDS: &'static [&'static str] = &["static", "dylib", "framework", "raw-dylib"];
+
+/// Kinds that `cargo:rustc-link-search` accepts before the path.
+const LINK_SEARCH_KINDS: &'static [&'static str] =
+    &["native", "crate", "dependency", "framework", "all"];
+
+/// A line of cargo metadata emitted by a build script, in a form that does not
+/// have to be parsed again.
+///
+/// ```
+/// use vcpkg::MetadataDirective;
+///
+/// let directive = MetadataDirective::parse("cargo:rustc-link-lib=static=z");
+/// assert_eq!(
+///     directive,
+///     MetadataDirective::LinkLib {
+///         kind: Some("static".to_owned()),
+///         name: "z".to_owned(),
+///     }
+/// );
+/// assert_eq!(directive.to_cargo_line(), "cargo:rustc-link-lib=static=z");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
+pub enum MetadataDirective {
+    /// `cargo:rustc-link-lib=[KIND[:MODIFIERS]=]NAME`
+    LinkLib {
+        /// the kind of the library with any modifiers, e.g. `static:+verbatim`
+        kind: Option<String>,
+        name: String,
+    },
+    /// `cargo:rustc-link-search=[KIND=]PATH`
+    LinkSearch { kind: Option<String>, path: PathBuf },
+    /// `cargo:rustc-link-arg=FLAG`
+    LinkArg(String),
+    /// `cargo:include=PATH`, passed to dependent build scripts as `DEP_<links>_INCLUDE`
+    Include(PathBuf),
+    /// `cargo:rerun-if-changed=PATH`
+    RerunIfChanged(PathBuf),
+    /// `cargo:warning=MESSAGE`
+    Warning(String),
+    /// Any other line, such as `cargo:defines=...`, as it was emitted
+    Other(String),
+}
+
+impl MetadataDirective {
+    /// Parse a line of cargo metadata such as those in `Library::cargo_metadata`.
+    pub fn parse(line: &str) -> MetadataDirective {
+        let (key, value) = match (line.starts_with("cargo:"), line.find('=')) {
+            (true, Some(eq)) => (&line["cargo:".len()..eq], &line[eq + 1..]),
+            _ => return MetadataDirective::Other(line.to_owned()),
+        };
+        match key {
+            "rustc-link-lib" => {
+                let (kind, name) = split_kind(value, LINK_LIB_KINDS);
+                MetadataDirective::LinkLib {
+                    kind,
+                    name: name.to_owned(),
+                }
+            }
+            "rustc-link-search" => {
+                let (kind, path) = split_kind(value, LINK_SEARCH_KINDS);
+                MetadataDirective::LinkSearch {
+                    kind,
+                    path: PathBuf::from(path),
+                }
+            }
+            "rustc-link-arg" => MetadataDirective::LinkArg(value.to_owned()),
+            "include" => MetadataDirective::Include(PathBuf::from(value)),
+            "rerun-if-changed" => MetadataDirective::RerunIfChanged(PathBuf::from(value)),
+            "warning" => MetadataDirective::Warning(value.to_owned()),
+            _ => MetadataDirective::Other(line.to_owned()),
+        }
+    }
+
+    /// Render the directive as the line of cargo metadata it was parsed from.
+    pub fn to_cargo_line(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl fmt::Display for MetadataDirective {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            MetadataDirective::LinkLib {
+                kind: Some(ref kind),
+                ref name,
+            } => write!(f, "cargo:rustc-link-lib={}={}", kind, name),
+            MetadataDirective::LinkLib {
+                kind: None,
+                ref name,
+            } => write!(f, "cargo:rustc-link-lib={}", name),
+            MetadataDirective::LinkSearch {
+                kind: Some(ref kind),
+                ref path,
+            } => write!(f, "cargo:rustc-link-search={}={}", kind, path.display()),
+            MetadataDirective::LinkSearch {
+                kind: None,
+                ref path,
+            } => write!(f, "cargo:rustc-link-search={}", path.display()),
+            MetadataDirective::LinkArg(ref flag) => write!(f, "cargo:rustc-link-arg={}", flag),
+            MetadataDirective::Include(ref path) => write!(f, "cargo:include={}", path.display()),
+            MetadataDirective::RerunIfChanged(ref path) => {
+                write!(f, "cargo:rerun-if-changed={}", path.display())
+            }
+            MetadataDirective::Warning(ref message) => write!(f, "cargo:warning={}", message),
+            MetadataDirective::Other(ref line) => write!(f, "{}", line),
+        }
+    }
+}
+
+/// `path` as it is written in cargo metadata. Cargo reads the metadata as UTF-8
+/// text, so a path that is not valid UTF-8 can't be passed to it at all.
+pub(crate) fn cargo_path(path: &Path) -> Result<&str, Error> {
+    path.to_str()
+        .ok_or_else(|| Error::NonUtf8Path(path.to_path_buf()))
+}
+
+/// Split `value` into a leading kind from `kinds`, which may carry modifiers
+/// such as `static:+verbatim`, and the rest. Paths that contain `=` without a
+/// kind, such as `C:\a=b`, are left whole.
+fn split_kind<'a>(value: &'a str, kinds: &[&str]) -> (Option<String>, &'a str) {
+    if let Some(eq) = value.find('=') {
+        let kind = &value[..eq];
+        let base = kind.split(':').next().unwrap();
+        if kinds.contains(&base) {
+            return (Some(kind.to_owned()), &value[eq + 1..]);
+        }
+    }
+    (None, value)
+}
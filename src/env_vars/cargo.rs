@@ -12,6 +12,13 @@ pub(crate) mod build_rs {
     /// [`TARGET`]: https://doc.rust-lang.org/cargo/reference/environment-variables.html#environment-variables-cargo-sets-for-build-scripts:~:text=package%20in%20question.-,TARGET,-%E2%80%94%20the%20target%20triple
     pub(crate) const TARGET: &'static str = "TARGET";
 
+    /// The [`HOST`] environment variable which is [set by Cargo for build scripts].
+    /// Also, the host triple of the Rust compiler, which the build script itself runs on.
+    ///
+    /// [set by Cargo for build scripts]: https://doc.rust-lang.org/cargo/reference/environment-variables.html#environment-variables-cargo-sets-for-build-scripts
+    /// [`HOST`]: https://doc.rust-lang.org/cargo/reference/environment-variables.html#environment-variables-cargo-sets-for-build-scripts:~:text=HOST
+    pub(crate) const HOST: &'static str = "HOST";
+
     /// The [`OUT_DIR`] environment variable which is [set by Cargo for build scripts].
     /// Also, it is the folder in which all output and intermediate artifacts should be placed.
     /// This folder is inside the build directory for the package being built,
@@ -29,6 +36,20 @@ pub(crate) mod build_rs {
     /// [target features]: https://doc.rust-lang.org/reference/conditional-compilation.html#target_feature
     pub(crate) const CARGO_CFG_TARGET_FEATURE: &'static str = "CARGO_CFG_TARGET_FEATURE";
 
+    /// The [`CARGO_MANIFEST_DIR`] environment variable which is [set by Cargo for build scripts].
+    /// Also, the directory containing the manifest for the package being built.
+    ///
+    /// [set by Cargo for build scripts]: https://doc.rust-lang.org/cargo/reference/environment-variables.html#environment-variables-cargo-sets-for-build-scripts
+    /// [`CARGO_MANIFEST_DIR`]: https://doc.rust-lang.org/cargo/reference/environment-variables.html#environment-variables-cargo-sets-for-build-scripts:~:text=CARGO_MANIFEST_DIR
+    pub(crate) const CARGO_MANIFEST_DIR: &'static str = "CARGO_MANIFEST_DIR";
+
+    /// The [`PROFILE`] environment variable which is [set by Cargo for build scripts].
+    /// Also, `release` for release builds and `debug` for other builds.
+    ///
+    /// [set by Cargo for build scripts]: https://doc.rust-lang.org/cargo/reference/environment-variables.html#environment-variables-cargo-sets-for-build-scripts
+    /// [`PROFILE`]: https://doc.rust-lang.org/cargo/reference/environment-variables.html#environment-variables-cargo-sets-for-build-scripts:~:text=PROFILE
+    pub(crate) const PROFILE: &'static str = "PROFILE";
+
     pub(crate) mod prelude {
         pub(crate) use super::*;
     }
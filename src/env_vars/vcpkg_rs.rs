@@ -3,14 +3,27 @@
 pub(crate) const VCPKGRS_TRIPLET: &'static str = "VCPKGRS_TRIPLET";
 pub(crate) const VCPKGRS_DISABLE: &'static str = "VCPKGRS_DISABLE";
 pub(crate) const VCPKGRS_DYNAMIC: &'static str = "VCPKGRS_DYNAMIC";
+pub(crate) const VCPKGRS_STATIC: &'static str = "VCPKGRS_STATIC";
+pub(crate) const VCPKGRS_ALLOW_CRT_MISMATCH: &'static str = "VCPKGRS_ALLOW_CRT_MISMATCH";
+pub(crate) const VCPKGRS_MUSL_FALLBACK: &'static str = "VCPKGRS_MUSL_FALLBACK";
+pub(crate) const VCPKGRS_TARGET_MAP: &'static str = "VCPKGRS_TARGET_MAP";
+pub(crate) const VCPKGRS_TRIPLET_FALLBACKS: &'static str = "VCPKGRS_TRIPLET_FALLBACKS";
+pub(crate) const VCPKGRS_VERBOSE: &'static str = "VCPKGRS_VERBOSE";
+pub(crate) const VCPKGRS_LIB: &'static str = "VCPKGRS_LIB";
 pub(crate) const NO_VCPKG: &'static str = "NO_VCPKG";
 pub(crate) const VCPKG_ROOT: &'static str = "VCPKG_ROOT";
+pub(crate) const VCPKG_INSTALLED_DIR: &'static str = "VCPKG_INSTALLED_DIR";
+pub(crate) const VCPKG_OVERLAY_TRIPLETS: &'static str = "VCPKG_OVERLAY_TRIPLETS";
+pub(crate) const VCPKG_DEFAULT_BINARY_CACHE: &'static str = "VCPKG_DEFAULT_BINARY_CACHE";
+pub(crate) const VCPKG_DEFAULT_HOST_TRIPLET: &'static str = "VCPKG_DEFAULT_HOST_TRIPLET";
 
 #[cfg(any(test, doctest))]
 pub(crate) const ARBITRARY_VCPKGRS_NO_FOO: &'static str = concat!("VCPKGRS_NO_", "FOO");
 
 pub(crate) mod prefix {
     pub(crate) const VCPKGRS_NO_: &'static str = "VCPKGRS_NO_";
+    pub(crate) const VCPKGRS_TRIPLET_: &'static str = "VCPKGRS_TRIPLET_";
+    pub(crate) const VCPKGRS_DYNAMIC_: &'static str = "VCPKGRS_DYNAMIC_";
 }
 
 pub(crate) mod suffix {
@@ -1,7 +1,15 @@
 use std::error;
 use std::fmt;
+use std::io;
+use std::path::PathBuf;
 
-#[derive(Debug)] // need Display?
+/// The reasons vcpkg-rs can fail to find a package.
+///
+/// More variants may be added in the future, so matches on it need a wildcard
+/// arm.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
 pub enum Error {
     /// Aborted because of a `VCPKGRS_NO_*` environment variable.
     ///
@@ -11,20 +19,81 @@ pub enum Error {
     /// Aborted because a required environment variable was not set.
     RequiredEnvMissing(String),
 
-    /// On Windows, only MSVC ABI is supported
+    /// There is no default triplet for the target, which is also the case for the
+    /// Windows ABIs other than MSVC and GNU
     NotMSVC,
 
     /// Can't find a vcpkg tree
     VcpkgNotFound(String),
 
     /// Library not found in vcpkg tree
-    LibNotFound(String),
+    LibNotFound {
+        /// the port that was being found, or that a missing file belongs to
+        port: String,
+        /// the vcpkg triplet that was looked in
+        triplet: String,
+        /// the file that was looked for, if the port is installed but the
+        /// file is missing
+        path: Option<PathBuf>,
+        /// the triplets the port is installed for, if it is not installed for
+        /// `triplet`
+        installed_triplets: Vec<String>,
+        /// the command that installs what is missing, e.g.
+        /// `vcpkg install openssl:x64-windows-static-md`
+        install_command: Option<String>,
+        /// a description of what is missing
+        detail: String,
+    },
 
     /// Could not understand vcpkg installation
     VcpkgInstallation(String),
 
-    #[doc(hidden)]
-    __Nonexhaustive,
+    /// An external command run by vcpkg-rs could not be started or failed
+    CommandFailed(String),
+
+    /// The `[package.metadata.vcpkg]` table of the crate could not be read
+    InvalidMetadata(String),
+
+    /// A triplet configured for some of the ports can't be mixed with the main triplet
+    IncompatibleTriplet(String),
+
+    /// The C runtime linkage that the triplet file sets does not match the
+    /// `crt-static` target feature, see `VCPKGRS_ALLOW_CRT_MISMATCH`
+    CrtMismatch(String),
+
+    /// A file or directory could not be read or written
+    Io {
+        /// the file or directory, empty if writing to `Config::metadata_writer` failed
+        path: PathBuf,
+        /// the underlying error, which only keeps its message when serialized
+        #[cfg_attr(feature = "serde", serde(with = "io_error"))]
+        error: io::Error,
+    },
+
+    /// A path that has to be passed to cargo is not valid UTF-8, which cargo
+    /// can't read
+    NonUtf8Path(PathBuf),
+
+    /// The status database records a port as partly installed or removed, as
+    /// happens when `vcpkg install` or `vcpkg remove` is interrupted
+    BrokenPort {
+        /// the name of the port, e.g. `openssl`
+        port: String,
+        /// the vcpkg triplet the port was being installed or removed for
+        triplet: String,
+        /// the `Status` recorded for the port, e.g. `install ok half-installed`
+        status: String,
+    },
+}
+
+impl Error {
+    /// An `Error::Io` for `path`.
+    pub(crate) fn io<P: Into<PathBuf>>(path: P, error: io::Error) -> Error {
+        Error::Io {
+            path: path.into(),
+            error,
+        }
+    }
 }
 
 impl error::Error for Error {
@@ -32,17 +101,23 @@ impl error::Error for Error {
         match *self {
             Error::DisabledByEnv(_) => "vcpkg-rs requested to be aborted",
             Error::RequiredEnvMissing(_) => "a required env setting is missing",
-            Error::NotMSVC => "vcpkg-rs only can only find libraries for MSVC ABI builds",
+            Error::NotMSVC => "vcpkg-rs has no default triplet for the target",
             Error::VcpkgNotFound(_) => "could not find Vcpkg tree",
-            Error::LibNotFound(_) => "could not find library in Vcpkg tree",
+            Error::LibNotFound { .. } => "could not find library in Vcpkg tree",
             Error::VcpkgInstallation(_) => "could not look up details of packages in vcpkg tree",
-            Error::__Nonexhaustive => panic!(),
+            Error::CommandFailed(_) => "an external command failed",
+            Error::InvalidMetadata(_) => "could not read vcpkg metadata from Cargo.toml",
+            Error::IncompatibleTriplet(_) => "vcpkg triplets can't be mixed",
+            Error::CrtMismatch(_) => "the vcpkg triplet links the C runtime differently",
+            Error::Io { .. } => "could not read or write a file",
+            Error::NonUtf8Path(_) => "a path is not valid UTF-8",
+            Error::BrokenPort { .. } => "a vcpkg port is partly installed",
         }
     }
 
-    fn cause(&self) -> Option<&error::Error> {
+    fn source(&self) -> Option<&(error::Error + 'static)> {
         match *self {
-            // Error::Command { ref cause, .. } => Some(cause),
+            Error::Io { ref error, .. } => Some(error),
             _ => None,
         }
     }
@@ -55,18 +130,78 @@ impl fmt::Display for Error {
             Error::RequiredEnvMissing(ref name) => write!(f, "Aborted because {} is not set", name),
             Error::NotMSVC => write!(
                 f,
-                "the vcpkg-rs Vcpkg build helper can only find libraries built for the MSVC ABI."
+                "the vcpkg-rs Vcpkg build helper has no default triplet for this target, set VCPKGRS_TRIPLET to choose one."
             ),
             Error::VcpkgNotFound(ref detail) => write!(f, "Could not find Vcpkg tree: {}", detail),
-            Error::LibNotFound(ref detail) => {
-                write!(f, "Could not find library in Vcpkg tree {}", detail)
+            Error::LibNotFound {
+                ref detail,
+                ref install_command,
+                ..
+            } => {
+                write!(f, "Could not find library in Vcpkg tree {}", detail)?;
+                match *install_command {
+                    Some(ref command) => write!(f, ", install it with `{}`", command),
+                    None => Ok(()),
+                }
             }
             Error::VcpkgInstallation(ref detail) => write!(
                 f,
                 "Could not look up details of packages in vcpkg tree {}",
                 detail
             ),
-            Error::__Nonexhaustive => panic!(),
+            Error::CommandFailed(ref detail) => write!(f, "Command failed: {}", detail),
+            Error::InvalidMetadata(ref detail) => {
+                write!(
+                    f,
+                    "Could not read vcpkg metadata from Cargo.toml: {}",
+                    detail
+                )
+            }
+            Error::IncompatibleTriplet(ref detail) => {
+                write!(f, "Incompatible vcpkg triplets: {}", detail)
+            }
+            Error::CrtMismatch(ref detail) => write!(f, "Mismatched C runtime: {}", detail),
+            Error::Io {
+                ref path,
+                ref error,
+            } if path.as_os_str().is_empty() => {
+                write!(f, "Could not write the cargo metadata: {}", error)
+            }
+            Error::Io {
+                ref path,
+                ref error,
+            } => write!(f, "Could not access {}: {}", path.display(), error),
+            Error::NonUtf8Path(ref path) => write!(
+                f,
+                "The path {} can't be passed to cargo as it is not valid UTF-8",
+                path.display()
+            ),
+            Error::BrokenPort {
+                ref port,
+                ref triplet,
+                ref status,
+            } => write!(
+                f,
+                "Package {} is in a broken state for vcpkg triplet {} ({}), run \
+                 `vcpkg remove {}:{}` and `vcpkg install {}:{}` to repair it",
+                port, triplet, status, port, triplet, port, triplet
+            ),
         }
     }
 }
+
+/// Serializes an `io::Error` as its message, which is all that can be restored.
+#[cfg(feature = "serde")]
+mod io_error {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::io;
+
+    pub fn serialize<S: Serializer>(error: &io::Error, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&error.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<io::Error, D::Error> {
+        String::deserialize(deserializer)
+            .map(|message| io::Error::new(io::ErrorKind::Other, message))
+    }
+}
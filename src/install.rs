@@ -0,0 +1,35 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::Error;
+
+/// The vcpkg executable in a vcpkg root.
+pub(crate) fn vcpkg_tool(vcpkg_root: &Path) -> PathBuf {
+    if cfg!(windows) {
+        vcpkg_root.join("vcpkg.exe")
+    } else {
+        vcpkg_root.join("vcpkg")
+    }
+}
+
+/// Run `vcpkg install <port>:<triplet>` in a vcpkg root.
+pub(crate) fn install_port(vcpkg_root: &Path, port: &str, triplet: &str) -> Result<(), Error> {
+    let mut command = Command::new(vcpkg_tool(vcpkg_root));
+    command
+        .arg("install")
+        .arg(format!("{}:{}", port, triplet))
+        .current_dir(vcpkg_root);
+    // the output is not passed through as cargo would read it as metadata
+    let output = command
+        .output()
+        .map_err(|e| Error::CommandFailed(format!("could not run {:?}: {}", command, e)))?;
+    if !output.status.success() {
+        return Err(Error::CommandFailed(format!(
+            "{:?} exited with {}: {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stdout).trim()
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::{Config, Error, Library, VcpkgTriplet};
+
+/// A vcpkg tree and the triplet to find packages in, discovered once so that
+/// several packages can be looked up without finding the tree and reading its
+/// status database each time.
+///
+/// ```rust,no_run
+/// // build.rs
+/// let vcpkg = vcpkg::VcpkgInstallation::new().unwrap();
+/// if vcpkg.is_installed("openssl").unwrap() {
+///     vcpkg.config().emit_includes(true).find_package("openssl").unwrap();
+/// }
+/// vcpkg.find_package("zlib").unwrap();
+/// ```
+pub struct VcpkgInstallation {
+    /// the vcpkg root, if there is one besides the installed tree
+    pub(crate) vcpkg_root: Option<PathBuf>,
+
+    /// the directory containing the triplet directories and the status database
+    pub(crate) installed_root: PathBuf,
+
+    /// the triplet to find packages in
+    pub(crate) triplet: VcpkgTriplet,
+
+    /// versions of the ports installed for the triplet, by port name
+    pub(crate) ports: BTreeMap<String, String>,
+}
+
+impl VcpkgInstallation {
+    /// Discover the vcpkg tree and triplet that `Config::new()` would find
+    /// packages in, see `Config::installation`.
+    pub fn new() -> Result<VcpkgInstallation, Error> {
+        Config::new().installation()
+    }
+
+    /// The directory the ports are installed in, e.g. `<vcpkg root>/installed`.
+    pub fn installed_root(&self) -> &Path {
+        &self.installed_root
+    }
+
+    /// The name of the vcpkg triplet that packages are found in.
+    pub fn triplet(&self) -> &str {
+        &self.triplet.name
+    }
+
+    /// The ports installed for the triplet, with their versions.
+    pub fn ports(&self) -> &BTreeMap<String, String> {
+        &self.ports
+    }
+
+    /// A `Config` that finds packages in this installation, to set the other
+    /// options of a find on.
+    pub fn config(&self) -> Config {
+        let mut config = Config::new();
+        if let Some(ref vcpkg_root) = self.vcpkg_root {
+            config.vcpkg_root(vcpkg_root.clone());
+        }
+        config.installed_root(self.installed_root.clone());
+        config.target = Some(self.triplet.clone());
+        config
+    }
+
+    /// Find a package with the default options, see `Config::find_package`.
+    pub fn find_package(&self, port_name: &str) -> Result<Library, Error> {
+        self.config().find_package(port_name)
+    }
+
+    /// Check whether a package and its dependencies are installed, see
+    /// `Config::is_installed`.
+    pub fn is_installed(&self, port_name: &str) -> Result<bool, Error> {
+        self.config().is_installed(port_name)
+    }
+}
@@ -3,7 +3,8 @@
 //!
 //! From a Vcpkg package name
 //! this build helper will emit cargo metadata to link it and it's dependencies
-//! (excluding system libraries, which it does not determine).
+//! (excluding system libraries, unless `Config::emit_usage_libs` asks for the
+//! ones named in the usage files of the ports).
 //!
 //! The simplest possible usage looks like this :-
 //!
@@ -21,6 +22,14 @@
 //!     .find_package("zlib").unwrap();
 //! ```
 //!
+//! The packages listed in the `[package.metadata.vcpkg]` table used by
+//! [cargo-vcpkg](https://crates.io/crates/cargo-vcpkg) can all be found at once :-
+//!
+//! ```rust,no_run
+//! // build.rs
+//! let libs = vcpkg::find_all!().unwrap();
+//! ```
+//!
 //! If the search was successful all appropriate Cargo metadata will be printed
 //! to stdout.
 //!
@@ -29,6 +38,20 @@
 //! At this time, vcpkg has a single triplet on macOS and Linux, which builds
 //! static link versions of libraries. This triplet works well with Rust. It is also possible
 //! to select a custom triplet using the `VCPKGRS_TRIPLET` environment variable.
+//! With `VCPKGRS_DYNAMIC` set, `x86_64-unknown-linux-gnu` links to the shared libraries of the
+//! `x64-linux-dynamic` community triplet instead. They are not copied to `OUT_DIR`, as the
+//! dynamic loader does not look there, so they have to be found at run time through
+//! `LD_LIBRARY_PATH` or an rpath, see `Config::rpath`.
+//! On macOS the `x64-osx-dynamic` and `arm64-osx-dynamic` triplets are used the same way. vcpkg
+//! gives their dylibs `@rpath/` install names, which `Library::needs_rpath` tells about.
+//! When nothing is installed for a dynamic triplet selected this way, the static one is used, as
+//! `VCPKGRS_DYNAMIC` may be set for the sake of Windows builds.
+//! The static `x64-freebsd`, `x64-openbsd`, `x64-haiku`, `x64-illumos` and `x64-solaris`
+//! community triplets are selected for the corresponding x86_64 targets.
+//! ## Android
+//! The `-linux-android` targets use the `arm64-android`, `x64-android`, `x86-android` and, for
+//! `armv7-linux-androideabi` and `thumbv7neon-linux-androideabi`, `arm-neon-android` triplets,
+//! which build static libraries.
 //! ## Windows
 //! On Windows there are three
 //! configurations that are supported for 64-bit builds and another three for 32-bit.
@@ -46,6 +69,26 @@
 //! generate dynamically linked binaries, in which case you will have to arrange for
 //! dlls from your Vcpkg installation to be available in your path.
 //!
+//! 32-bit ARM builds for `thumbv7a-pc-windows-msvc` are configured the same way with the
+//! `arm-windows-static-md`, `arm-windows-static` and `arm-windows` triplets.
+//!
+//! ## Windows GNU
+//! The `-pc-windows-gnu` targets use the community supported mingw triplets, `x64-mingw-static`
+//! by default or `x64-mingw-dynamic` if `VCPKGRS_DYNAMIC` is set (`x86-` and `arm64-` for the
+//! other architectures). Dynamic builds link to the `lib*.dll.a` import libraries, and statically
+//! to the `.a` archives that some ports install next to them, such as `libSDL2main.a`.
+//!
+//! ## Release-only triplets
+//! When nothing is installed for the triplet selected for the rust target but something is for
+//! its `-release` variant, such as `x64-linux-release` or `x64-windows-release`, that variant is
+//! used. Release-only trees, including those built with `VCPKG_BUILD_TYPE=release`, have no
+//! debug libraries, so the release ones are linked even with `Config::use_debug_profile`.
+//!
+//! ## Manifest mode
+//! When the crate or its workspace has a `vcpkg.json`, the packages that vcpkg installed for it
+//! in manifest mode are found in the `vcpkg_installed` directory next to that file, unless
+//! `VCPKG_INSTALLED_DIR` or `Config::vcpkg_root` say otherwise.
+//!
 //! # Environment variables
 //!
 //! A number of environment variables are available to globally configure which
@@ -56,15 +99,64 @@
 //! set up with `vcpkg integrate install`, and check the crate source and target
 //! to see if a vcpkg tree has been created by [cargo-vcpkg](https://crates.io/crates/cargo-vcpkg).
 //!
+//! * `VCPKG_INSTALLED_DIR` - Look for the installed packages in this directory instead of
+//! the `installed` directory of the vcpkg root, like the `--x-install-root` option of vcpkg.
+//!
 //! * `VCPKGRS_TRIPLET` - Use this to override vcpkg-rs' default triplet selection with your own.
 //! This is how to select a custom vcpkg triplet.
 //!
+//! * `VCPKG_OVERLAY_TRIPLETS` - Directories to look in for the `.cmake` file of a custom
+//! triplet, as for vcpkg. The linkage is read from the triplet file when there is one, so
+//! a custom triplet does not have to be named like the vcpkg ones.
+//!
+//! * `VCPKGRS_TARGET_MAP` - Triplets to use for rust targets, as `rust-target=triplet` entries
+//! separated by commas or semicolons, such as `riscv64gc-unknown-linux-gnu=riscv64-linux`.
+//!
+//! * `VCPKGRS_TRIPLET_FALLBACKS` - Triplets to try in turn, separated by commas or semicolons,
+//! when a package is not installed for the selected triplet, such as
+//! `x64-windows-static-md,x64-windows-static`. See also `Config::triplet_fallbacks`.
+//!
+//! * `VCPKG_DEFAULT_HOST_TRIPLET` - The triplet that vcpkg builds tools for, as for vcpkg. By
+//! default it is inferred from the rust host, such as `x64-windows` or `x64-linux`. See also
+//! `Config::host_triplet`.
+//!
+//! * `VCPKGRS_TRIPLET_FOO` - Find the package `foo` in the given triplet instead, regardless of
+//! the triplet that would otherwise be used. See also `Config::package_triplet`.
+//!
+//! * `VCPKGRS_TRIPLET_<RUST_TARGET>` - Like `VCPKGRS_TRIPLET`, but only for the given rust target,
+//! e.g. `VCPKGRS_TRIPLET_AARCH64_APPLE_DARWIN=arm64-osx-dynamic`, so that one cargo invocation
+//! can build for several targets with different triplets. It takes precedence over
+//! `VCPKGRS_TRIPLET`.
+//!
 //! * `VCPKGRS_NO_FOO` - if set, vcpkg-rs will not attempt to find the
 //! library named `foo`.
 //!
 //! * `VCPKGRS_DISABLE` - if set, vcpkg-rs will not attempt to find any libraries.
 //!
-//! * `VCPKGRS_DYNAMIC` - if set, vcpkg-rs will link to DLL builds of ports.
+//! * `VCPKGRS_DYNAMIC` - if set, vcpkg-rs will link to DLL builds of ports, unless it is set to
+//! `0`, which selects the static builds like `VCPKGRS_STATIC`. It takes precedence over
+//! `Config::linkage_preference`.
+//!
+//! * `VCPKGRS_STATIC` - if set, vcpkg-rs will link to the static builds of ports, even if
+//! `VCPKGRS_DYNAMIC` is set too or `Config::linkage_preference` prefers the DLL builds.
+//!
+//! * `VCPKGRS_DYNAMIC_FOO` - if set, vcpkg-rs will link to the DLL build of the package `foo`,
+//! like `VCPKGRS_DYNAMIC` does for all packages, while the other packages stay static.
+//!
+//! * `VCPKGRS_MUSL_FALLBACK` - if set, musl targets use the static triplet of the corresponding
+//! glibc target, see `Config::allow_musl_fallback`.
+//!
+//! * `VCPKGRS_ALLOW_CRT_MISMATCH` - if set, vcpkg-rs will link to libraries from a triplet
+//! whose triplet file links the C runtime differently than the `crt-static` target feature
+//! asks for, with a warning rather than an error. Without a triplet file the CRT linkage is
+//! guessed from the name of the triplet, and a mismatch is always only a warning.
+//!
+//! * `VCPKGRS_LIB` - the `lib.exe` that `Config::bundle` merges MSVC libraries with, if it is
+//! not on the `PATH`.
+//!
+//! * `VCPKGRS_VERBOSE` - if set, vcpkg-rs explains on stderr how it found the vcpkg root and
+//! chose the triplet, which ports it required, which `.pc` files it read and the order it
+//! links the libraries in. Cargo shows this output with `cargo build -vv`.
 //! # Related tools
 //! ## cargo vcpkg
 //! [`cargo vcpkg`](https://crates.io/crates/cargo-vcpkg) can fetch and build a vcpkg installation of
@@ -98,6 +190,13 @@
 #[macro_use]
 extern crate lazy_static;
 
+#[cfg(feature = "cc")]
+extern crate cc;
+
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+
 #[allow(unused_imports)]
 use std::ascii::AsciiExt;
 
@@ -110,18 +209,48 @@ use std::fs::{self, File};
 use std::io::{BufRead, BufReader, Read};
 use std::path::{Path, PathBuf};
 
+mod binary_cache;
+#[cfg(feature = "bootstrap")]
+pub mod bootstrap;
+mod bundle;
 mod config;
+mod conflicts;
+mod copy_mode;
+mod crt;
+mod directive;
 mod env_vars;
 mod error;
+mod install;
+mod installation;
 mod library;
+mod linkage_preference;
+mod metadata;
 mod pc_file;
 mod port;
+mod port_cache;
+mod probe;
+mod probe_json;
+mod rpath;
+mod stale;
 mod target_triplet;
+mod usage;
 mod vcpkg_target;
+mod verbose;
 
 pub use config::Config;
+pub use conflicts::ConflictPolicy;
+pub use copy_mode::CopyMode;
+pub use crt::Crt;
+pub use directive::MetadataDirective;
 pub use error::Error;
+pub use installation::VcpkgInstallation;
 pub use library::Library;
+pub use linkage_preference::LinkagePreference;
+pub use probe::Probe;
+pub use rpath::Rpath;
+pub use target_triplet::{
+    check_compatibility, compatibility, Compatibility, Linkage, Mismatch, Triplet, TripletSettings,
+};
 
 pub(crate) use port::Port;
 pub(crate) use target_triplet::VcpkgTriplet;
@@ -129,6 +258,7 @@ pub(crate) use vcpkg_target::VcpkgTarget;
 
 use env_vars::prelude::*;
 use pc_file::{PcFile, PcFiles};
+use vcpkg_target::{is_versioned_shared_lib, unversioned_dylib};
 
 /// Deprecated in favor of the find_package function
 #[doc(hidden)]
@@ -147,17 +277,70 @@ pub fn find_package(package: &str) -> Result<Library, Error> {
     Config::new().find_package(package)
 }
 
+/// Find all of the packages listed in the `[package.metadata.vcpkg]` table of
+/// the `Cargo.toml` of the crate being built. See `Config::find_all`.
+pub fn find_all() -> Result<Vec<Library>, Error> {
+    Config::new().find_all()
+}
+
+/// Find all of the packages listed in the `[package.metadata.vcpkg]` table of
+/// the `Cargo.toml` of the crate that invokes the macro, optionally with a
+/// `Config` to use for each of them.
+///
+/// ```rust,no_run
+/// // build.rs
+/// let libs = vcpkg::find_all!().unwrap();
+///
+/// let libs = vcpkg::find_all!(vcpkg::Config::new().emit_includes(true)).unwrap();
+/// ```
+#[macro_export]
+macro_rules! find_all {
+    () => {
+        $crate::find_all!($crate::Config::new())
+    };
+    ($config:expr) => {
+        $config.find_all_in(::std::path::Path::new(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/Cargo.toml"
+        )))
+    };
+}
+
+/// The vcpkg triplet that packages are found in for the rust target `rust_target`
+/// by default, such as `x64-windows-static-md` for `x86_64-pc-windows-msvc` or
+/// `x64-linux` for `x86_64-unknown-linux-gnu`, as described in the module docs.
+///
+/// This is the static triplet that is used without `VCPKGRS_DYNAMIC` or the
+/// `crt-static` target feature, and does not look at the environment, so tools
+/// other than build scripts can use it too. Targets that vcpkg-rs does not know
+/// a triplet for give `Error::NotMSVC`.
+///
+/// ```
+/// assert_eq!(
+///     vcpkg::default_triplet_for("aarch64-apple-darwin").unwrap(),
+///     "arm64-osx"
+/// );
+/// ```
+pub fn default_triplet_for(rust_target: &str) -> Result<String, Error> {
+    default_triplet(rust_target, false, false).map(|triplet| triplet.name)
+}
+
 /// Find the vcpkg root
 #[doc(hidden)]
 pub fn find_vcpkg_root(cfg: &Config) -> Result<PathBuf, Error> {
+    find_vcpkg_root_and_source(cfg).map(|(root, _)| root)
+}
+
+/// The vcpkg root and a description of where it was found.
+fn find_vcpkg_root_and_source(cfg: &Config) -> Result<(PathBuf, &'static str), Error> {
     // prefer the setting from the use if there is one
     if let &Some(ref path) = &cfg.vcpkg_root {
-        return Ok(path.clone());
+        return Ok((path.clone(), "Config::vcpkg_root"));
     }
 
     // otherwise, use the setting from the environment
     if let Some(path) = env::var_os(VCPKG_ROOT) {
-        return Ok(PathBuf::from(path));
+        return Ok((PathBuf::from(path), VCPKG_ROOT));
     }
 
     // see if there is a per-user vcpkg tree that has been integrated into msbuild
@@ -193,7 +376,7 @@ pub fn find_vcpkg_root(cfg: &Config) -> Result<PathBuf, Error> {
                                 found
                             )));
                         }
-                        return Ok(vcpkg_root);
+                        return Ok((vcpkg_root, "vcpkg integrate install"));
                     }
                 }
             }
@@ -223,7 +406,7 @@ pub fn find_vcpkg_root(cfg: &Config) -> Result<PathBuf, Error> {
                 cv_cfg.push("downloads");
                 cv_cfg.push("cargo-vcpkg.toml");
                 if cv_cfg.exists() {
-                    return Ok(try_root);
+                    return Ok((try_root, "cargo-vcpkg"));
                 }
             }
         }
@@ -236,11 +419,14 @@ pub fn find_vcpkg_root(cfg: &Config) -> Result<PathBuf, Error> {
     )))
 }
 
-fn validate_vcpkg_root(path: &PathBuf) -> Result<(), Error> {
+fn validate_vcpkg_root(cfg: &Config, path: &PathBuf) -> Result<(), Error> {
     let mut vcpkg_root_path = path.clone();
     vcpkg_root_path.push(".vcpkg-root");
 
-    if vcpkg_root_path.exists() {
+    if vcpkg_root_path.exists()
+        || is_export_tree(path)
+        || (cfg.export_tree && path.join("installed").is_dir())
+    {
         Ok(())
     } else {
         Err(Error::VcpkgNotFound(format!(
@@ -250,24 +436,113 @@ fn validate_vcpkg_root(path: &PathBuf) -> Result<(), Error> {
     }
 }
 
+/// The `vcpkg_installed` directory that vcpkg creates in manifest mode next to
+/// the `vcpkg.json` of the crate or its workspace, if packages were installed.
+fn find_manifest_installed_root() -> Option<PathBuf> {
+    let mut dir = PathBuf::from(env::var_os(CARGO_MANIFEST_DIR)?);
+    loop {
+        if dir.join("vcpkg.json").is_file() {
+            let installed_root = dir.join("vcpkg_installed");
+            return match installed_root.join("vcpkg").is_dir() {
+                true => Some(installed_root),
+                false => None,
+            };
+        }
+        if !dir.pop() {
+            return None;
+        }
+    }
+}
+
+/// Does `path` look like a tree made by `vcpkg export --raw`? Such a tree has
+/// the installed packages and their status database, but not the rest of a vcpkg
+/// root, so there is no `.vcpkg-root` and no directory of status updates.
+fn is_export_tree(path: &Path) -> bool {
+    path.join("installed")
+        .join("vcpkg")
+        .join("status")
+        .is_file()
+}
+
 // Should it be an associated function of Config?
 pub(crate) fn find_vcpkg_target(
     cfg: &Config,
     target_triplet: &VcpkgTriplet,
 ) -> Result<VcpkgTarget, Error> {
-    let vcpkg_root = find_vcpkg_root(&cfg)?;
-    validate_vcpkg_root(&vcpkg_root)?;
-
-    let mut base = vcpkg_root.clone();
-    base.push("installed");
+    let installed_root = cfg
+        .installed_root
+        .clone()
+        .or_else(|| env::var_os(VCPKG_INSTALLED_DIR).map(PathBuf::from))
+        .or_else(|| match cfg.vcpkg_root {
+            Some(_) => None,
+            None => find_manifest_installed_root(),
+        });
+    let (mut base, packages_path) = match installed_root {
+        // the installed tree can live outside of the vcpkg root, which is then
+        // only needed for the packages directory
+        Some(installed_root) => {
+            if !installed_root.join("vcpkg").is_dir() {
+                return Err(Error::VcpkgNotFound(format!(
+                    "Could not find the vcpkg status database in {}",
+                    installed_root.display()
+                )));
+            }
+            let packages_path = match find_vcpkg_root(&cfg) {
+                Ok(vcpkg_root) => vcpkg_root.join("packages"),
+                Err(_) => installed_root.with_file_name("packages"),
+            };
+            verbose::log(format_args!(
+                "using the installed tree {}",
+                installed_root.display()
+            ));
+            (installed_root, packages_path)
+        }
+        None => {
+            let (vcpkg_root, source) = find_vcpkg_root_and_source(&cfg)?;
+            validate_vcpkg_root(&cfg, &vcpkg_root)?;
+            verbose::log(format_args!(
+                "using the vcpkg root {} from {}",
+                vcpkg_root.display(),
+                source
+            ));
+            (vcpkg_root.join("installed"), vcpkg_root.join("packages"))
+        }
+    };
     let status_path = base.join("vcpkg");
 
+    let is_inferred = cfg.inferred_triplet
+        && cfg
+            .target
+            .as_ref()
+            .map_or(false, |target| target.name == target_triplet.name);
+    let keep_linkage = cfg.linkage_preference.is_some();
+    let target_triplet = &match installed_alternative(&base, target_triplet, keep_linkage) {
+        Some(alternative) if is_inferred => {
+            verbose::log(format_args!(
+                "using vcpkg triplet {} as nothing is installed for {}",
+                alternative.name, target_triplet.name
+            ));
+            alternative
+        }
+        _ => target_triplet.clone(),
+    };
+
     base.push(&target_triplet.name);
 
-    let lib_path = base.join("lib");
-    let bin_path = base.join("bin");
+    // the debug variants of the libraries live in a tree of their own, but
+    // share the headers with the release variants. Release-only triplets have
+    // no debug tree, so the release variants are used then.
+    let is_debug = cfg.use_debug_profile
+        && env::var(PROFILE).map_or(false, |p| p == "debug")
+        && base.join("debug").is_dir();
+    let lib_base = if is_debug {
+        base.join("debug")
+    } else {
+        base.clone()
+    };
+    let lib_path = lib_base.join("lib");
+    let bin_path = lib_base.join("bin");
     let include_path = base.join("include");
-    let packages_path = vcpkg_root.join("packages");
 
     Ok(VcpkgTarget {
         lib_path,
@@ -275,35 +550,92 @@ pub(crate) fn find_vcpkg_target(
         include_path,
         status_path,
         packages_path,
-        target_triplet: target_triplet.clone(),
+        target_triplet: VcpkgTriplet {
+            strip_lib_prefix: cfg
+                .strip_lib_prefix
+                .unwrap_or(target_triplet.strip_lib_prefix),
+            ..target_triplet.clone()
+        },
+        is_debug,
+        host_triplet: cfg.get_host_triplet().map(|triplet| triplet.name),
     })
 }
 
-fn load_port_manifest(
-    path: &PathBuf,
-    port: &str,
-    version: &str,
+/// A triplet to use instead of one inferred from the rust target that nothing is
+/// installed for in the installed tree `base`. A tree may have been built with
+/// the community -release triplets, such as x64-linux-release, or with the static
+/// triplets of platforms other than Windows while `VCPKGRS_DYNAMIC` is set for the
+/// sake of Windows builds, unless `keep_linkage` as there is a `Config::linkage_preference`.
+fn installed_alternative(
+    base: &Path,
+    triplet: &VcpkgTriplet,
+    keep_linkage: bool,
+) -> Option<VcpkgTriplet> {
+    if base.join(&triplet.name).is_dir() {
+        return None;
+    }
+    let mut alternatives = vec![VcpkgTriplet {
+        name: format!("{}-release", triplet.name),
+        ..triplet.clone()
+    }];
+    if !keep_linkage && !triplet.is_windows() && triplet.name.ends_with("-dynamic") {
+        let static_name = &triplet.name[..triplet.name.len() - "-dynamic".len()];
+        alternatives.push(static_name.into());
+    }
+    alternatives
+        .into_iter()
+        .find(|alternative| base.join(&alternative.name).is_dir())
+}
+
+/// The manifest listing the files that a port installed.
+fn port_manifest_path(status_path: &Path, port: &str, version: &str, triplet: &str) -> PathBuf {
+    status_path
+        .join("info")
+        .join(format!("{}_{}_{}.list", port, version, triplet))
+}
+
+/// The files of the status database that cargo should watch for changes: the
+/// status file if there is one, and the directory of updates to it.
+pub(crate) fn status_paths(target: &VcpkgTarget) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    let status = target.status_path.join("status");
+    if status.exists() {
+        paths.push(status);
+    }
+    let updates = target.status_path.join("updates");
+    // exported trees only have the status file
+    if updates.exists() || paths.is_empty() {
+        paths.push(updates);
+    }
+    paths
+}
+
+/// Read the files of an installed port from its manifest into `port`, which
+/// has been read from the status database by `load_ports`.
+pub(crate) fn load_port_manifest(
+    port_name: &str,
+    port: &mut Port,
     vcpkg_target: &VcpkgTarget,
-) -> Result<(Vec<String>, Vec<String>), Error> {
-    let manifest_file = path.join("info").join(format!(
-        "{}_{}_{}.list",
-        port, version, vcpkg_target.target_triplet.name
-    ));
+) -> Result<(), Error> {
+    let manifest_file = &port.manifest;
 
     let mut dlls = Vec::new();
     let mut libs = Vec::new();
+    let mut subdir_libs = BTreeMap::new();
+    let mut tools = Vec::new();
+    let mut has_headers = false;
+    let mut pc_file_paths = Vec::new();
 
-    let f = File::open(&manifest_file).map_err(|_| {
-        Error::VcpkgInstallation(format!(
-            "Could not open port manifest file {}",
-            manifest_file.display()
-        ))
-    })?;
+    let f = File::open(&manifest_file).map_err(|e| Error::io(manifest_file, e))?;
 
     let file = BufReader::new(&f);
 
     let dll_prefix = Path::new(&vcpkg_target.target_triplet.name).join("bin");
     let lib_prefix = Path::new(&vcpkg_target.target_triplet.name).join("lib");
+    let tool_prefix = Path::new(&vcpkg_target.target_triplet.name).join("tools");
+    let include_prefix = Path::new(&vcpkg_target.target_triplet.name).join("include");
+    let pkgconfig_prefix = lib_prefix.join("pkgconfig");
+    let installed_path = vcpkg_target.status_path.parent().unwrap();
 
     for line in file.lines() {
         let line = line.unwrap();
@@ -319,29 +651,187 @@ fn load_port_manifest(
                 dll.to_str().map(|s| dlls.push(s.to_owned()));
             }
         } else if let Ok(lib) = file_path.strip_prefix(&lib_prefix) {
-            if lib.extension() == Some(OsStr::new(&vcpkg_target.target_triplet.lib_suffix))
-                && lib.components().collect::<Vec<_>>().len() == 1
+            if file_path.parent() == Some(&pkgconfig_prefix)
+                && file_path.extension() == Some(OsStr::new("pc"))
             {
+                pc_file_paths.push(installed_path.join(file_path));
+                continue;
+            }
+            let components: Vec<_> = lib.components().collect();
+            if components.len() == 2 {
+                // libraries in subdirectories such as manual-link/SDL2main.lib are only
+                // linked when asked for, see `Config::include_manual_link` and
+                // `Config::extra_lib_subdirs`
+                let file_name = Path::new(components[1].as_os_str());
+                if file_name.to_str().map_or(false, |lib| vcpkg_target.is_lib_file(lib)) {
+                    if let (Some(subdir), Some(lib)) = (
+                        components[0].as_os_str().to_str(),
+                        vcpkg_target.link_name_for_lib(file_name),
+                    ) {
+                        subdir_libs
+                            .entry(subdir.to_owned())
+                            .or_insert_with(Vec::new)
+                            .push(lib);
+                    }
+                }
+                continue;
+            }
+            if components.len() != 1 {
+                continue;
+            }
+            if lib.to_str().map_or(false, |lib| vcpkg_target.is_lib_file(lib)) {
                 if let Some(lib) = vcpkg_target.link_name_for_lib(lib) {
                     libs.push(lib);
                 }
+            } else if !vcpkg_target.target_triplet.is_static {
+                // libfoo.so.1 and libfoo.so.1.2.3 are needed at runtime
+                match lib.to_str() {
+                    Some(lib) if is_versioned_shared_lib(lib) => dlls.push(lib.to_owned()),
+                    _ => {}
+                }
+            }
+        } else if let Ok(tool) = file_path.strip_prefix(&tool_prefix) {
+            // directories are listed with a trailing slash
+            let is_executable = match tool.extension() {
+                Some(ext) => ext == OsStr::new("exe"),
+                None => !line.ends_with('/'),
+            };
+            if is_executable {
+                tools.push(Path::new("tools").join(tool).to_string_lossy().into_owned());
+            }
+        } else if file_path.starts_with(&include_prefix) && !line.ends_with('/') {
+            has_headers = true;
+        }
+    }
+
+    // a port that only installs versioned shared objects is linked by the
+    // shortest name, which is usually the soname
+    let mut versioned_only: BTreeMap<String, String> = BTreeMap::new();
+    for dll in dlls.iter().filter(|dll| is_versioned_shared_lib(dll)) {
+        let unversioned = &dll[..dll.find(".so.").unwrap() + 3];
+        if libs.iter().any(|lib| lib == unversioned) {
+            continue;
+        }
+        let shortest = versioned_only
+            .entry(unversioned.to_owned())
+            .or_insert(dll.clone());
+        if dll.len() < shortest.len() {
+            *shortest = dll.clone();
+        }
+    }
+    libs.extend(versioned_only.into_iter().map(|(_, lib)| lib));
+
+    // dylibs have the version before the suffix, as in libfoo.1.dylib, and are
+    // linked the same way, by the shortest name if there is no libfoo.dylib
+    let (versioned, unversioned): (Vec<String>, Vec<String>) = libs
+        .into_iter()
+        .partition(|lib| unversioned_dylib(lib).is_some());
+    libs = unversioned;
+    let mut versioned_only: BTreeMap<String, String> = BTreeMap::new();
+    for dylib in versioned {
+        let unversioned = unversioned_dylib(&dylib).unwrap();
+        if !libs.contains(&unversioned) {
+            let shortest = versioned_only.entry(unversioned).or_insert(dylib.clone());
+            if dylib.len() < shortest.len() {
+                *shortest = dylib.clone();
             }
         }
+        dlls.push(dylib);
     }
+    libs.extend(versioned_only.into_iter().map(|(_, lib)| lib));
 
-    // Load .pc files for hints about intra-port library ordering.
-    let pkg_config_prefix = vcpkg_target
+    // Load .pc files for hints about intra-port library ordering, preferring the
+    // installed files listed in the manifest to those in the packages directory,
+    // which is often cleaned out.
+    let package_path = vcpkg_target
         .packages_path
-        .join(format!("{}_{}", port, vcpkg_target.target_triplet.name))
-        .join("lib")
-        .join("pkgconfig");
+        .join(format!("{}_{}", port_name, vcpkg_target.target_triplet.name));
+    let pc_files = if !pc_file_paths.is_empty() {
+        PcFiles::load_files(vcpkg_target, &pc_file_paths)
+    } else {
+        let pkg_config_prefix = package_path.join("lib").join("pkgconfig");
+        PcFiles::load_pkgconfig_dir(vcpkg_target, &pkg_config_prefix)
+    };
     // Try loading the pc files, if they are present. Not all ports have pkgconfig.
-    if let Ok(pc_files) = PcFiles::load_pkgconfig_dir(vcpkg_target, &pkg_config_prefix) {
+    let mut defines = Vec::new();
+    let mut include_dirs = Vec::new();
+    let mut system_libs = Vec::new();
+    let mut private_libs = Vec::new();
+    let mut fallbacks = Vec::new();
+    match pc_files {
+        Ok(ref pc_files) => {
+            let mut ids: Vec<&String> = pc_files.files.keys().collect();
+            ids.sort();
+            verbose::log(format_args!("read .pc files {:?} of port {}", ids, port_name));
+        }
+        Err(ref err) => {
+            verbose::log(format_args!("no .pc files for port {}: {}", port_name, err));
+            if !libs.is_empty() {
+                fallbacks.push(format!(
+                    "vcpkg port {} has no .pc files, so its libraries are linked in the order \
+                     of its file list, without the system libraries they need",
+                    port_name
+                ));
+            }
+        }
+    }
+    if let Ok(pc_files) = pc_files {
         // Use the .pc file data to potentially sort the libs to the correct order.
-        libs = pc_files.fix_ordering(libs);
+        let (ordered_libs, gave_up) = pc_files.fix_ordering_or_give_up(libs);
+        libs = ordered_libs;
+        if gave_up {
+            fallbacks.push(format!(
+                "vcpkg gave up ordering the libraries of port {} by its .pc files, so they \
+                 may be linked in the wrong order",
+                port_name
+            ));
+        }
+        verbose::log(format_args!(
+            "libraries of port {} in link order: {:?}",
+            port_name, libs
+        ));
+        defines = pc_files.defines();
+        system_libs = pc_files.system_libs(vcpkg_target, &libs);
+        private_libs = pc_files.private_libs();
+        // headers in the packages directory are installed to the triplet's directory
+        let installed_prefix = installed_path.join(&vcpkg_target.target_triplet.name);
+        for include_dir in pc_files.include_dirs() {
+            let include_dir = match include_dir.strip_prefix(&package_path) {
+                Ok(relative) => installed_prefix.join(relative),
+                Err(_) => include_dir,
+            };
+            if include_dir.is_dir()
+                && !same_dir(&include_dir, &vcpkg_target.include_path)
+                && !include_dirs.contains(&include_dir)
+            {
+                include_dirs.push(include_dir);
+            }
+        }
     }
 
-    Ok((dlls, libs))
+    port.dlls = dlls;
+    vcpkg_target.drop_shadowed_archives(&mut libs);
+    for subdir_libs in subdir_libs.values_mut() {
+        vcpkg_target.drop_shadowed_archives(subdir_libs);
+    }
+    port.libs = libs;
+    port.subdir_libs = subdir_libs;
+    port.tools = tools;
+    port.has_headers = has_headers;
+    port.defines = defines;
+    port.include_dirs = include_dirs;
+    port.system_libs = system_libs;
+    port.private_libs = private_libs;
+    port.fallbacks = fallbacks;
+    Ok(())
+}
+
+// whether two paths name the same directory, e.g. `lib/../include` and `include`
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
 }
 
 // load ports from the status file or one of the incremental updates
@@ -349,34 +839,38 @@ fn load_port_file(
     filename: &PathBuf,
     port_info: &mut Vec<BTreeMap<String, String>>,
 ) -> Result<(), Error> {
-    let f = File::open(&filename).map_err(|e| {
-        Error::VcpkgInstallation(format!(
-            "Could not open status file at {}: {}",
-            filename.display(),
-            e
-        ))
-    })?;
+    let f = File::open(&filename).map_err(|e| Error::io(filename, e))?;
     let file = BufReader::new(&f);
     let mut current: BTreeMap<String, String> = BTreeMap::new();
+    // the key of the last field, which continuation lines are added to
+    let mut last_key: Option<String> = None;
     for line in file.lines() {
-        let line = line.unwrap();
-        let parts = line.splitn(2, ": ").clone().collect::<Vec<_>>();
-        if parts.len() == 2 {
-            // a key: value line
-            current.insert(parts[0].trim().into(), parts[1].trim().into());
-        } else if line.len() == 0 {
+        let line = line.map_err(|e| Error::io(filename, e))?;
+        if line.trim().is_empty() {
             // end of section
-            port_info.push(current.clone());
-            current.clear();
-        } else {
-            // ignore all extension lines of the form
+            if !current.is_empty() {
+                port_info.push(current.clone());
+                current.clear();
+            }
+            last_key = None;
+        } else if line.starts_with(' ') || line.starts_with('\t') {
+            // a field can be folded onto lines that start with whitespace, as in
             //
-            // Description: a package with a
-            //   very long description
+            // Depends: vcpkg-cmake, vcpkg-cmake-config,
+            //   zlib
             //
-            // the description key is not used so this is harmless but
-            // this will eat extension lines for any multiline key which
-            // could become an issue in future
+            // and is unfolded by joining the lines with a space
+            if let Some(value) = last_key.as_ref().and_then(|key| current.get_mut(key)) {
+                if !value.is_empty() {
+                    value.push(' ');
+                }
+                value.push_str(line.trim());
+            }
+        } else if let Some(colon) = line.find(':') {
+            // a key: value line
+            let key = line[..colon].trim().to_owned();
+            current.insert(key.clone(), line[colon + 1..].trim().into());
+            last_key = Some(key);
         }
     }
 
@@ -387,9 +881,8 @@ fn load_port_file(
     Ok(())
 }
 
-pub(crate) fn load_ports(target: &VcpkgTarget) -> Result<BTreeMap<String, Port>, Error> {
-    let mut ports: BTreeMap<String, Port> = BTreeMap::new();
-
+/// Read the entries of the status database in `status_path`, oldest first.
+fn load_status_entries(status_path: &Path) -> Result<Vec<BTreeMap<String, String>>, Error> {
     let mut port_info: Vec<BTreeMap<String, String>> = Vec::new();
 
     // load the main status file. It is not an error if this file does not
@@ -397,26 +890,27 @@ pub(crate) fn load_ports(target: &VcpkgTarget) -> Result<BTreeMap<String, Port>,
     // is a single `vcpkg install package` then there will likely be no
     // status file, only incremental updates. This is the typical case when
     // running in a CI environment.
-    let status_filename = target.status_path.join("status");
-    load_port_file(&status_filename, &mut port_info).ok();
+    let status_filename = status_path.join("status");
+    let has_status_file = load_port_file(&status_filename, &mut port_info).is_ok();
 
     // load updates to the status file that have yet to be normalized
-    let status_update_dir = target.status_path.join("updates");
+    let status_update_dir = status_path.join("updates");
 
-    let paths = fs::read_dir(status_update_dir).map_err(|e| {
-        Error::VcpkgInstallation(format!("could not read status file updates dir: {}", e))
-    })?;
+    // a tree made by `vcpkg export` has a status file but no updates
+    let paths = if has_status_file && !status_update_dir.exists() {
+        Vec::new()
+    } else {
+        fs::read_dir(&status_update_dir)
+            .map_err(|e| Error::io(&status_update_dir, e))?
+            .collect()
+    };
 
     // get all of the paths of the update files into a Vec<PathBuf>
     let mut paths = paths
+        .into_iter()
         .map(|rde| rde.map(|de| de.path())) // Result<DirEntry, io::Error> -> Result<PathBuf, io::Error>
         .collect::<Result<Vec<_>, _>>() // collect into Result<Vec<PathBuf>, io::Error>
-        .map_err(|e| {
-            Error::VcpkgInstallation(format!(
-                "could not read status file update filenames: {}",
-                e
-            ))
-        })?;
+        .map_err(|e| Error::io(&status_update_dir, e))?;
 
     // Sort the paths and read them. This could be done directly from the iterator if
     // read_dir() guarantees that the files will be read in alpha order but that appears
@@ -427,6 +921,82 @@ pub(crate) fn load_ports(target: &VcpkgTarget) -> Result<BTreeMap<String, Port>,
         //       println!("Name: {}", path.display());
         load_port_file(&path, &mut port_info)?;
     }
+    Ok(port_info)
+}
+
+/// The `Status` that the status database in `status_path` last recorded for a
+/// port, by triplet.
+pub(crate) fn port_statuses(status_path: &Path, port_name: &str) -> BTreeMap<String, String> {
+    let mut statuses = BTreeMap::new();
+    for entry in load_status_entries(status_path).unwrap_or_default() {
+        match (entry.get("Package"), entry.get("Architecture")) {
+            (Some(pkg), Some(arch)) if pkg == port_name && !entry.contains_key("Feature") => {
+                let status = entry.get("Status").cloned().unwrap_or_default();
+                statuses.insert(arch.clone(), status);
+            }
+            _ => {}
+        }
+    }
+    statuses
+}
+
+/// The triplets that the status database in `status_path` has a port installed
+/// for, to suggest when it is not installed for the triplet that was looked in.
+pub(crate) fn installed_triplets(status_path: &Path, port_name: &str) -> Vec<String> {
+    port_statuses(status_path, port_name)
+        .into_iter()
+        .filter(|&(_, ref status)| status.ends_with(" installed"))
+        .map(|(arch, _)| arch)
+        .collect()
+}
+
+/// Whether a `Status` such as `install ok half-installed` shows that vcpkg was
+/// interrupted while installing or removing a port. The status is made of the
+/// wanted action, a flag and the state, and a port that is cleanly installed or
+/// removed is `install ok installed` or `purge ok not-installed`.
+pub(crate) fn is_broken_status(status: &str) -> bool {
+    let mut words = status.split_whitespace();
+    match (words.next(), words.next(), words.next()) {
+        (_, Some("ok"), Some("installed")) => false,
+        (Some("install"), Some("ok"), Some("not-installed")) => true,
+        (_, Some("ok"), Some("not-installed")) => false,
+        _ => true,
+    }
+}
+
+/// The name of the port in an entry of a `Depends` field, without the version
+/// constraint that newer versions of vcpkg may add, as in `zlib (>= 1.2.13)`.
+fn strip_version_qualifier(dep: &str) -> &str {
+    dep.split('(').next().unwrap().trim()
+}
+
+/// The port that an entry of a `Depends` field refers to in `triplet`. An entry
+/// qualified with a triplet, such as `pkgconf:x64-windows` for a tool that is
+/// built for the host, is left out unless that is `triplet`.
+fn dep_for_triplet<'a>(dep: &'a str, triplet: &str) -> Option<&'a str> {
+    let dep = strip_version_qualifier(dep);
+    let mut parts = dep.splitn(2, ':');
+    let name = parts.next().unwrap().trim();
+    match parts.next() {
+        _ if name.is_empty() => None,
+        Some(dep_triplet) if dep_triplet.trim() != triplet => None,
+        _ => Some(name),
+    }
+}
+
+/// The port that an entry of a `Depends` field refers to if it is qualified with
+/// the host triplet `host` rather than `triplet`, as tools that are run while
+/// building the port are.
+fn host_dep_for_triplet<'a>(dep: &'a str, triplet: &str, host: &str) -> Option<&'a str> {
+    if host == triplet || !strip_version_qualifier(dep).contains(':') {
+        return None;
+    }
+    dep_for_triplet(dep, host)
+}
+
+pub(crate) fn load_ports(target: &VcpkgTarget) -> Result<BTreeMap<String, Port>, Error> {
+    let mut ports: BTreeMap<String, Port> = BTreeMap::new();
+    let port_info = load_status_entries(&target.status_path)?;
     //println!("{:#?}", port_info);
 
     let mut seen_names = BTreeMap::new();
@@ -447,10 +1017,23 @@ pub(crate) fn load_ports(target: &VcpkgTarget) -> Result<BTreeMap<String, Port>,
     for (&(name, arch, feature), current) in &seen_names {
         if arch.as_str() == target.target_triplet.name {
             let mut deps = if let Some(deps) = current.get("Depends") {
-                deps.split(", ").map(|x| x.to_owned()).collect()
+                deps.split(',')
+                    .filter_map(|dep| dep_for_triplet(dep, &target.target_triplet.name))
+                    .map(|dep| dep.to_owned())
+                    .collect()
             } else {
                 Vec::new()
             };
+            let host_deps: Vec<String> = match (current.get("Depends"), &target.host_triplet) {
+                (Some(deps), &Some(ref host)) => deps
+                    .split(',')
+                    .filter_map(|dep| {
+                        host_dep_for_triplet(dep, &target.target_triplet.name, host)
+                    })
+                    .map(|dep| dep.to_owned())
+                    .collect(),
+                _ => Vec::new(),
+            };
 
             if current
                 .get("Status")
@@ -459,30 +1042,54 @@ pub(crate) fn load_ports(target: &VcpkgTarget) -> Result<BTreeMap<String, Port>,
             {
                 match (current.get("Version"), feature) {
                     (Some(version), _) => {
-                        // this failing here and bailing out causes everything to fail
-                        let lib_info =
-                            load_port_manifest(&target.status_path, &name, version, &target)?;
-                        let port = Port {
-                            dlls: lib_info.0,
-                            libs: lib_info.1,
-                            deps,
-                        };
-
-                        ports.insert(name.to_string(), port);
+                        // the manifest is only read if the port is needed, see
+                        // `load_port_manifest`
+                        let manifest = port_manifest_path(
+                            &target.status_path,
+                            &name,
+                            version,
+                            &target.target_triplet.name,
+                        );
+                        let default_features = current
+                            .get("Default-Features")
+                            .map(|features| {
+                                features
+                                    .split(',')
+                                    .map(|feature| feature.trim().to_owned())
+                                    .filter(|feature| !feature.is_empty())
+                                    .collect()
+                            })
+                            .unwrap_or_default();
+                        ports.insert(
+                            name.to_string(),
+                            Port {
+                                version: version.clone(),
+                                deps,
+                                host_deps,
+                                default_features,
+                                manifest,
+                                ..Port::default()
+                            },
+                        );
                     }
-                    (_, Some(_feature)) => match ports.get_mut(name) {
+                    (_, Some(feature)) => match ports.get_mut(name) {
                         Some(ref mut port) => {
-                            port.deps.append(&mut deps);
+                            port.features.insert(feature.clone(), deps);
+                            port.host_deps.extend(host_deps);
                         }
                         _ => {
-                            println!("found a feature that had no corresponding port :-");
-                            println!("current {:+?}", current);
+                            verbose::log(format_args!(
+                                "ignoring feature {} of port {}, which is not installed",
+                                feature, name
+                            ));
                             continue;
                         }
                     },
                     (_, _) => {
-                        println!("didn't know how to deal with status file entry :-");
-                        println!("{:+?}", current);
+                        verbose::log(format_args!(
+                            "ignoring a status database entry without a version: {:?}",
+                            current
+                        ));
                         continue;
                     }
                 }
@@ -512,113 +1119,72 @@ pub(crate) fn envify(name: &str) -> String {
         .collect()
 }
 
+/// Whether the environment forces the dynamic triplets, `Some(true)`, with
+/// `VCPKGRS_DYNAMIC`, or the static ones, `Some(false)`, with `VCPKGRS_STATIC` or
+/// `VCPKGRS_DYNAMIC=0`. `VCPKGRS_STATIC` wins if both are set.
+///
+/// `None` means neither is set, so the linkage is decided per package.
+pub fn forced_dynamic() -> Option<bool> {
+    if env::var_os(VCPKGRS_STATIC).is_some() {
+        return Some(false);
+    }
+    env::var_os(VCPKGRS_DYNAMIC).map(|value| value != "0")
+}
+
+/// Has linking to DLL builds been opted into for all of `port_names`, with
+/// `VCPKGRS_DYNAMIC` or `VCPKGRS_DYNAMIC_<PACKAGE>`?
+pub(crate) fn dynamic_allowed(port_names: &[&str]) -> bool {
+    forced_dynamic() == Some(true)
+        || port_names.iter().all(|port_name| {
+            env::var_os(format!("{}{}", prefix::VCPKGRS_DYNAMIC_, envify(port_name))).is_some()
+        })
+}
+
 pub(crate) fn msvc_target() -> Result<VcpkgTriplet, Error> {
-    let is_definitely_dynamic = env::var(VCPKGRS_DYNAMIC).is_ok();
+    env_default_triplet(forced_dynamic() == Some(true))
+}
+
+/// The default triplet for the target cargo is building for.
+pub(crate) fn env_default_triplet(is_definitely_dynamic: bool) -> Result<VcpkgTriplet, Error> {
     let target = env::var(TARGET).unwrap_or(String::new());
     let is_static = env::var(CARGO_CFG_TARGET_FEATURE)
         .unwrap_or(String::new()) // rustc 1.10
         .contains("crt-static");
-    if target == "x86_64-apple-darwin" {
-        Ok(VcpkgTriplet {
-            name: "x64-osx".into(),
-            is_static: true,
-            lib_suffix: "a".into(),
-            strip_lib_prefix: true,
-        })
-    } else if target == "aarch64-apple-darwin" {
-        Ok(VcpkgTriplet {
-            name: "arm64-osx".into(),
-            is_static: true,
-            lib_suffix: "a".into(),
-            strip_lib_prefix: true,
-        })
-    } else if target == "x86_64-unknown-linux-gnu" {
-        Ok(VcpkgTriplet {
-            name: "x64-linux".into(),
-            is_static: true,
-            lib_suffix: "a".into(),
-            strip_lib_prefix: true,
-        })
-    } else if target == "aarch64-apple-ios" {
-        Ok(VcpkgTriplet {
-            name: "arm64-ios".into(),
-            is_static: true,
-            lib_suffix: "a".into(),
-            strip_lib_prefix: true,
-        })
-    } else if !target.contains("-pc-windows-msvc") {
-        Err(Error::NotMSVC)
-    } else if target.starts_with("x86_64-") {
-        if is_static {
-            Ok(VcpkgTriplet {
-                name: "x64-windows-static".into(),
-                is_static: true,
-                lib_suffix: "lib".into(),
-                strip_lib_prefix: false,
-            })
-        } else if is_definitely_dynamic {
-            Ok(VcpkgTriplet {
-                name: "x64-windows".into(),
-                is_static: false,
-                lib_suffix: "lib".into(),
-                strip_lib_prefix: false,
-            })
-        } else {
-            Ok(VcpkgTriplet {
-                name: "x64-windows-static-md".into(),
-                is_static: true,
-                lib_suffix: "lib".into(),
-                strip_lib_prefix: false,
-            })
-        }
-    } else if target.starts_with("aarch64") {
-        if is_static {
-            Ok(VcpkgTriplet {
-                name: "arm64-windows-static".into(),
-                is_static: true,
-                lib_suffix: "lib".into(),
-                strip_lib_prefix: false,
-            })
-        } else if is_definitely_dynamic {
-            Ok(VcpkgTriplet {
-                name: "arm64-windows".into(),
-                is_static: false,
-                lib_suffix: "lib".into(),
-                strip_lib_prefix: false,
-            })
-        } else {
-            Ok(VcpkgTriplet {
-                name: "arm64-windows-static-md".into(),
-                is_static: true,
-                lib_suffix: "lib".into(),
-                strip_lib_prefix: false,
-            })
-        }
-    } else {
-        // everything else is x86
-        if is_static {
-            Ok(VcpkgTriplet {
-                name: "x86-windows-static".into(),
-                is_static: true,
-                lib_suffix: "lib".into(),
-                strip_lib_prefix: false,
-            })
-        } else if is_definitely_dynamic {
-            Ok(VcpkgTriplet {
-                name: "x86-windows".into(),
-                is_static: false,
-                lib_suffix: "lib".into(),
-                strip_lib_prefix: false,
-            })
-        } else {
-            Ok(VcpkgTriplet {
-                name: "x86-windows-static-md".into(),
-                is_static: true,
-                lib_suffix: "lib".into(),
-                strip_lib_prefix: false,
-            })
-        }
+    default_triplet(&target, is_static, is_definitely_dynamic)
+}
+
+/// The triplet of the glibc target corresponding to a musl target, such as `x64-linux`
+/// for `x86_64-unknown-linux-musl`, see `Config::allow_musl_fallback`.
+pub(crate) fn musl_fallback_target() -> Result<VcpkgTriplet, Error> {
+    let target = env::var(TARGET).unwrap_or(String::new());
+    if !target.contains("-linux-musl") {
+        return Err(Error::NotMSVC);
+    }
+    default_triplet(&target.replace("-linux-musl", "-linux-gnu"), true, false)
+}
+
+/// The triplet that vcpkg builds tools for, from `VCPKG_DEFAULT_HOST_TRIPLET` or
+/// like vcpkg does for the rust host, which is the dynamic triplet on Windows.
+pub(crate) fn default_host_triplet() -> Option<VcpkgTriplet> {
+    if let Ok(triplet) = env::var(VCPKG_DEFAULT_HOST_TRIPLET) {
+        return Some(triplet.into());
     }
+    let host = env::var(HOST).ok()?;
+    default_triplet(&host, false, host.contains("-windows-msvc")).ok()
+}
+
+/// The triplet vcpkg-rs selects for a rust target when none is configured.
+///
+/// `is_static` corresponds to the `crt-static` target feature and
+/// `is_definitely_dynamic` to `VCPKGRS_DYNAMIC`.
+pub(crate) fn default_triplet(
+    target: &str,
+    is_static: bool,
+    is_definitely_dynamic: bool,
+) -> Result<VcpkgTriplet, Error> {
+    Triplet::for_rust_target(target, is_static, is_definitely_dynamic)
+        .map(|triplet| VcpkgTriplet::from(triplet.to_string()))
+        .ok_or(Error::NotMSVC)
 }
 
 #[cfg(test)]
@@ -629,12 +1195,15 @@ mod tests {
     use super::*;
     use std::env;
     use std::sync::Mutex;
+    use std::process::Command;
     use self::tempfile::tempdir;
 
     use env_vars::prelude::*;
 
     lazy_static! {
-        static ref LOCK: Mutex<()> = Mutex::new(());
+        /// Serializes the tests that set environment variables, including those
+        /// in the test modules of other files.
+        pub(crate) static ref LOCK: Mutex<()> = Mutex::new(());
     }
 
     #[test]
@@ -644,14 +1213,14 @@ mod tests {
 
         let _g = LOCK.lock();
         env::set_var(VCPKG_ROOT, "/");
-        env::set_var(TARGET, "x86_64-pc-windows-gnu");
+        env::set_var(TARGET, "wasm32-unknown-unknown");
         assert!(match ::probe_package("foo") {
             Err(Error::NotMSVC) => true,
             _ => false,
         });
 
-        env::set_var(TARGET, "x86_64-pc-windows-gnu");
-        assert_eq!(env::var(TARGET), Ok("x86_64-pc-windows-gnu".to_string()));
+        env::set_var(TARGET, "wasm32-unknown-unknown");
+        assert_eq!(env::var(TARGET), Ok("wasm32-unknown-unknown".to_string()));
         assert!(match ::probe_package("foo") {
             Err(Error::NotMSVC) => true,
             _ => false,
@@ -660,6 +1229,28 @@ mod tests {
         env::remove_var(VCPKG_ROOT);
     }
 
+    #[test]
+    fn community_unix_targets() {
+        let _g = LOCK.lock();
+        clean_env();
+        for &(target, triplet) in &[
+            ("x86_64-unknown-haiku", "x64-haiku"),
+            ("x86_64-unknown-illumos", "x64-illumos"),
+            ("x86_64-pc-solaris", "x64-solaris"),
+            ("x86_64-unknown-freebsd", "x64-freebsd"),
+            ("x86_64-unknown-openbsd", "x64-openbsd"),
+        ] {
+            env::set_var(TARGET, target);
+            let vcpkg_triplet = msvc_target().unwrap();
+            assert_eq!(vcpkg_triplet.name, triplet);
+            assert!(vcpkg_triplet.is_static);
+            assert_eq!(vcpkg_triplet.lib_suffix, "a");
+            assert!(vcpkg_triplet.strip_lib_prefix);
+            assert_eq!(compatibility(target, triplet), Compatibility::KnownGood);
+        }
+        clean_env();
+    }
+
     #[test]
     fn do_nothing_for_bailout_variables_set() {
         let _g = LOCK.lock();
@@ -706,7 +1297,7 @@ mod tests {
         env::set_var(VCPKG_ROOT, vcpkg_test_tree_loc("normalized"));
         env::set_var(TARGET, "x86_64-pc-windows-msvc");
         let tmp_dir = tempdir().unwrap();
-        env::set_var(VCPKG_ROOT, tmp_dir.path());
+        env::set_var(OUT_DIR, tmp_dir.path());
 
         // CARGO_CFG_TARGET_FEATURE is set in response to
         // RUSTFLAGS=-Ctarget-feature=+crt-static. It would
@@ -756,6 +1347,181 @@ mod tests {
         clean_env();
     }
 
+    #[test]
+    fn handle_multiline_depends() {
+        let _g = LOCK.lock();
+        clean_env();
+        let root = vcpkg_test_tree_loc("multiline-depends");
+        env::set_var(VCPKG_ROOT, &root);
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        let tmp_dir = tempdir().unwrap();
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let entries = load_status_entries(&root.join("installed").join("vcpkg")).unwrap();
+        assert_eq!(entries.len(), 3);
+        let libpng = &entries[2];
+        assert_eq!(libpng["Depends"], "bzip2, zlib");
+        assert!(libpng["Description"].ends_with(
+            "(Portable Network Graphics) format files. \
+             note: this continuation line looks like a field"
+        ));
+        assert!(!libpng.contains_key("note"));
+        assert_eq!(libpng["Status"], "install ok installed");
+
+        let lib = ::find_package("libpng").unwrap();
+        assert_eq!(lib.ports, vec!["libpng", "zlib", "bzip2"]);
+        assert_eq!(lib.found_names, vec!["png16", "z", "bz2"]);
+        clean_env();
+    }
+
+    #[test]
+    fn depends_version_qualifiers() {
+        let _g = LOCK.lock();
+        clean_env();
+        assert_eq!(strip_version_qualifier(" zlib (>= 1.2.13)"), "zlib");
+        assert_eq!(strip_version_qualifier("bzip2(=1.0.8)"), "bzip2");
+        assert_eq!(strip_version_qualifier(" zlib"), "zlib");
+
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-linux",
+            &[
+                ("foo", "1.0", &["lib/libfoo.a"]),
+                ("bar", "2.1", &["lib/libbar.a"]),
+                ("baz", "0.3", &["lib/libbaz.a"]),
+            ],
+        );
+        set_status_fields(&root, "foo", &["Depends: bar (>= 2.0), baz(=0.3)"]);
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("foo")
+            .unwrap();
+        assert_eq!(lib.ports, vec!["foo", "baz", "bar"]);
+        assert_eq!(lib.found_names, vec!["foo", "baz", "bar"]);
+        clean_env();
+    }
+
+    #[test]
+    fn triplet_qualified_depends() {
+        let _g = LOCK.lock();
+        clean_env();
+        assert_eq!(dep_for_triplet(" zlib", "x64-linux"), Some("zlib"));
+        assert_eq!(dep_for_triplet(" zlib:x64-linux", "x64-linux"), Some("zlib"));
+        assert_eq!(dep_for_triplet(" pkgconf:x64-windows", "x64-linux"), None);
+        assert_eq!(dep_for_triplet(" zlib:x64-linux (>= 1.2)", "x64-linux"), Some("zlib"));
+        assert_eq!(dep_for_triplet(" ", "x64-linux"), None);
+
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-linux",
+            &[
+                ("foo", "1.0", &["lib/libfoo.a"]),
+                ("bar", "2.1", &["lib/libbar.a"]),
+            ],
+        );
+        set_status_fields(&root, "foo", &["Depends: bar:x64-linux, pkgconf:x64-windows"]);
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .warn_on_fallback(true)
+            .find_package("foo")
+            .unwrap();
+        assert_eq!(lib.ports, vec!["foo", "bar"]);
+        assert!(!lib.cargo_metadata.iter().any(|line| line.contains("pkgconf")));
+        clean_env();
+    }
+
+    #[test]
+    fn host_triplet() {
+        let _g = LOCK.lock();
+        clean_env();
+        let host = |cfg: &::Config| cfg.get_host_triplet().map(|triplet| triplet.name);
+        assert_eq!(host(&::Config::new()), None);
+        env::set_var(HOST, "x86_64-pc-windows-msvc");
+        assert_eq!(host(&::Config::new()), Some("x64-windows".to_owned()));
+        env::set_var(HOST, "x86_64-unknown-linux-gnu");
+        assert_eq!(host(&::Config::new()), Some("x64-linux".to_owned()));
+        env::set_var(VCPKG_DEFAULT_HOST_TRIPLET, "arm64-osx");
+        assert_eq!(host(&::Config::new()), Some("arm64-osx".to_owned()));
+        assert_eq!(
+            host(::Config::new().host_triplet("x64-windows")),
+            Some("x64-windows".to_owned())
+        );
+
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "arm64-linux",
+            &[
+                ("foo", "1.0", &["lib/libfoo.a"]),
+                ("bar", "2.1", &["lib/libbar.a"]),
+            ],
+        );
+        set_status_fields(
+            &root,
+            "foo",
+            &["Depends: bar, vcpkg-cmake:arm64-osx, ninja:x64-windows"],
+        );
+        let mut cfg = ::Config::new();
+        cfg.vcpkg_root(root.clone());
+        let target = find_vcpkg_target(&cfg, &"arm64-linux".into()).unwrap();
+        assert_eq!(target.host_triplet, Some("arm64-osx".to_owned()));
+        let ports = load_ports(&target).unwrap();
+        assert_eq!(ports["foo"].deps, vec!["bar"]);
+        assert_eq!(ports["foo"].host_deps, vec!["vcpkg-cmake"]);
+        clean_env();
+    }
+
+    #[test]
+    fn skip_build_tool_ports() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-linux",
+            &[
+                ("foo", "1.0", &["lib/libfoo.a"]),
+                (
+                    "vcpkg-cmake",
+                    "2023-05-04",
+                    &["share/vcpkg-cmake/vcpkg_cmake_build.cmake"],
+                ),
+                ("eigen3", "3.4.0", &["include/eigen3/Eigen/Core"]),
+            ],
+        );
+        set_status_fields(&root, "foo", &["Depends: vcpkg-cmake, eigen3"]);
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("foo")
+            .unwrap();
+        // the header only eigen3 is kept, but not vcpkg-cmake
+        assert_eq!(lib.ports, vec!["foo", "eigen3"]);
+        assert!(!lib.port_versions.contains_key("vcpkg-cmake"));
+
+        // a build tool is still found when it is asked for
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("vcpkg-cmake")
+            .unwrap();
+        assert_eq!(lib.ports, vec!["vcpkg-cmake"]);
+        clean_env();
+    }
+
     #[test]
     fn link_libs_required_by_optional_features() {
         let _g = LOCK.lock();
@@ -808,6 +1574,92 @@ mod tests {
         }
     }
 
+    #[test]
+    fn strip_lib_prefix() {
+        let _g = LOCK.lock();
+        for &(target, triplet) in &[
+            ("x86_64-apple-darwin", "x64-osx"),
+            ("x86_64-unknown-linux-gnu", "x64-linux"),
+        ] {
+            clean_env();
+            let tmp_dir = tempdir().unwrap();
+            let root = tmp_dir.path().join("vcpkg");
+            make_vcpkg_tree(
+                &root,
+                triplet,
+                &[
+                    ("libpng", "1.6.43", &["lib/libpng16.a"]),
+                    ("liblzma", "5.4.4", &["lib/liblzma.a"]),
+                    ("libfoo", "1.0", &["lib/liblibfoo.a", "lib/foo_static.a"]),
+                ],
+            );
+            env::set_var(TARGET, target);
+            env::set_var(OUT_DIR, tmp_dir.path());
+
+            let names = |cfg: &mut ::Config, port: &str| {
+                cfg.vcpkg_root(root.clone())
+                    .find_package(port)
+                    .unwrap()
+                    .cargo_metadata
+                    .into_iter()
+                    .filter(|line| line.starts_with("cargo:rustc-link-lib="))
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(
+                names(&mut ::Config::new(), "libpng"),
+                vec!["cargo:rustc-link-lib=png16"]
+            );
+            assert_eq!(
+                names(&mut ::Config::new(), "liblzma"),
+                vec!["cargo:rustc-link-lib=lzma"]
+            );
+            // one prefix is taken off, and a library without one is linked by its file name
+            assert_eq!(
+                names(&mut ::Config::new(), "libfoo"),
+                vec![
+                    "cargo:rustc-link-lib=libfoo",
+                    "cargo:rustc-link-lib=static:+verbatim=foo_static.a",
+                ]
+            );
+            assert_eq!(
+                names(::Config::new().strip_lib_prefix(false), "libpng"),
+                vec!["cargo:rustc-link-lib=libpng16"]
+            );
+            clean_env();
+        }
+    }
+
+    #[test]
+    fn explicit_link_kind() {
+        let _g = LOCK.lock();
+        clean_env();
+        env::set_var(VCPKG_ROOT, vcpkg_test_tree_loc("normalized"));
+        let tmp_dir = tempdir().unwrap();
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let link_libs = |target: &str| {
+            env::set_var(TARGET, target);
+            ::Config::new()
+                .explicit_link_kind(true)
+                .find_package("zlib")
+                .unwrap()
+                .cargo_metadata
+                .into_iter()
+                .filter(|line| line.starts_with("cargo:rustc-link-lib="))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            link_libs("x86_64-apple-darwin"),
+            vec!["cargo:rustc-link-lib=static=z"]
+        );
+        env::set_var(VCPKGRS_DYNAMIC, "1");
+        assert_eq!(
+            link_libs("i686-pc-windows-msvc"),
+            vec!["cargo:rustc-link-lib=dylib=zlib"]
+        );
+        clean_env();
+    }
+
     #[test]
     fn link_dependencies_after_port() {
         let _g = LOCK.lock();
@@ -824,8 +1676,20 @@ mod tests {
         check_before(&lib, "freetype", "bzip2");
         check_before(&lib, "freetype", "libpng");
         check_before(&lib, "harfbuzz", "freetype");
-        check_before(&lib, "harfbuzz", "ragel");
         check_before(&lib, "libpng", "zlib");
+        // ragel is a tool that harfbuzz is built with
+        assert!(!lib.ports.contains(&"ragel".to_owned()));
+
+        // every port's libraries are attributed to it, in the order they are linked
+        let zlib_libs = &lib.port_libs["zlib"];
+        assert!(zlib_libs.iter().all(|l| lib.found_libs.contains(l)));
+        assert!(zlib_libs.iter().any(|l| l.ends_with("zlib.lib")));
+        let attributed: Vec<&PathBuf> = lib
+            .ports
+            .iter()
+            .flat_map(|port| lib.port_libs[port].iter())
+            .collect();
+        assert_eq!(attributed, lib.found_libs.iter().collect::<Vec<_>>());
 
         clean_env();
 
@@ -849,7 +1713,3273 @@ mod tests {
     }
 
     #[test]
-    fn custom_target_triplet_in_config() {
+    fn sys_crate_conflicts() {
+        let _g = LOCK.lock();
+        clean_env();
+        env::set_var(VCPKG_ROOT, vcpkg_test_tree_loc("normalized"));
+        env::set_var(TARGET, "i686-pc-windows-msvc");
+        env::set_var(VCPKGRS_DYNAMIC, "1");
+        let tmp_dir = tempdir().unwrap();
+        env::set_var(OUT_DIR, tmp_dir.path());
+        // as if libz-sys was a dependency of the crate being built
+        env::set_var("DEP_Z_INCLUDE", "/somewhere/include");
+
+        // nothing is done about conflicts by default
+        let lib = ::find_package("harfbuzz").unwrap();
+        assert!(lib.ports.iter().any(|p| p == "zlib"));
+        assert!(!lib
+            .cargo_metadata
+            .iter()
+            .any(|x| x.starts_with("cargo:warning=vcpkg port zlib")));
+
+        let lib = ::Config::new()
+            .conflict_policy(::ConflictPolicy::Warn)
+            .find_package("harfbuzz")
+            .unwrap();
+        assert!(lib.ports.iter().any(|p| p == "zlib"));
+        assert!(lib
+            .cargo_metadata
+            .iter()
+            .any(|x| x.starts_with("cargo:warning=vcpkg port zlib")));
+
+        let lib = ::Config::new()
+            .conflict_policy(::ConflictPolicy::Skip)
+            .find_package("harfbuzz")
+            .unwrap();
+        assert!(!lib.ports.iter().any(|p| p == "zlib"));
+        assert!(!lib
+            .cargo_metadata
+            .iter()
+            .any(|x| x == "cargo:rustc-link-lib=zlib"));
+        assert!(lib
+            .cargo_metadata
+            .iter()
+            .any(|x| x == "cargo:rustc-link-lib=harfbuzz"));
+
+        // the requested port is never skipped
+        let lib = ::Config::new()
+            .conflict_policy(::ConflictPolicy::Skip)
+            .find_package("zlib")
+            .unwrap();
+        assert_eq!(lib.ports, vec!["zlib"]);
+
+        // libz-sys found zlib in the same installed tree
+        let include = vcpkg_test_tree_loc("normalized").join("installed/x86-windows/include");
+        env::set_var("DEP_Z_INCLUDE", &include);
+        let lib = ::Config::new()
+            .conflict_policy(::ConflictPolicy::Skip)
+            .find_package("harfbuzz")
+            .unwrap();
+        assert!(lib.ports.iter().any(|p| p == "zlib"));
+
+        clean_env();
+    }
+
+    #[test]
+    fn bundle_static_libs() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let a = tmp_dir.path().join("liba.a");
+        let b = tmp_dir.path().join("libb.a");
+        let bundled = tmp_dir.path().join("libmerged.a");
+        assert_eq!(
+            bundle::mri_script(&bundled, &[a.clone(), b.clone()]),
+            format!(
+                "create {}\naddlib {}\naddlib {}\nsave\nend\n",
+                bundled.display(),
+                a.display(),
+                b.display()
+            )
+        );
+
+        // an empty archive is a valid input for ar
+        for path in &[&a, &b] {
+            fs::write(path, "!<arch>\n").unwrap();
+        }
+        let mut lib = Library::new(true, "x64-linux");
+        lib.cargo_metadata.push("cargo:rustc-link-lib=a".to_owned());
+        lib.cargo_metadata.push("cargo:rustc-link-lib=b".to_owned());
+        // system libraries stay after the bundle
+        lib.cargo_metadata.push("cargo:rustc-link-lib=pthread".to_owned());
+        lib.found_libs = vec![a, b];
+        lib.found_names = vec!["a".to_owned(), "b".to_owned()];
+        let triplet: VcpkgTriplet = "x64-linux".into();
+        bundle::bundle_libs(&mut lib, "merged", &triplet).unwrap();
+
+        assert!(bundled.exists());
+        assert_eq!(lib.bundled_lib, Some(bundled));
+        assert_eq!(
+            lib.cargo_metadata,
+            vec![
+                format!(
+                    "cargo:rustc-link-search=native={}",
+                    tmp_dir.path().display()
+                ),
+                "cargo:rustc-link-lib=static=merged".to_owned(),
+                "cargo:rustc-link-lib=pthread".to_owned(),
+            ]
+        );
+
+        // lib.exe is not on the PATH outside of a developer prompt
+        let triplet: VcpkgTriplet = "x64-windows-static".into();
+        env::set_var(TARGET, "x86_64-pc-windows-msvc");
+        let path = env::var_os("PATH");
+        env::set_var("PATH", tmp_dir.path());
+        let result = bundle::bundle_libs(&mut lib, "merged", &triplet);
+        match path {
+            Some(path) => env::set_var("PATH", path),
+            None => env::remove_var("PATH"),
+        }
+        match result {
+            Err(Error::CommandFailed(ref msg)) if msg.contains("VCPKGRS_LIB") => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        env::set_var(VCPKGRS_LIB, tmp_dir.path().join("no-lib.exe"));
+        match bundle::bundle_libs(&mut lib, "merged", &triplet) {
+            Err(Error::CommandFailed(ref msg)) if msg.starts_with("could not bundle") => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        clean_env();
+    }
+
+    #[test]
+    fn bundle_paths_with_spaces() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let out_dir = tmp_dir.path().join("out dir");
+        let lib_dir = tmp_dir.path().join("Program Files").join("lib");
+        fs::create_dir_all(&out_dir).unwrap();
+        fs::create_dir_all(&lib_dir).unwrap();
+        env::set_var(OUT_DIR, &out_dir);
+
+        let mut lib = Library::new(true, "x64-linux");
+        for name in &["liba.a", "libb.a"] {
+            fs::write(lib_dir.join(name), "!<arch>\n").unwrap();
+            lib.found_libs.push(lib_dir.join(name));
+        }
+        let triplet: VcpkgTriplet = "x64-linux".into();
+        bundle::bundle_libs(&mut lib, "merged", &triplet).unwrap();
+
+        assert_eq!(lib.bundled_lib, Some(out_dir.join("libmerged.a")));
+        assert!(out_dir.join("libmerged.a").exists());
+        assert!(!out_dir.join("vcpkg-bundle").exists());
+        assert_eq!(
+            lib.cargo_metadata[0],
+            format!("cargo:rustc-link-search=native={}", out_dir.display())
+        );
+        clean_env();
+    }
+
+    #[test]
+    fn vcpkg_root_with_spaces() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("Program Files").join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-windows",
+            &[("zlib", "1.2.11", &["lib/zlib.lib", "bin/zlib1.dll"])],
+        );
+        let out_dir = tmp_dir.path().join("out dir");
+        fs::create_dir_all(&out_dir).unwrap();
+        env::set_var(TARGET, "x86_64-pc-windows-msvc");
+        env::set_var(VCPKGRS_DYNAMIC, "1");
+        env::set_var(OUT_DIR, &out_dir);
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("zlib")
+            .unwrap();
+
+        // cargo takes everything after `native=` as the path, so it must not be quoted
+        let lib_dir = root.join("installed").join("x64-windows").join("lib");
+        let bin_dir = root.join("installed").join("x64-windows").join("bin");
+        for dir in &[&lib_dir, &bin_dir, &out_dir] {
+            assert!(lib
+                .cargo_metadata
+                .contains(&format!("cargo:rustc-link-search=native={}", dir.display())));
+        }
+        for directive in &lib.directives() {
+            if let MetadataDirective::LinkSearch { ref path, .. } = *directive {
+                assert!(path.is_dir(), "{} is not a directory", path.display());
+            }
+        }
+        assert_eq!(lib.found_libs, vec![lib_dir.join("zlib.lib")]);
+        assert!(out_dir.join("zlib1.dll").exists());
+        clean_env();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_vcpkg_root() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join(OsStr::from_bytes(b"vcpkg\xff"));
+        make_vcpkg_tree(&root, "x64-linux", &[("zlib", "1.2.11", &["lib/libz.a"])]);
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        match ::Config::new().vcpkg_root(root.clone()).find_package("zlib") {
+            Err(Error::NonUtf8Path(ref path)) => assert!(path.starts_with(&root)),
+            other => panic!("unexpected result {:?}", other),
+        }
+        clean_env();
+    }
+
+    #[test]
+    fn stale_installation() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path();
+        let status_path = root.join("installed").join("vcpkg");
+        fs::create_dir_all(status_path.join("updates")).unwrap();
+        fs::create_dir_all(status_path.join("info")).unwrap();
+        fs::write(root.join(".vcpkg-root"), "").unwrap();
+        fs::write(
+            status_path.join("updates").join("0000000001"),
+            "Package: zlib\nVersion: 1.2.13\nArchitecture: x64-linux\n\
+             Status: install ok installed\n",
+        )
+        .unwrap();
+        // the upgrade wrote the new manifest but never got to the status database
+        fs::write(
+            status_path.join("info").join("zlib_1.2.11_x64-linux.list"),
+            "x64-linux/lib/libz.a\n",
+        )
+        .unwrap();
+        let manifest = status_path.join("info").join("zlib_1.2.13_x64-linux.list");
+        fs::write(&manifest, "x64-linux/lib/libz.a\n").unwrap();
+        // a stray manifest without a version is not taken for one
+        fs::write(status_path.join("info").join("zlib_x64-linux.list"), "").unwrap();
+        let later = std::time::SystemTime::now() + std::time::Duration::from_secs(60);
+        File::options()
+            .write(true)
+            .open(&manifest)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+
+        let target = VcpkgTarget {
+            lib_path: root.join("installed").join("x64-linux").join("lib"),
+            bin_path: root.join("installed").join("x64-linux").join("bin"),
+            include_path: root.join("installed").join("x64-linux").join("include"),
+            status_path: status_path.clone(),
+            packages_path: root.join("packages"),
+            target_triplet: "x64-linux".into(),
+            is_debug: false,
+            host_triplet: None,
+        };
+        let ports = load_ports(&target).unwrap();
+        let warnings = stale::stale_installation_warnings(&target, &ports);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("manifests for 1.2.11 are also present"));
+        assert!(warnings[1].contains("is newer than the status database"));
+
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        match ::Config::new()
+            .vcpkg_root(root.to_path_buf())
+            .find_package("zlib")
+        {
+            Err(Error::LibNotFound { detail, .. }) => assert!(detail.contains("partially upgraded")),
+            other => panic!("unexpected result {:?}", other),
+        }
+        clean_env();
+    }
+
+    #[test]
+    fn target_triplet_compatibility() {
+        use target_triplet::check_compatibility;
+
+        assert_eq!(
+            compatibility("i686-pc-windows-msvc", "x86-windows-static-md"),
+            Compatibility::KnownGood
+        );
+        assert_eq!(
+            compatibility("x86_64-pc-windows-msvc", "x64-windows"),
+            Compatibility::KnownGood
+        );
+        assert_eq!(
+            compatibility("x86_64-pc-windows-msvc", "x64-windows-static"),
+            Compatibility::Mismatched(Mismatch::Linkage)
+        );
+        assert_eq!(
+            check_compatibility(
+                "x86_64-pc-windows-msvc",
+                true,
+                &TripletSettings::from_name("x64-windows-static")
+            ),
+            Compatibility::KnownGood
+        );
+        assert_eq!(
+            compatibility("x86_64-pc-windows-msvc", "x64-rust-static"),
+            Compatibility::Plausible
+        );
+        assert_eq!(
+            compatibility("armv7-unknown-linux-gnueabihf", "arm-linux"),
+            Compatibility::Plausible
+        );
+        assert_eq!(
+            compatibility("x86_64-unknown-linux-gnu", "x64-osx"),
+            Compatibility::Mismatched(Mismatch::Os)
+        );
+        assert_eq!(
+            compatibility("aarch64-apple-ios", "x64-osx"),
+            Compatibility::Mismatched(Mismatch::Arch)
+        );
+        assert_eq!(
+            compatibility("avr-unknown-gnu-atmega328", "x64-linux"),
+            Compatibility::Mismatched(Mismatch::Arch)
+        );
+
+        let _g = LOCK.lock();
+        clean_env();
+        env::set_var(VCPKG_ROOT, vcpkg_test_tree_loc("normalized"));
+        env::set_var(TARGET, "aarch64-apple-ios");
+        let tmp_dir = tempdir().unwrap();
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let harfbuzz = ::find_package("harfbuzz").unwrap();
+        assert!(!harfbuzz
+            .cargo_metadata
+            .iter()
+            .any(|x| x.contains("does not suit rust target")));
+
+        env::set_var(VCPKGRS_TRIPLET, "x64-osx");
+        let harfbuzz = ::find_package("harfbuzz").unwrap();
+        assert!(harfbuzz.cargo_metadata.iter().any(|x| x
+            == "cargo:warning=vcpkg triplet x64-osx does not suit rust target \
+                aarch64-apple-ios (Arch mismatch)"));
+        clean_env();
+    }
+
+    #[test]
+    fn versioned_shared_libs() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-linux-dynamic",
+            &[
+                (
+                    "foo",
+                    "1.2.3",
+                    &[
+                        "include/foo.h",
+                        "lib/libfoo.so",
+                        "lib/libfoo.so.1",
+                        "lib/libfoo.so.1.2.3",
+                    ],
+                ),
+                ("bar", "2.0", &["lib/libbar.so.2.0", "lib/libbar.so.2"]),
+            ],
+        );
+        env::set_var(TARGET, "x86_64-unknown-linux-gnu");
+        env::set_var(VCPKGRS_DYNAMIC, "1");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let lib_path = root.join("installed").join("x64-linux-dynamic").join("lib");
+        let foo = ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("foo")
+            .unwrap();
+        assert!(!foo.is_static);
+        assert_eq!(foo.found_names, vec!["foo"]);
+        assert_eq!(foo.found_libs, vec![lib_path.join("libfoo.so")]);
+        assert_eq!(
+            foo.found_dlls,
+            vec![lib_path.join("libfoo.so.1"), lib_path.join("libfoo.so.1.2.3")]
+        );
+        assert_eq!(foo.dll_paths, vec![lib_path.clone()]);
+        // the loader doesn't look in OUT_DIR for shared objects
+        assert!(!tmp_dir.path().join("libfoo.so.1").exists());
+
+        // without an unversioned libbar.so the soname is linked verbatim
+        let bar = ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("bar")
+            .unwrap();
+        assert!(bar
+            .cargo_metadata
+            .iter()
+            .any(|x| x == "cargo:rustc-link-lib=dylib:+verbatim=libbar.so.2"));
+        assert_eq!(bar.found_libs, vec![lib_path.join("libbar.so.2")]);
+        assert_eq!(bar.found_dlls.len(), 2);
+        clean_env();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn rpath() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(&root, "x64-linux-dynamic", &[("foo", "1.0", &["lib/libfoo.so"])]);
+        let out_dir = tmp_dir.path().join("target/debug/build/foo-0123/out");
+        fs::create_dir_all(&out_dir).unwrap();
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux-dynamic");
+        env::set_var(VCPKGRS_DYNAMIC, "1");
+        env::set_var(OUT_DIR, &out_dir);
+        let rpaths = |lib: &Library| {
+            lib.cargo_metadata
+                .iter()
+                .filter(|line| line.starts_with("cargo:rustc-link-arg=-Wl,-rpath,"))
+                .map(|line| line["cargo:rustc-link-arg=-Wl,-rpath,".len()..].to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("foo")
+            .unwrap();
+        assert!(rpaths(&lib).is_empty());
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .rpath(Rpath::Absolute)
+            .find_package("foo")
+            .unwrap();
+        let lib_path = root.join("installed/x64-linux-dynamic/lib");
+        assert_eq!(rpaths(&lib), vec![lib_path.display().to_string()]);
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .rpath(Rpath::Origin)
+            .find_package("foo")
+            .unwrap();
+        assert_eq!(
+            rpaths(&lib),
+            vec![
+                "$ORIGIN/../../vcpkg/installed/x64-linux-dynamic/lib",
+                "$ORIGIN/../../../vcpkg/installed/x64-linux-dynamic/lib",
+            ]
+        );
+        clean_env();
+    }
+
+    #[test]
+    fn osx_dynamic_triplets() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "arm64-osx-dynamic",
+            &[
+                ("foo", "1.0", &["include/foo.h", "lib/libfoo.dylib"]),
+                ("bar", "2.0", &["lib/libbar.dylib"]),
+                (
+                    "baz",
+                    "1.2.3",
+                    &["lib/libbaz.dylib", "lib/libbaz.1.dylib", "lib/libbaz.1.2.3.dylib"],
+                ),
+                ("qux", "2.0", &["lib/libqux.2.0.dylib", "lib/libqux.2.dylib"]),
+            ],
+        );
+        let lib_path = root.join("installed/arm64-osx-dynamic/lib");
+        fs::write(
+            lib_path.join("libfoo.dylib"),
+            b"\xcf\xfa\xed\xfe\x0d\x00\x00\x00@rpath/libfoo.dylib\x00".to_vec(),
+        )
+        .unwrap();
+        fs::write(lib_path.join("libbar.dylib"), b"\xcf\xfa\xed\xfe".to_vec()).unwrap();
+        env::set_var(TARGET, "aarch64-apple-darwin");
+        env::set_var(VCPKGRS_DYNAMIC, "1");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let foo = ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("foo")
+            .unwrap();
+        assert_eq!(foo.vcpkg_triplet, "arm64-osx-dynamic");
+        assert!(!foo.is_static);
+        assert_eq!(foo.found_names, vec!["foo"]);
+        assert_eq!(foo.found_libs, vec![lib_path.join("libfoo.dylib")]);
+        assert!(foo.cargo_metadata.contains(&format!(
+            "cargo:rustc-link-search=native={}",
+            lib_path.display()
+        )));
+        assert!(foo.needs_rpath);
+        assert!(!tmp_dir.path().join("libfoo.dylib").exists());
+
+        let bar = ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("bar")
+            .unwrap();
+        assert!(!bar.needs_rpath);
+
+        // the versioned names are only needed at run time
+        let baz = ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("baz")
+            .unwrap();
+        assert_eq!(baz.found_names, vec!["baz"]);
+        assert_eq!(
+            baz.found_dlls,
+            vec![lib_path.join("libbaz.1.dylib"), lib_path.join("libbaz.1.2.3.dylib")]
+        );
+
+        // without an unversioned libqux.dylib the shortest name is linked
+        let qux = ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("qux")
+            .unwrap();
+        assert_eq!(qux.found_names, vec!["qux.2"]);
+        assert_eq!(qux.found_libs, vec![lib_path.join("libqux.2.dylib")]);
+        clean_env();
+    }
+
+    #[test]
+    fn write_probe_json() {
+        let _g = LOCK.lock();
+        clean_env();
+        env::set_var(VCPKG_ROOT, vcpkg_test_tree_loc("normalized"));
+        env::set_var(TARGET, "x86_64-apple-darwin");
+        let tmp_dir = tempdir().unwrap();
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let mut lib = ::Config::new()
+            .write_probe_json(true)
+            .find_package("harfbuzz")
+            .unwrap();
+        let json = fs::read_to_string(tmp_dir.path().join("vcpkg-probe.json")).unwrap();
+        assert!(json.starts_with("{\n  \"vcpkg_triplet\": \"x64-osx\",\n  \"is_static\": true,\n"));
+        assert!(json.contains("\"harfbuzz\": \"1.8.4\""));
+        assert!(json.contains("\"found_names\": [\"harfbuzz\", "));
+        assert!(json.ends_with("]\n}\n"));
+        assert_eq!(json.matches("cargo:rustc-link-lib=").count(), lib.found_names.len());
+
+        assert_eq!(lib.version("harfbuzz"), Some("1.8.4"));
+        lib.port_versions = BTreeMap::new();
+        lib.port_versions
+            .insert("a\\b".to_owned(), "\"1\"\n".to_owned());
+        assert!(probe_json::probe_json(&lib)
+            .contains("\"port_versions\": {\"a\\\\b\": \"\\\"1\\\"\\n\"},"));
+        clean_env();
+    }
+
+    #[test]
+    fn defines() {
+        let _g = LOCK.lock();
+        clean_env();
+        env::set_var(VCPKG_ROOT, vcpkg_test_tree_loc("normalized"));
+        env::set_var(TARGET, "x86_64-apple-darwin");
+        let tmp_dir = tempdir().unwrap();
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let lib = ::Config::new()
+            .define("CURL_STATICLIB", None)
+            .define("ZLIB_CONST", Some("1"))
+            .find_package("zlib")
+            .unwrap();
+        assert_eq!(
+            lib.defines,
+            vec![
+                ("CURL_STATICLIB".to_owned(), None),
+                ("ZLIB_CONST".to_owned(), Some("1".to_owned()))
+            ]
+        );
+        assert!(lib
+            .cargo_metadata
+            .contains(&"cargo:defines=CURL_STATICLIB,ZLIB_CONST=1".to_owned()));
+
+        let lib = ::find_package("zlib").unwrap();
+        assert!(lib.defines.is_empty());
+        assert!(!lib.cargo_metadata.iter().any(|l| l.starts_with("cargo:defines=")));
+        clean_env();
+    }
+
+    #[test]
+    fn link_args() {
+        let _g = LOCK.lock();
+        clean_env();
+        env::set_var(VCPKG_ROOT, vcpkg_test_tree_loc("normalized"));
+        env::set_var(TARGET, "x86_64-apple-darwin");
+        let tmp_dir = tempdir().unwrap();
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let lib = ::Config::new()
+            .link_arg("-Wl,-rpath,{lib_dir}")
+            .link_arg("-Wl,--allow-multiple-definition")
+            .find_package("zlib")
+            .unwrap();
+        let rpath = format!("-Wl,-rpath,{}", lib.link_paths[0].display());
+        assert_eq!(
+            lib.cargo_metadata[lib.cargo_metadata.len() - 2..].to_vec(),
+            vec![
+                format!("cargo:rustc-link-arg={}", rpath),
+                "cargo:rustc-link-arg=-Wl,--allow-multiple-definition".to_owned()
+            ]
+        );
+        assert!(lib
+            .directives()
+            .contains(&MetadataDirective::LinkArg(rpath)));
+
+        let lib = ::find_package("zlib").unwrap();
+        assert!(!lib
+            .cargo_metadata
+            .iter()
+            .any(|l| l.starts_with("cargo:rustc-link-arg=")));
+        clean_env();
+    }
+
+    #[test]
+    fn links_metadata() {
+        let _g = LOCK.lock();
+        clean_env();
+        env::set_var(VCPKG_ROOT, vcpkg_test_tree_loc("normalized"));
+        env::set_var(TARGET, "x86_64-apple-darwin");
+        let tmp_dir = tempdir().unwrap();
+        env::set_var(OUT_DIR, tmp_dir.path());
+        let installed = vcpkg_test_tree_loc("normalized")
+            .join("installed")
+            .join("x64-osx");
+
+        let lib = ::Config::new()
+            .emit_links_metadata(true)
+            .find_package("zlib")
+            .unwrap();
+        for line in &[
+            format!("cargo:root={}", installed.display()),
+            format!("cargo:include={}", installed.join("include").display()),
+            format!("cargo:lib={}", installed.join("lib").display()),
+            format!("cargo:libs={}", lib.found_names.join(",")),
+        ] {
+            assert!(lib.cargo_metadata.contains(line), "{} is missing", line);
+        }
+        assert!(!lib.found_names.is_empty());
+
+        let lib = ::find_package("zlib").unwrap();
+        assert!(!lib.cargo_metadata.iter().any(|l| l.starts_with("cargo:root=")));
+        clean_env();
+    }
+
+    #[test]
+    fn port_include_paths() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-linux",
+            &[
+                ("libxml2", "2.11.0", &["include/libxml2/libxml/tree.h", "lib/libxml2.a"]),
+                ("zlib", "1.3", &["include/zlib.h", "lib/libz.a"]),
+            ],
+        );
+        let package_include = root
+            .join("packages")
+            .join("libxml2_x64-linux")
+            .join("include");
+        fs::create_dir_all(package_include.join("libxml2")).unwrap();
+        env::set_var(VCPKG_ROOT, &root);
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let lib = ::find_package("libxml2").unwrap();
+        assert_eq!(
+            lib.port_include_paths.get("libxml2"),
+            Some(&package_include)
+        );
+        assert!(!lib
+            .cargo_metadata
+            .contains(&format!("cargo:include={}", package_include.display())));
+
+        let lib = ::Config::new()
+            .emit_port_includes(true)
+            .find_package("libxml2")
+            .unwrap();
+        assert!(lib
+            .cargo_metadata
+            .contains(&format!("cargo:include={}", package_include.display())));
+
+        // ports whose packages were cleaned out have none
+        let lib = ::find_package("zlib").unwrap();
+        assert!(lib.port_include_paths.is_empty());
+        clean_env();
+    }
+
+    #[test]
+    fn pc_file_include_dirs() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-linux",
+            &[(
+                "libxml2",
+                "2.9.14",
+                &[
+                    "include/libxml2/libxml/tree.h",
+                    "lib/libxml2.a",
+                    "lib/pkgconfig/libxml-2.0.pc",
+                ],
+            )],
+        );
+        // the prefix was written by the machine that built the port
+        fs::write(
+            root.join("installed/x64-linux/lib/pkgconfig/libxml-2.0.pc"),
+            "prefix=/build/vcpkg/packages/libxml2_x64-linux\n\
+             includedir=${prefix}/include\n\
+             Cflags: -I${includedir}/libxml2 -I${includedir} -I/usr/include/missing\n\
+             Libs: -L${prefix}/lib -lxml2\n",
+        )
+        .unwrap();
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let include_path = root.join("installed").join("x64-linux").join("include");
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .emit_includes(true)
+            .find_package("libxml2")
+            .unwrap();
+        assert_eq!(
+            lib.include_paths,
+            vec![include_path.clone(), include_path.join("libxml2")]
+        );
+        assert!(lib.cargo_metadata.contains(&format!(
+            "cargo:include={}",
+            include_path.join("libxml2").display()
+        )));
+        clean_env();
+    }
+
+    #[test]
+    fn warn_on_fallback() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(&root, "x64-linux", &[("foo", "1.0", &["lib/libfoo.a"])]);
+        set_status_fields(&root, "foo", &["Depends: bar"]);
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let warnings = |warn_on_fallback| {
+            ::Config::new()
+                .vcpkg_root(root.clone())
+                .warn_on_fallback(warn_on_fallback)
+                .find_package("foo")
+                .unwrap()
+                .cargo_metadata
+                .into_iter()
+                .filter(|line| line.starts_with("cargo:warning="))
+                .collect::<Vec<_>>()
+        };
+        assert!(warnings(false).is_empty());
+        let warnings = warnings(true);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("vcpkg port bar is a dependency of foo but is not installed"));
+        assert!(warnings[1].contains("vcpkg port foo has no .pc files"));
+        clean_env();
+    }
+
+    #[test]
+    fn metadata_writer() {
+        use std::io::{self, Write};
+        use std::sync::{Arc, Mutex};
+
+        struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+        impl Write for SharedBuf {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        struct FailingWriter;
+
+        impl Write for FailingWriter {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::BrokenPipe, "closed"))
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        fn assert_send<T: Send>() {}
+        assert_send::<::Config>();
+
+        let _g = LOCK.lock();
+        clean_env();
+        env::set_var(VCPKG_ROOT, vcpkg_test_tree_loc("normalized"));
+        env::set_var(TARGET, "x86_64-pc-windows-msvc");
+        env::set_var(VCPKGRS_DYNAMIC, "1");
+        let tmp_dir = tempdir().unwrap();
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let buf = Arc::new(Mutex::new(Vec::new()));
+        let lib = ::Config::new()
+            .metadata_writer(SharedBuf(buf.clone()))
+            .find_package("zlib")
+            .unwrap();
+        let output = String::from_utf8(buf.lock().unwrap().clone()).unwrap();
+        let mut expected = format!(
+            "vcpkg build helper copied {} to {}\n",
+            lib.found_dlls[0].to_string_lossy(),
+            tmp_dir.path().join("zlib1.dll").to_string_lossy()
+        );
+        for line in &lib.cargo_metadata {
+            expected.push_str(line);
+            expected.push('\n');
+        }
+        assert_eq!(output, expected);
+
+        match ::Config::new().metadata_writer(FailingWriter).find_package("zlib") {
+            Err(Error::Io { ref path, .. }) if path.as_os_str().is_empty() => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        clean_env();
+    }
+
+    #[test]
+    fn pc_file_private_libs() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-linux",
+            &[(
+                "curl",
+                "7.84.0",
+                &["lib/libcurl.a", "lib/libssh.a", "lib/pkgconfig/libcurl.pc"],
+            )],
+        );
+        fs::write(
+            root.join("installed/x64-linux/lib/pkgconfig/libcurl.pc"),
+            "Libs: -lcurl -ldl\n\
+             Libs.private: -lssh -lpthread -lm -pthread\n",
+        )
+        .unwrap();
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("curl")
+            .unwrap();
+        assert_eq!(lib.system_libs, vec!["pthread", "m"]);
+        let link_libs: Vec<&String> = lib
+            .cargo_metadata
+            .iter()
+            .filter(|l| l.starts_with("cargo:rustc-link-lib="))
+            .collect();
+        assert_eq!(
+            link_libs,
+            vec![
+                "cargo:rustc-link-lib=curl",
+                "cargo:rustc-link-lib=ssh",
+                "cargo:rustc-link-lib=pthread",
+                "cargo:rustc-link-lib=m"
+            ]
+        );
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .emit_system_libs(true)
+            .find_package("curl")
+            .unwrap();
+        assert_eq!(lib.system_libs, vec!["dl", "pthread", "m"]);
+        assert!(lib
+            .cargo_metadata
+            .contains(&"cargo:rustc-link-lib=dl".to_owned()));
+        clean_env();
+    }
+
+    #[test]
+    fn find_packages() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-linux",
+            &[
+                ("zlib", "1.2.13", &["include/zlib.h", "lib/libz.a"]),
+                ("bzip2", "1.0.8", &["include/bzlib.h", "lib/libbz2.a"]),
+                ("lzma", "5.2.5", &["include/lzma.h", "lib/liblzma.a"]),
+            ],
+        );
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_packages(&["zlib", "bzip2"])
+            .unwrap();
+        assert_eq!(lib.ports, vec!["zlib", "bzip2"]);
+        assert_eq!(lib.found_names, vec!["z", "bz2"]);
+        assert_eq!(
+            lib.cargo_metadata
+                .iter()
+                .filter(|l| l.starts_with("cargo:rustc-link-search="))
+                .count(),
+            1
+        );
+
+        match ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_packages(&["zlib", "brotli"])
+        {
+            Err(Error::LibNotFound { ref detail, .. }) => assert!(detail.contains("brotli")),
+            _ => panic!("brotli is not installed"),
+        }
+
+        env::set_var("VCPKGRS_TRIPLET_BZIP2", "x64-linux-dynamic");
+        match ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_packages(&["zlib", "bzip2"])
+        {
+            Err(Error::IncompatibleTriplet(_)) => {}
+            _ => panic!("the packages must be in the same triplet"),
+        }
+        env::remove_var("VCPKGRS_TRIPLET_BZIP2");
+        clean_env();
+    }
+
+    #[test]
+    fn metadata_directives() {
+        let _g = LOCK.lock();
+        clean_env();
+        env::set_var(VCPKG_ROOT, vcpkg_test_tree_loc("normalized"));
+        env::set_var(TARGET, "x86_64-apple-darwin");
+        let tmp_dir = tempdir().unwrap();
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let lib = ::Config::new()
+            .emit_includes(true)
+            .find_package("harfbuzz")
+            .unwrap();
+        assert_eq!(lib.directives().len(), lib.cargo_metadata.len());
+        assert_eq!(lib.to_cargo_lines(), lib.cargo_metadata);
+        assert!(lib.directives().contains(&MetadataDirective::LinkLib {
+            kind: None,
+            name: "harfbuzz".to_owned(),
+        }));
+        assert!(lib.directives().contains(&MetadataDirective::Include(
+            vcpkg_test_tree_loc("normalized")
+                .join("installed")
+                .join("x64-osx")
+                .join("include")
+        )));
+
+        let cases = vec![
+            (
+                "cargo:rustc-link-lib=static:+verbatim=libfoo.a",
+                MetadataDirective::LinkLib {
+                    kind: Some("static:+verbatim".to_owned()),
+                    name: "libfoo.a".to_owned(),
+                },
+            ),
+            (
+                "cargo:rustc-link-search=native=/opt/vcpkg/lib",
+                MetadataDirective::LinkSearch {
+                    kind: Some("native".to_owned()),
+                    path: PathBuf::from("/opt/vcpkg/lib"),
+                },
+            ),
+            (
+                "cargo:rustc-link-search=/a=b",
+                MetadataDirective::LinkSearch {
+                    kind: None,
+                    path: PathBuf::from("/a=b"),
+                },
+            ),
+            (
+                "cargo:rustc-link-arg=/NODEFAULTLIB:msvcrt",
+                MetadataDirective::LinkArg("/NODEFAULTLIB:msvcrt".to_owned()),
+            ),
+            (
+                "cargo:warning=a = b",
+                MetadataDirective::Warning("a = b".to_owned()),
+            ),
+            (
+                "cargo:defines=A,B=1",
+                MetadataDirective::Other("cargo:defines=A,B=1".to_owned()),
+            ),
+        ];
+        for (line, directive) in cases {
+            assert_eq!(MetadataDirective::parse(line), directive);
+            assert_eq!(directive.to_cargo_line(), line);
+        }
+        clean_env();
+    }
+
+    #[test]
+    fn export_tree() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("export");
+        make_vcpkg_tree(
+            &root,
+            "x64-linux",
+            &[("zlib", "1.2.13", &["include/zlib.h", "lib/libz.a"])],
+        );
+        fs::remove_file(root.join(".vcpkg-root")).unwrap();
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        // without a status database it can't be told from any other directory
+        match ::Config::new().vcpkg_root(root.clone()).find_package("zlib") {
+            Err(Error::VcpkgNotFound(_)) => {}
+            _ => panic!("not a vcpkg root"),
+        }
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .export_tree(true)
+            .find_package("zlib")
+            .unwrap();
+        assert_eq!(lib.found_names, vec!["z"]);
+
+        // the layout written by `vcpkg export --raw`
+        let status_path = root.join("installed").join("vcpkg");
+        fs::rename(
+            status_path.join("updates").join("0000000001"),
+            status_path.join("status"),
+        )
+        .unwrap();
+        fs::remove_dir(status_path.join("updates")).unwrap();
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("zlib")
+            .unwrap();
+        assert_eq!(lib.found_names, vec!["z"]);
+        assert!(lib.cargo_metadata.contains(&format!(
+            "cargo:rerun-if-changed={}",
+            status_path.join("status").display()
+        )));
+        assert!(!lib
+            .cargo_metadata
+            .iter()
+            .any(|line| line.ends_with("updates")));
+        clean_env();
+    }
+
+    #[test]
+    fn installed_root() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("monorepo");
+        make_vcpkg_tree(
+            &root,
+            "x64-linux",
+            &[("zlib", "1.2.13", &["include/zlib.h", "lib/libz.a"])],
+        );
+        let installed_root = tmp_dir.path().join("shared-cache");
+        fs::rename(root.join("installed"), &installed_root).unwrap();
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        env::set_var(OUT_DIR, tmp_dir.path());
+        let lib_path = installed_root.join("x64-linux").join("lib");
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .installed_root(installed_root.clone())
+            .find_package("zlib")
+            .unwrap();
+        assert_eq!(lib.found_libs, vec![lib_path.join("libz.a")]);
+
+        // no vcpkg root is needed
+        env::set_var(VCPKG_INSTALLED_DIR, &installed_root);
+        let lib = ::find_package("zlib").unwrap();
+        assert_eq!(lib.found_libs, vec![lib_path.join("libz.a")]);
+
+        match ::Config::new()
+            .installed_root(root.clone())
+            .find_package("zlib")
+        {
+            Err(Error::VcpkgNotFound(_)) => {}
+            _ => panic!("there are no installed packages there"),
+        }
+        clean_env();
+    }
+
+    #[test]
+    fn overlay_triplets() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-linux-shared",
+            &[("zlib", "1.2.13", &["include/zlib.h", "lib/libz.so"])],
+        );
+        let windows_root = tmp_dir.path().join("vcpkg-windows");
+        make_vcpkg_tree(
+            &windows_root,
+            "x64-windows-custom",
+            &[("zlib", "1.2.13", &["include/zlib.h", "lib/zlib.lib"])],
+        );
+        let overlay = tmp_dir.path().join("triplets");
+        fs::create_dir_all(&overlay).unwrap();
+        fs::write(
+            overlay.join("x64-linux-shared.cmake"),
+            "set(VCPKG_TARGET_ARCHITECTURE x64)\n\
+             set(VCPKG_CRT_LINKAGE dynamic)\n\
+             set(VCPKG_LIBRARY_LINKAGE dynamic)\n",
+        )
+        .unwrap();
+        fs::write(
+            overlay.join("x64-windows-custom.cmake"),
+            "set(VCPKG_CRT_LINKAGE static)\nset(VCPKG_LIBRARY_LINKAGE static)\n",
+        )
+        .unwrap();
+        env::set_var(VCPKG_ROOT, &root);
+        env::set_var(OUT_DIR, tmp_dir.path());
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux-shared");
+
+        // going by the name, the libraries are static and there are none
+        assert!(::find_package("zlib").unwrap().found_libs.is_empty());
+
+        env::set_var(VCPKG_OVERLAY_TRIPLETS, &overlay);
+        match ::find_package("zlib") {
+            Err(Error::RequiredEnvMissing(ref var)) if var == VCPKGRS_DYNAMIC => {}
+            _ => panic!("the triplet file says the libraries are dynamic"),
+        }
+        env::set_var(VCPKGRS_DYNAMIC, "1");
+        let lib = ::find_package("zlib").unwrap();
+        let lib_path = root.join("installed").join("x64-linux-shared").join("lib");
+        assert_eq!(lib.found_libs, vec![lib_path.join("libz.so")]);
+        assert!(!lib.is_static);
+        env::remove_var(VCPKGRS_DYNAMIC);
+
+        // the CRT linkage comes from the triplet file too
+        env::set_var(VCPKG_ROOT, &windows_root);
+        env::set_var(VCPKGRS_TRIPLET, "x64-windows-custom");
+        env::set_var(TARGET, "x86_64-pc-windows-msvc");
+        env::set_var(CARGO_CFG_TARGET_FEATURE, "crt-static");
+        let lib = ::find_package("zlib").unwrap();
+        assert!(lib.is_static);
+        assert_eq!(
+            lib.triplet_settings.as_ref().map(|settings| settings.crt_linkage),
+            Some(::Linkage::Static)
+        );
+        assert!(!lib
+            .cargo_metadata
+            .iter()
+            .any(|x| x.contains("does not suit rust target")));
+        env::remove_var(CARGO_CFG_TARGET_FEATURE);
+        match ::find_package("zlib") {
+            Err(Error::CrtMismatch(_)) => {}
+            _ => panic!("the triplet file says the CRT is static"),
+        }
+        clean_env();
+    }
+
+    #[test]
+    fn crt_mismatch() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-windows-static-md",
+            &[("zlib", "1.2.13", &["include/zlib.h", "lib/zlib.lib"])],
+        );
+        env::set_var(VCPKG_ROOT, &root);
+        env::set_var(OUT_DIR, tmp_dir.path());
+        env::set_var(TARGET, "x86_64-pc-windows-msvc");
+        env::set_var(VCPKGRS_TRIPLET, "x64-windows-static-md");
+        env::set_var(CARGO_CFG_TARGET_FEATURE, "crt-static");
+        let warning = "cargo:warning=vcpkg triplet x64-windows-static-md does not suit rust \
+                       target x86_64-pc-windows-msvc (Linkage mismatch)";
+
+        // without a triplet file the CRT linkage is only guessed from the name
+        let lib = ::find_package("zlib").unwrap();
+        assert!(lib.cargo_metadata.iter().any(|x| x == warning));
+
+        fs::create_dir_all(root.join("triplets")).unwrap();
+        fs::write(
+            root.join("triplets").join("x64-windows-static-md.cmake"),
+            "set(VCPKG_CRT_LINKAGE dynamic)\nset(VCPKG_LIBRARY_LINKAGE static)\n",
+        )
+        .unwrap();
+        match ::find_package("zlib") {
+            Err(Error::CrtMismatch(ref detail)) => assert!(detail.contains(
+                "vcpkg triplet x64-windows-static-md links the C runtime dynamically"
+            )),
+            _ => panic!("the triplet links the CRT dynamically"),
+        }
+
+        env::set_var(VCPKGRS_ALLOW_CRT_MISMATCH, "1");
+        let lib = ::find_package("zlib").unwrap();
+        assert!(lib.cargo_metadata.iter().any(|x| x == warning));
+
+        env::remove_var(VCPKGRS_ALLOW_CRT_MISMATCH);
+        env::remove_var(CARGO_CFG_TARGET_FEATURE);
+        assert!(::find_package("zlib").is_ok());
+        clean_env();
+    }
+
+    #[test]
+    fn crt_mismatch_custom_triplet() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        // a custom static-CRT triplet that doesn't follow the naming conventions
+        make_vcpkg_tree(
+            &root,
+            "x64-windows-mt",
+            &[("zlib", "1.2.13", &["include/zlib.h", "lib/zlib.lib"])],
+        );
+        env::set_var(VCPKG_ROOT, &root);
+        env::set_var(OUT_DIR, tmp_dir.path());
+        env::set_var(TARGET, "x86_64-pc-windows-msvc");
+        env::set_var(VCPKGRS_TRIPLET, "x64-windows-mt");
+        env::set_var(VCPKGRS_DYNAMIC, "1");
+        env::set_var(CARGO_CFG_TARGET_FEATURE, "crt-static");
+
+        let lib = ::find_package("zlib").unwrap();
+        assert_eq!(lib.found_names, vec!["zlib"]);
+        assert!(lib.cargo_metadata.iter().any(|x| x
+            == "cargo:warning=vcpkg triplet x64-windows-mt does not suit rust target \
+                x86_64-pc-windows-msvc (Linkage mismatch)"));
+        clean_env();
+    }
+
+    #[test]
+    fn manifest_mode() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let workspace = tmp_dir.path().join("workspace");
+        make_vcpkg_tree(
+            &workspace,
+            "x64-linux",
+            &[("zlib", "1.2.13", &["include/zlib.h", "lib/libz.a"])],
+        );
+        fs::rename(
+            workspace.join("installed"),
+            workspace.join("vcpkg_installed"),
+        )
+        .unwrap();
+        let crate_dir = workspace.join("crates").join("zlib-sys");
+        fs::create_dir_all(&crate_dir).unwrap();
+        env::set_var(CARGO_MANIFEST_DIR, &crate_dir);
+        env::set_var(VCPKG_ROOT, vcpkg_test_tree_loc("normalized"));
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        // not without a manifest
+        assert!(::find_package("zlib").is_err());
+
+        fs::write(workspace.join("vcpkg.json"), "{}").unwrap();
+        let lib = ::find_package("zlib").unwrap();
+        assert_eq!(
+            lib.found_libs,
+            vec![workspace.join("vcpkg_installed/x64-linux/lib/libz.a")]
+        );
+
+        env::set_var(CARGO_MANIFEST_DIR, env!("CARGO_MANIFEST_DIR"));
+        clean_env();
+    }
+
+    #[test]
+    fn is_installed() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-linux",
+            &[
+                ("zlib", "1.2.13", &["include/zlib.h", "lib/libz.a"]),
+                ("bzip2", "1.0.8", &["include/bzlib.h", "lib/libbz2.a"]),
+            ],
+        );
+        fs::remove_file(root.join("installed/x64-linux/lib/libbz2.a")).unwrap();
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let mut config = ::Config::new();
+        config.vcpkg_root(root.clone()).write_probe_json(true);
+        assert_eq!(config.is_installed("zlib").unwrap(), true);
+        // the status database lists it but the library is gone
+        assert_eq!(config.is_installed("bzip2").unwrap(), false);
+        assert_eq!(config.is_installed("brotli").unwrap(), false);
+        assert!(!tmp_dir.path().join("vcpkg-probe.json").exists());
+
+        // the configuration is unchanged for finding the package afterwards
+        let lib = config.find_package("zlib").unwrap();
+        assert_eq!(lib.found_names, vec!["z"]);
+        assert!(tmp_dir.path().join("vcpkg-probe.json").exists());
+
+        // a missing port is neither installed nor restored from the binary cache
+        let mut config = ::Config::new();
+        config
+            .vcpkg_root(root.clone())
+            .install_missing(true)
+            .binary_cache(true);
+        assert_eq!(config.is_installed("brotli").unwrap(), false);
+
+        let tmp_dir = tempdir().unwrap();
+        assert!(::Config::new()
+            .vcpkg_root(tmp_dir.path().to_owned())
+            .is_installed("zlib")
+            .is_err());
+        clean_env();
+    }
+
+    #[test]
+    fn debug_profile() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-linux",
+            &[(
+                "zlib",
+                "1.2.13",
+                &["include/zlib.h", "lib/libz.a", "debug/lib/libz.a"],
+            )],
+        );
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        env::set_var(OUT_DIR, tmp_dir.path());
+        let installed = root.join("installed").join("x64-linux");
+
+        env::set_var(PROFILE, "debug");
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .use_debug_profile(true)
+            .find_package("zlib")
+            .unwrap();
+        assert_eq!(lib.found_libs, vec![installed.join("debug/lib/libz.a")]);
+        assert_eq!(lib.link_paths, vec![installed.join("debug/lib")]);
+        assert_eq!(lib.include_paths, vec![installed.join("include")]);
+
+        // only when asked to
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("zlib")
+            .unwrap();
+        assert_eq!(lib.found_libs, vec![installed.join("lib/libz.a")]);
+
+        env::set_var(PROFILE, "release");
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .use_debug_profile(true)
+            .find_package("zlib")
+            .unwrap();
+        assert_eq!(lib.found_libs, vec![installed.join("lib/libz.a")]);
+        assert!(!lib.is_debug);
+        clean_env();
+    }
+
+    #[test]
+    fn debug_profile_dlls() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        let out_dir = tmp_dir.path().join("out");
+        fs::create_dir(&out_dir).unwrap();
+        make_vcpkg_tree(
+            &root,
+            "x64-windows",
+            &[(
+                "zlib",
+                "1.3",
+                &[
+                    "include/zlib.h",
+                    "lib/zlib.lib",
+                    "bin/zlib1.dll",
+                    "debug/lib/zlibd.lib",
+                    "debug/bin/zlibd1.dll",
+                ],
+            )],
+        );
+        env::set_var(VCPKGRS_TRIPLET, "x64-windows");
+        env::set_var(VCPKGRS_DYNAMIC, "1");
+        env::set_var(OUT_DIR, &out_dir);
+        env::set_var(PROFILE, "debug");
+        let installed = root.join("installed").join("x64-windows");
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .use_debug_profile(true)
+            .find_package("zlib")
+            .unwrap();
+        assert!(lib.is_debug);
+        assert_eq!(lib.found_libs, vec![installed.join("debug/lib/zlibd.lib")]);
+        assert_eq!(lib.found_dlls, vec![installed.join("debug/bin/zlibd1.dll")]);
+        assert_eq!(lib.dll_paths, vec![installed.join("debug/bin")]);
+        assert!(out_dir.join("zlibd1.dll").exists());
+        assert!(!out_dir.join("zlib1.dll").exists());
+
+        // release-only triplets have no debug tree
+        fs::remove_dir_all(installed.join("debug")).unwrap();
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .use_debug_profile(true)
+            .find_package("zlib")
+            .unwrap();
+        assert!(!lib.is_debug);
+        assert_eq!(lib.found_dlls, vec![installed.join("bin/zlib1.dll")]);
+        clean_env();
+    }
+
+    #[test]
+    fn release_only_triplets() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-linux-release",
+            &[("zlib", "1.3", &["lib/libz.a", "include/zlib.h"])],
+        );
+        env::set_var(TARGET, "x86_64-unknown-linux-gnu");
+        env::set_var(OUT_DIR, tmp_dir.path());
+        env::set_var(PROFILE, "debug");
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .use_debug_profile(true)
+            .find_package("zlib")
+            .unwrap();
+        assert_eq!(lib.vcpkg_triplet, "x64-linux-release");
+        assert!(!lib.is_debug);
+        assert_eq!(
+            lib.found_libs,
+            vec![root.join("installed/x64-linux-release/lib/libz.a")]
+        );
+
+        // a triplet that is asked for is not swapped for another
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        assert!(::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("zlib")
+            .is_err());
+        clean_env();
+    }
+
+    #[test]
+    fn debug_profile_suffixed_names() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-windows",
+            &[(
+                "zlib",
+                "1.2.13",
+                &[
+                    "bin/zlib1.dll",
+                    "lib/zlib.lib",
+                    "debug/bin/zlibd1.dll",
+                    "debug/lib/zlibd.lib",
+                ],
+            )],
+        );
+        env::set_var(VCPKGRS_TRIPLET, "x64-windows");
+        env::set_var(VCPKGRS_DYNAMIC, "1");
+        env::set_var(PROFILE, "debug");
+        env::set_var(OUT_DIR, tmp_dir.path());
+        let debug = root.join("installed").join("x64-windows").join("debug");
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .use_debug_profile(true)
+            .find_package("zlib")
+            .unwrap();
+        assert_eq!(lib.found_names, vec!["zlibd"]);
+        assert_eq!(lib.found_libs, vec![debug.join("lib").join("zlibd.lib")]);
+        assert_eq!(lib.found_dlls, vec![debug.join("bin").join("zlibd1.dll")]);
+        clean_env();
+    }
+
+    #[test]
+    fn pc_file_defines() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-linux",
+            &[(
+                "pcre2",
+                "10.40",
+                &["lib/libpcre2-8.a", "lib/pkgconfig/libpcre2-8.pc"],
+            )],
+        );
+        fs::write(
+            root.join("installed/x64-linux/lib/pkgconfig/libpcre2-8.pc"),
+            "prefix=${pcfiledir}/../..\n\
+             Cflags: -I${prefix}/include -DPCRE2_STATIC -D PCRE2_CODE_UNIT_WIDTH=8\n\
+             Libs: -L${prefix}/lib -lpcre2-8\n",
+        )
+        .unwrap();
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("pcre2")
+            .unwrap();
+        assert_eq!(lib.found_names, vec!["pcre2-8"]);
+        assert_eq!(
+            lib.defines,
+            vec![
+                ("PCRE2_STATIC".to_owned(), None),
+                ("PCRE2_CODE_UNIT_WIDTH".to_owned(), Some("8".to_owned()))
+            ]
+        );
+        assert!(lib
+            .cargo_metadata
+            .contains(&"cargo:defines=PCRE2_STATIC,PCRE2_CODE_UNIT_WIDTH=8".to_owned()));
+
+        // a define given to the config takes precedence
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .define("PCRE2_CODE_UNIT_WIDTH", Some("16"))
+            .find_package("pcre2")
+            .unwrap();
+        assert_eq!(
+            lib.defines,
+            vec![
+                ("PCRE2_CODE_UNIT_WIDTH".to_owned(), Some("16".to_owned())),
+                ("PCRE2_STATIC".to_owned(), None)
+            ]
+        );
+        clean_env();
+    }
+
+    #[test]
+    fn port_triplet() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        let updates = root.join("installed").join("vcpkg").join("updates");
+        make_vcpkg_tree(
+            &root,
+            "x64-windows-static-md",
+            &[("openssl", "3.0.0", &["lib/libssl.lib", "lib/libcrypto.lib"])],
+        );
+        fs::rename(updates.join("0000000001"), updates.join("0000000000")).unwrap();
+        make_vcpkg_tree(
+            &root,
+            "x64-windows",
+            &[("curl", "8.0.0", &["lib/libcurl.lib", "bin/libcurl.dll"])],
+        );
+        set_status_fields(&root, "curl", &["Depends: openssl"]);
+        env::set_var(TARGET, "x86_64-pc-windows-msvc");
+        env::set_var(VCPKGRS_DYNAMIC, "1");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let installed = root.join("installed");
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .port_triplet("openssl", "x64-windows-static-md")
+            .find_package("curl")
+            .unwrap();
+        assert_eq!(lib.ports, vec!["curl", "openssl"]);
+        assert_eq!(lib.found_names, vec!["libcurl", "libssl", "libcrypto"]);
+        assert_eq!(
+            lib.found_libs,
+            vec![
+                installed.join("x64-windows").join("lib").join("libcurl.lib"),
+                installed
+                    .join("x64-windows-static-md")
+                    .join("lib")
+                    .join("libssl.lib"),
+                installed
+                    .join("x64-windows-static-md")
+                    .join("lib")
+                    .join("libcrypto.lib"),
+            ]
+        );
+        assert_eq!(
+            lib.found_dlls,
+            vec![installed.join("x64-windows").join("bin").join("libcurl.dll")]
+        );
+        assert!(lib
+            .link_paths
+            .contains(&installed.join("x64-windows-static-md").join("lib")));
+
+        // the static CRT can't be mixed with the dynamic one
+        match ::Config::new()
+            .vcpkg_root(root.clone())
+            .port_triplet("openssl", "x64-windows-static")
+            .find_package("curl")
+        {
+            Err(Error::IncompatibleTriplet(_)) => {}
+            _ => panic!("expected IncompatibleTriplet"),
+        }
+        match ::Config::new()
+            .vcpkg_root(root.clone())
+            .port_triplet("openssl", "arm64-windows-static-md")
+            .find_package("curl")
+        {
+            Err(Error::IncompatibleTriplet(ref detail)) => assert!(detail.contains("Arch")),
+            _ => panic!("expected IncompatibleTriplet"),
+        }
+        clean_env();
+    }
+
+    #[test]
+    fn usage_system_libs() {
+        let usage = "The package curl is compatible with built-in CMake targets:
+
+    find_package(CURL REQUIRED)
+    target_link_libraries(main PRIVATE CURL::libcurl ${ZLIB_LIBRARIES} ws2_32
+        optimized crypt32 CURL)
+
+Link with -lpthread, -lcurl, bcrypt.lib and \"Normaliz.lib\".
+";
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-windows-static",
+            &[("curl", "8.0.0", &["lib/libcurl.lib", "share/curl/usage"])],
+        );
+        let share = root.join("installed").join("x64-windows-static").join("share");
+        fs::write(share.join("curl").join("usage"), usage).unwrap();
+        env::set_var(VCPKGRS_TRIPLET, "x64-windows-static");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("curl")
+            .unwrap();
+        // CURL and -lcurl are the library of the port itself
+        assert_eq!(
+            lib.system_libs,
+            vec!["ws2_32", "crypt32", "pthread", "bcrypt", "Normaliz"]
+        );
+        assert!(!lib
+            .cargo_metadata
+            .contains(&"cargo:rustc-link-lib=ws2_32".to_owned()));
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .emit_usage_libs(true)
+            .find_package("curl")
+            .unwrap();
+        let link_libs: Vec<_> = lib
+            .cargo_metadata
+            .iter()
+            .filter(|line| line.starts_with("cargo:rustc-link-lib="))
+            .collect();
+        assert_eq!(link_libs[0], "cargo:rustc-link-lib=libcurl");
+        assert_eq!(link_libs[1], "cargo:rustc-link-lib=ws2_32");
+        assert_eq!(link_libs.len(), 6);
+        clean_env();
+    }
+
+    #[test]
+    fn tool_only_port() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-windows-static",
+            &[
+                (
+                    "pkgconf",
+                    "2.1.0",
+                    &[
+                        "tools/pkgconf/pkgconf.exe",
+                        "tools/pkgconf/pkgconf.pdb",
+                        "share/pkgconf/copyright",
+                    ],
+                ),
+                ("zlib", "1.3", &["lib/zlib.lib", "tools/zlib/minigzip.exe"]),
+            ],
+        );
+        env::set_var(VCPKGRS_TRIPLET, "x64-windows-static");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let installed = root.join("installed").join("x64-windows-static");
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("pkgconf")
+            .unwrap();
+        assert_eq!(
+            lib.tools,
+            vec![installed.join("tools").join("pkgconf").join("pkgconf.exe")]
+        );
+        assert_eq!(lib.ports, vec!["pkgconf"]);
+        assert!(lib.found_libs.is_empty());
+        assert!(lib.cargo_metadata.is_empty());
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("zlib")
+            .unwrap();
+        assert_eq!(lib.found_names, vec!["zlib"]);
+        assert_eq!(
+            lib.tools,
+            vec![installed.join("tools").join("zlib").join("minigzip.exe")]
+        );
+        clean_env();
+    }
+
+    #[test]
+    fn find_host_package() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-linux",
+            &[(
+                "protobuf",
+                "3.21.12",
+                &["lib/libprotobuf.a", "tools/protobuf/protoc"],
+            )],
+        );
+        env::set_var(VCPKGRS_TRIPLET, "arm64-linux");
+        env::set_var(HOST, "x86_64-unknown-linux-gnu");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        assert!(::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("protobuf")
+            .is_err());
+
+        let installed = root.join("installed").join("x64-linux");
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_host_package("protobuf")
+            .unwrap();
+        assert_eq!(lib.vcpkg_triplet, "x64-linux");
+        assert_eq!(
+            lib.tools,
+            vec![installed.join("tools").join("protobuf").join("protoc")]
+        );
+        assert_eq!(lib.found_libs, vec![installed.join("lib").join("libprotobuf.a")]);
+        assert_eq!(lib.link_paths, vec![installed.join("lib")]);
+
+        env::remove_var(HOST);
+        assert!(::Config::new()
+            .vcpkg_root(root.clone())
+            .find_host_package("protobuf")
+            .is_err());
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .host_triplet("x64-linux")
+            .find_host_package("protobuf")
+            .unwrap();
+        assert_eq!(lib.ports, vec!["protobuf"]);
+        clean_env();
+    }
+
+    #[test]
+    fn triplet_fallbacks() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-windows-static",
+            &[("zlib", "1.3", &["lib/zlib.lib"])],
+        );
+        env::set_var(VCPKGRS_TRIPLET, "x64-windows-static-md");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        assert!(::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("zlib")
+            .is_err());
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .triplet_fallbacks(&["x86-windows-static", "x64-windows-static"])
+            .find_package("zlib")
+            .unwrap();
+        assert_eq!(lib.vcpkg_triplet, "x64-windows-static");
+        assert_eq!(lib.found_names, vec!["zlib"]);
+
+        env::set_var(VCPKGRS_TRIPLET_FALLBACKS, "x86-windows-static; x64-windows-static");
+        let mut cfg = ::Config::new();
+        cfg.vcpkg_root(root.clone());
+        assert_eq!(cfg.find_package("zlib").unwrap().vcpkg_triplet, "x64-windows-static");
+        // the fallback is only for the package that was not found
+        assert_eq!(cfg.target.as_ref().unwrap().name, "x64-windows-static-md");
+
+        env::set_var(VCPKGRS_TRIPLET_FALLBACKS, "x86-windows-static");
+        match ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("zlib")
+        {
+            Err(Error::LibNotFound { triplet, .. }) => assert_eq!(triplet, "x64-windows-static-md"),
+            other => panic!("unexpected result {:?}", other),
+        }
+        clean_env();
+    }
+
+    #[test]
+    fn linkage_preference() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let dynamic_root = tmp_dir.path().join("dynamic");
+        make_vcpkg_tree(
+            &dynamic_root,
+            "x64-linux-dynamic",
+            &[("zlib", "1.3", &["lib/libz.so"])],
+        );
+        let static_root = tmp_dir.path().join("static");
+        make_vcpkg_tree(&static_root, "x64-linux", &[("zlib", "1.3", &["lib/libz.a"])]);
+        env::set_var(TARGET, "x86_64-unknown-linux-gnu");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let find = |root: &Path, preference: Option<::LinkagePreference>| {
+            let mut cfg = ::Config::new();
+            cfg.vcpkg_root(root.to_path_buf());
+            if let Some(preference) = preference {
+                cfg.linkage_preference(preference);
+            }
+            cfg.find_package("zlib").map(|lib| lib.vcpkg_triplet)
+        };
+        assert!(find(&dynamic_root, None).is_err());
+        assert!(find(&dynamic_root, Some(::LinkagePreference::StaticOnly)).is_err());
+        assert_eq!(
+            find(&dynamic_root, Some(::LinkagePreference::DynamicOnly)).unwrap(),
+            "x64-linux-dynamic"
+        );
+        assert_eq!(
+            find(&dynamic_root, Some(::LinkagePreference::PreferStatic)).unwrap(),
+            "x64-linux-dynamic"
+        );
+        assert_eq!(
+            find(&static_root, Some(::LinkagePreference::PreferDynamic)).unwrap(),
+            "x64-linux"
+        );
+        assert!(find(&static_root, Some(::LinkagePreference::DynamicOnly)).is_err());
+
+        // VCPKGRS_DYNAMIC overrides the preference
+        env::set_var(VCPKGRS_DYNAMIC, "1");
+        assert_eq!(
+            find(&dynamic_root, Some(::LinkagePreference::StaticOnly)).unwrap(),
+            "x64-linux-dynamic"
+        );
+        clean_env();
+    }
+
+    #[test]
+    fn static_override_env() {
+        let _g = LOCK.lock();
+        clean_env();
+        env::set_var(TARGET, "x86_64-pc-windows-msvc");
+        let triplet = || msvc_target().unwrap().name;
+
+        env::set_var(VCPKGRS_DYNAMIC, "1");
+        assert_eq!(triplet(), "x64-windows");
+        assert!(dynamic_allowed(&["zlib"]));
+        env::set_var(VCPKGRS_DYNAMIC, "0");
+        assert_eq!(triplet(), "x64-windows-static-md");
+        assert!(!dynamic_allowed(&["zlib"]));
+        env::set_var(VCPKGRS_DYNAMIC, "1");
+        env::set_var(VCPKGRS_STATIC, "1");
+        assert_eq!(triplet(), "x64-windows-static-md");
+        assert!(!dynamic_allowed(&["zlib"]));
+
+        // the static triplet is forced over a linkage preference too
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(&root, "x64-windows", &[("zlib", "1.3", &["lib/zlib.lib"])]);
+        env::set_var(OUT_DIR, tmp_dir.path());
+        let find = || {
+            ::Config::new()
+                .vcpkg_root(root.clone())
+                .linkage_preference(::LinkagePreference::PreferDynamic)
+                .find_package("zlib")
+        };
+        assert!(find().is_err());
+        env::remove_var(VCPKGRS_STATIC);
+        env::remove_var(VCPKGRS_DYNAMIC);
+        assert_eq!(find().unwrap().vcpkg_triplet, "x64-windows");
+        clean_env();
+    }
+
+    #[test]
+    fn package_triplet_env() {
+        let _g = LOCK.lock();
+        clean_env();
+        env::set_var(VCPKG_ROOT, vcpkg_test_tree_loc("normalized"));
+        env::set_var(TARGET, "x86_64-apple-darwin");
+        let tmp_dir = tempdir().unwrap();
+        env::set_var(OUT_DIR, tmp_dir.path());
+        env::set_var("VCPKGRS_TRIPLET_ZLIB", "x64-windows-static");
+
+        let zlib = ::find_package("zlib").unwrap();
+        assert_eq!(zlib.vcpkg_triplet, "x64-windows-static");
+        assert_eq!(zlib.found_names, vec!["zlib"]);
+        let harfbuzz = ::find_package("harfbuzz").unwrap();
+        assert_eq!(harfbuzz.vcpkg_triplet, "x64-osx");
+
+        env::remove_var("VCPKGRS_TRIPLET_ZLIB");
+        let config = || {
+            let mut config = ::Config::new();
+            config.package_triplet("zlib", "x64-windows-static");
+            config
+        };
+        let zlib = config().find_package("zlib").unwrap();
+        assert_eq!(zlib.vcpkg_triplet, "x64-windows-static");
+        let harfbuzz = config().find_package("harfbuzz").unwrap();
+        assert_eq!(harfbuzz.vcpkg_triplet, "x64-osx");
+
+        // the environment has the final say
+        env::set_var("VCPKGRS_TRIPLET_ZLIB", "x64-osx");
+        let zlib = config().find_package("zlib").unwrap();
+        assert_eq!(zlib.vcpkg_triplet, "x64-osx");
+
+        env::remove_var("VCPKGRS_TRIPLET_ZLIB");
+        clean_env();
+    }
+
+    #[test]
+    fn package_dynamic_env() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        let updates = root.join("installed").join("vcpkg").join("updates");
+        make_vcpkg_tree(
+            &root,
+            "x64-windows-static-md",
+            &[("harfbuzz", "8.0.0", &["lib/harfbuzz.lib"])],
+        );
+        fs::rename(updates.join("0000000001"), updates.join("0000000000")).unwrap();
+        make_vcpkg_tree(
+            &root,
+            "x64-windows",
+            &[("zlib", "1.3", &["lib/zlib.lib", "bin/zlib.dll"])],
+        );
+        env::set_var(VCPKG_ROOT, &root);
+        env::set_var(TARGET, "x86_64-pc-windows-msvc");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        assert!(::find_package("zlib").is_err());
+
+        env::set_var("VCPKGRS_DYNAMIC_ZLIB", "1");
+        let zlib = ::find_package("zlib").unwrap();
+        assert_eq!(zlib.vcpkg_triplet, "x64-windows");
+        assert!(!zlib.is_static);
+        assert_eq!(
+            zlib.found_dlls,
+            vec![root.join("installed").join("x64-windows").join("bin").join("zlib.dll")]
+        );
+        let harfbuzz = ::find_package("harfbuzz").unwrap();
+        assert_eq!(harfbuzz.vcpkg_triplet, "x64-windows-static-md");
+        assert!(harfbuzz.is_static);
+
+        // an explicitly chosen dynamic triplet needs the opt-in too
+        let zlib = ::Config::new()
+            .target_triplet("x64-windows")
+            .find_package("zlib")
+            .unwrap();
+        assert!(!zlib.is_static);
+        env::remove_var("VCPKGRS_DYNAMIC_ZLIB");
+        match ::Config::new()
+            .target_triplet("x64-windows")
+            .find_package("zlib")
+        {
+            Err(Error::RequiredEnvMissing(ref var)) if var == VCPKGRS_DYNAMIC => {}
+            _ => panic!("zlib was not opted into dynamic linking"),
+        }
+        clean_env();
+    }
+
+    #[test]
+    fn rust_target_triplet_env() {
+        let _g = LOCK.lock();
+        clean_env();
+        env::set_var(VCPKG_ROOT, vcpkg_test_tree_loc("normalized"));
+        env::set_var(TARGET, "x86_64-apple-darwin");
+        let tmp_dir = tempdir().unwrap();
+        env::set_var(OUT_DIR, tmp_dir.path());
+        env::set_var(VCPKGRS_TRIPLET, "x64-osx");
+        env::set_var("VCPKGRS_TRIPLET_X86_64_APPLE_DARWIN", "x64-windows-static");
+        env::set_var("VCPKGRS_TRIPLET_AARCH64_APPLE_IOS", "x64-osx");
+
+        let zlib = ::find_package("zlib").unwrap();
+        assert_eq!(zlib.vcpkg_triplet, "x64-windows-static");
+
+        env::set_var(TARGET, "aarch64-apple-ios");
+        let harfbuzz = ::find_package("harfbuzz").unwrap();
+        assert_eq!(harfbuzz.vcpkg_triplet, "x64-osx");
+
+        env::remove_var("VCPKGRS_TRIPLET_X86_64_APPLE_DARWIN");
+        env::remove_var("VCPKGRS_TRIPLET_AARCH64_APPLE_IOS");
+        clean_env();
+    }
+
+    #[test]
+    fn copy_dlls_filter() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        let out_dir = tmp_dir.path().join("out");
+        fs::create_dir(&out_dir).unwrap();
+        make_vcpkg_tree(
+            &root,
+            "x64-windows",
+            &[(
+                "qt5-base",
+                "5.15.2",
+                &[
+                    "lib/Qt5Core.lib",
+                    "lib/Qt5Sql.lib",
+                    "bin/Qt5Core.dll",
+                    "bin/Qt5Sql.dll",
+                ],
+            )],
+        );
+        env::set_var(VCPKGRS_TRIPLET, "x64-windows");
+        env::set_var(VCPKGRS_DYNAMIC, "1");
+        env::set_var(OUT_DIR, &out_dir);
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .copy_dlls_filter(|dll| dll.ends_with("Qt5Core.dll"))
+            .find_package("qt5-base")
+            .unwrap();
+        assert_eq!(lib.found_dlls.len(), 2);
+        assert!(out_dir.join("Qt5Core.dll").exists());
+        assert!(!out_dir.join("Qt5Sql.dll").exists());
+        clean_env();
+    }
+
+    #[test]
+    fn dll_copy_mode() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        let out_dir = tmp_dir.path().join("out");
+        fs::create_dir(&out_dir).unwrap();
+        make_vcpkg_tree(
+            &root,
+            "x64-windows",
+            &[("zlib", "1.3", &["lib/zlib.lib", "bin/zlib1.dll"])],
+        );
+        let dll = root.join("installed/x64-windows/bin/zlib1.dll");
+        fs::write(&dll, "release").unwrap();
+        env::set_var(VCPKGRS_TRIPLET, "x64-windows");
+        env::set_var(VCPKGRS_DYNAMIC, "1");
+        env::set_var(OUT_DIR, &out_dir);
+        let copied = out_dir.join("zlib1.dll");
+        let find = |mode| {
+            ::Config::new()
+                .vcpkg_root(root.clone())
+                .dll_copy_mode(mode)
+                .find_package("zlib")
+                .unwrap()
+        };
+
+        find(CopyMode::Hardlink);
+        fs::write(&dll, "rebuilt").unwrap();
+        assert_eq!(fs::read_to_string(&copied).unwrap(), "rebuilt");
+
+        find(CopyMode::Symlink);
+        if cfg!(unix) {
+            assert!(fs::symlink_metadata(&copied)
+                .unwrap()
+                .file_type()
+                .is_symlink());
+        }
+        assert_eq!(fs::read_to_string(&copied).unwrap(), "rebuilt");
+
+        // copying replaces the link rather than writing through it
+        find(CopyMode::Copy);
+        assert!(!fs::symlink_metadata(&copied)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        fs::write(&copied, "modified").unwrap();
+        assert_eq!(fs::read_to_string(&dll).unwrap(), "rebuilt");
+        clean_env();
+    }
+
+    #[test]
+    fn copy_dlls_to_profile_dir() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        let profile_dir = tmp_dir.path().join("target").join("debug");
+        let out_dir = profile_dir
+            .join("build")
+            .join("zlib-sys-0123456789abcdef")
+            .join("out");
+        fs::create_dir_all(&out_dir).unwrap();
+        make_vcpkg_tree(
+            &root,
+            "x64-windows",
+            &[("zlib", "1.3", &["lib/zlib.lib", "bin/zlib.dll"])],
+        );
+        env::set_var(VCPKGRS_TRIPLET, "x64-windows");
+        env::set_var(VCPKGRS_DYNAMIC, "1");
+        env::set_var(OUT_DIR, &out_dir);
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .copy_dlls(false)
+            .copy_dlls_to_profile_dir(true)
+            .find_package("zlib")
+            .unwrap();
+        assert!(profile_dir.join("zlib.dll").exists());
+        assert!(profile_dir.join("deps").join("zlib.dll").exists());
+        assert!(!out_dir.join("zlib.dll").exists());
+        assert!(!lib
+            .cargo_metadata
+            .iter()
+            .any(|l| l.ends_with(&*out_dir.to_string_lossy())));
+
+        // OUT_DIR is not where cargo would put it
+        env::set_var(OUT_DIR, tmp_dir.path());
+        match ::Config::new()
+            .vcpkg_root(root.clone())
+            .copy_dlls_to_profile_dir(true)
+            .find_package("zlib")
+        {
+            Err(Error::Io { ref path, .. }) => assert_eq!(path, tmp_dir.path()),
+            _ => panic!("there is no profile directory above OUT_DIR"),
+        }
+        clean_env();
+    }
+
+    #[test]
+    fn binary_cache() {
+        // archives are created with the zip tool
+        if Command::new("zip").arg("-v").output().is_err() {
+            return;
+        }
+
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let cache = tmp_dir.path().join("archives");
+        let out_dir = tmp_dir.path().join("out");
+        fs::create_dir(&out_dir).unwrap();
+        let ports: &[(&str, &str, &str, &[&str])] = &[
+            (
+                "ab12",
+                "curl",
+                "Depends: vcpkg-cmake:x64-linux, zlib\n",
+                &["include/curl/curl.h", "lib/libcurl.a"],
+            ),
+            ("cd34", "zlib", "", &["include/zlib.h", "lib/libz.a"]),
+            ("ef56", "libssh2", "Depends: openssl\n", &["lib/libssh2.a"]),
+        ];
+        for &(hash, port, depends, files) in ports {
+            let staging = tmp_dir.path().join(hash);
+            for file in files {
+                fs::create_dir_all(staging.join(file).parent().unwrap()).unwrap();
+                fs::write(staging.join(file), "").unwrap();
+            }
+            fs::write(
+                staging.join("CONTROL"),
+                format!(
+                    "Package: {}\nVersion: 1.0\n{}Architecture: x64-linux\n",
+                    port, depends
+                ),
+            )
+            .unwrap();
+            let archive_dir = cache.join(&hash[..2]);
+            fs::create_dir_all(&archive_dir).unwrap();
+            let status = Command::new("zip")
+                .arg("-qr")
+                .arg(archive_dir.join(format!("{}.zip", hash)))
+                .arg(".")
+                .current_dir(&staging)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        }
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        env::set_var(OUT_DIR, &out_dir);
+        env::set_var("VCPKG_DEFAULT_BINARY_CACHE", &cache);
+
+        // there is no vcpkg tree
+        assert!(::find_package("curl").is_err());
+
+        let lib = ::Config::new()
+            .binary_cache(true)
+            .find_package("curl")
+            .unwrap();
+        let lib_path = out_dir
+            .join("vcpkg-binary-cache")
+            .join("installed")
+            .join("x64-linux")
+            .join("lib");
+        assert_eq!(lib.ports, vec!["curl", "zlib"]);
+        assert_eq!(lib.found_names, vec!["curl", "z"]);
+        assert_eq!(lib.link_paths, vec![lib_path.clone()]);
+        assert!(lib_path.join("libz.a").exists());
+        // the CONTROL files are only read from the archives once
+        let index = out_dir.join("vcpkg-binary-cache-index");
+        assert!(index.join("ab12.control").exists());
+        assert!(index.join("cd34.control").exists());
+
+        // the same archives are not unpacked again
+        fs::write(lib_path.join("libz.a"), "modified").unwrap();
+        ::Config::new()
+            .binary_cache(true)
+            .find_package("curl")
+            .unwrap();
+        assert_eq!(fs::read_to_string(lib_path.join("libz.a")).unwrap(), "modified");
+
+        match ::Config::new().binary_cache(true).find_package("openssl") {
+            Err(Error::VcpkgNotFound(_)) => {}
+            _ => panic!("expected VcpkgNotFound"),
+        }
+        match ::Config::new().binary_cache(true).find_package("libssh2") {
+            Err(Error::VcpkgInstallation(ref msg)) if msg.contains("depends on openssl") => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        env::remove_var("VCPKG_DEFAULT_BINARY_CACHE");
+        clean_env();
+    }
+
+    #[test]
+    fn arm_windows_targets() {
+        let _g = LOCK.lock();
+        clean_env();
+        let triplet = |is_static, is_definitely_dynamic| {
+            default_triplet("thumbv7a-pc-windows-msvc", is_static, is_definitely_dynamic)
+                .unwrap()
+                .name
+        };
+        assert_eq!(triplet(false, false), "arm-windows-static-md");
+        assert_eq!(triplet(true, false), "arm-windows-static");
+        assert_eq!(triplet(false, true), "arm-windows");
+        assert_eq!(
+            compatibility("thumbv7a-pc-windows-msvc", "arm-windows-static-md"),
+            Compatibility::KnownGood
+        );
+        assert_eq!(
+            compatibility("thumbv7a-pc-windows-msvc", "arm64-windows-static-md"),
+            Compatibility::Mismatched(Mismatch::Arch)
+        );
+
+        let tmp_dir = tempdir().unwrap();
+        for &(triplet, dynamic, crt_static) in &[
+            ("arm-windows-static-md", false, false),
+            ("arm-windows-static", false, true),
+            ("arm-windows", true, false),
+        ] {
+            let root = tmp_dir.path().join(triplet);
+            make_vcpkg_tree(
+                &root,
+                triplet,
+                &[(
+                    "zlib",
+                    "1.3",
+                    &["include/zlib.h", "lib/zlib.lib", "bin/zlib1.dll"],
+                )],
+            );
+            env::set_var(VCPKG_ROOT, &root);
+            env::set_var(TARGET, "thumbv7a-pc-windows-msvc");
+            env::set_var(OUT_DIR, tmp_dir.path());
+            if dynamic {
+                env::set_var(VCPKGRS_DYNAMIC, "1");
+            }
+            if crt_static {
+                env::set_var(CARGO_CFG_TARGET_FEATURE, "crt-static");
+            }
+            let lib = ::Config::new().copy_dlls(false).find_package("zlib").unwrap();
+            assert_eq!(lib.vcpkg_triplet, triplet);
+            assert_eq!(lib.is_static, !dynamic);
+            let installed = root.join("installed").join(triplet);
+            assert_eq!(lib.found_libs, vec![installed.join("lib").join("zlib.lib")]);
+            if dynamic {
+                assert_eq!(lib.found_dlls, vec![installed.join("bin").join("zlib1.dll")]);
+            }
+            clean_env();
+        }
+    }
+
+    #[test]
+    fn android_targets() {
+        let _g = LOCK.lock();
+        clean_env();
+        let triplet = |target| default_triplet(target, false, false).unwrap().name;
+        assert_eq!(triplet("aarch64-linux-android"), "arm64-android");
+        assert_eq!(triplet("x86_64-linux-android"), "x64-android");
+        assert_eq!(triplet("i686-linux-android"), "x86-android");
+        assert_eq!(triplet("armv7-linux-androideabi"), "arm-neon-android");
+        assert_eq!(triplet("thumbv7neon-linux-androideabi"), "arm-neon-android");
+        match default_triplet("arm-linux-androideabi", false, false) {
+            Err(Error::NotMSVC) => {}
+            _ => panic!("there is no triplet for armv6 android"),
+        }
+        assert_eq!(
+            compatibility("armv7-linux-androideabi", "arm-neon-android"),
+            Compatibility::KnownGood
+        );
+
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "arm64-android",
+            &[("zlib", "1.3", &["include/zlib.h", "lib/libz.a"])],
+        );
+        env::set_var(VCPKG_ROOT, &root);
+        env::set_var(TARGET, "aarch64-linux-android");
+        env::set_var(OUT_DIR, tmp_dir.path());
+        let lib = ::find_package("zlib").unwrap();
+        assert!(lib.is_static);
+        assert_eq!(lib.found_names, vec!["z"]);
+        assert!(lib
+            .cargo_metadata
+            .contains(&"cargo:rustc-link-lib=z".to_owned()));
+        clean_env();
+    }
+
+    #[test]
+    fn port_cache() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        let zlib: (&str, &str, &[&str]) = ("zlib", "1.3", &["include/zlib.h", "lib/libz.a"]);
+        make_vcpkg_tree(&root, "x64-linux", &[zlib]);
+        env::set_var(VCPKG_ROOT, &root);
+        env::set_var(TARGET, "x86_64-unknown-linux-gnu");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        ::find_package("zlib").unwrap();
+
+        // the manifests are not read again while the status database is unchanged
+        let status_path = root.join("installed").join("vcpkg");
+        fs::remove_file(status_path.join("info").join("zlib_1.3_x64-linux.list")).unwrap();
+        ::find_package("zlib").unwrap();
+        assert!(::find_package("bzip2").is_err());
+
+        // but ports installed since then are found
+        let updates = status_path.join("updates");
+        fs::rename(updates.join("0000000001"), updates.join("0000000000")).unwrap();
+        make_vcpkg_tree(
+            &root,
+            "x64-linux",
+            &[zlib, ("bzip2", "1.0.8", &["lib/libbz2.a"])],
+        );
+        assert_eq!(::find_package("bzip2").unwrap().found_names, vec!["bz2"]);
+        clean_env();
+    }
+
+    #[test]
+    fn lazy_port_manifests() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-linux",
+            &[
+                ("zlib", "1.3", &["include/zlib.h", "lib/libz.a"]),
+                ("bzip2", "1.0.8", &["lib/libbz2.a"]),
+            ],
+        );
+        env::set_var(VCPKG_ROOT, &root);
+        env::set_var(TARGET, "x86_64-unknown-linux-gnu");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        // only the manifests of the port being found and its dependencies are read
+        let info = root.join("installed").join("vcpkg").join("info");
+        fs::remove_file(info.join("bzip2_1.0.8_x64-linux.list")).unwrap();
+        assert_eq!(::find_package("zlib").unwrap().ports, vec!["zlib"]);
+        match ::find_package("bzip2") {
+            Err(Error::Io { ref path, .. }) => assert!(path.ends_with("bzip2_1.0.8_x64-linux.list")),
+            other => panic!("unexpected result {:?}", other),
+        }
+        clean_env();
+    }
+
+    #[test]
+    fn installation() {
+        let _g = LOCK.lock();
+        clean_env();
+        env::set_var(VCPKG_ROOT, vcpkg_test_tree_loc("normalized"));
+        env::set_var(TARGET, "x86_64-apple-darwin");
+        let tmp_dir = tempdir().unwrap();
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let vcpkg = ::VcpkgInstallation::new().unwrap();
+        assert_eq!(vcpkg.triplet(), "x64-osx");
+        assert_eq!(
+            vcpkg.installed_root(),
+            vcpkg_test_tree_loc("normalized").join("installed")
+        );
+        assert_eq!(
+            vcpkg.ports().get("zlib").map(|v| v.as_str()),
+            Some("1.2.11-3")
+        );
+
+        // the tree and triplet are not looked for again
+        env::set_var(VCPKG_ROOT, tmp_dir.path());
+        env::set_var(TARGET, "x86_64-unknown-linux-gnu");
+        assert!(vcpkg.is_installed("zlib").unwrap());
+        assert!(!vcpkg.is_installed("not_a_port").unwrap());
+        let lib = vcpkg.find_package("harfbuzz").unwrap();
+        assert_eq!(lib.vcpkg_triplet, "x64-osx");
+        let lib = vcpkg
+            .config()
+            .emit_includes(true)
+            .cargo_metadata(false)
+            .find_package("zlib")
+            .unwrap();
+        assert!(lib
+            .cargo_metadata
+            .iter()
+            .any(|line| line.starts_with("cargo:include=")));
+        clean_env();
+    }
+
+    #[test]
+    fn error_details() {
+        use std::error::Error as StdError;
+
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-linux",
+            &[
+                ("zlib", "1.3", &["include/zlib.h", "lib/libz.a"]),
+                ("bzip2", "1.0.8", &["lib/libbz2.a"]),
+            ],
+        );
+        env::set_var(VCPKG_ROOT, &root);
+        env::set_var(TARGET, "x86_64-unknown-linux-gnu");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        match ::find_package("libpng") {
+            Err(Error::LibNotFound {
+                ref port,
+                ref triplet,
+                path: None,
+                ..
+            }) => {
+                assert_eq!(port, "libpng");
+                assert_eq!(triplet, "x64-linux");
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let lib_path = root.join("installed").join("x64-linux").join("lib");
+        fs::remove_file(lib_path.join("libz.a")).unwrap();
+        match ::find_package("zlib") {
+            Err(Error::LibNotFound {
+                ref port,
+                path: Some(ref path),
+                ..
+            }) => {
+                assert_eq!(port, "zlib");
+                assert_eq!(path, &lib_path.join("libz.a"));
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let info = root.join("installed").join("vcpkg").join("info");
+        fs::remove_file(info.join("bzip2_1.0.8_x64-linux.list")).unwrap();
+        let err = ::find_package("bzip2").unwrap_err();
+        match err {
+            Error::Io { ref error, .. } => {
+                assert_eq!(error.kind(), ::std::io::ErrorKind::NotFound)
+            }
+            ref other => panic!("unexpected error {:?}", other),
+        }
+        assert!(err.source().is_some());
+        clean_env();
+    }
+
+    #[test]
+    fn near_miss_triplets() {
+        let _g = LOCK.lock();
+        clean_env();
+        env::set_var(VCPKG_ROOT, vcpkg_test_tree_loc("normalized"));
+        env::set_var(TARGET, "x86_64-pc-windows-msvc");
+        let tmp_dir = tempdir().unwrap();
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let err = ::find_package("zlib").unwrap_err();
+        match err {
+            Error::LibNotFound {
+                ref triplet,
+                ref installed_triplets,
+                ref install_command,
+                ..
+            } => {
+                assert_eq!(triplet, "x64-windows-static-md");
+                assert_eq!(
+                    install_command.as_ref().unwrap(),
+                    "vcpkg install zlib:x64-windows-static-md"
+                );
+                assert_eq!(
+                    installed_triplets,
+                    &["arm64-ios", "x64-osx", "x64-windows", "x64-windows-static", "x86-windows"]
+                );
+            }
+            ref other => panic!("unexpected error {:?}", other),
+        }
+        assert!(err.to_string().contains(
+            "package zlib is not installed for vcpkg triplet x64-windows-static-md, but is \
+             installed for triplets arm64-ios, x64-osx, x64-windows, x64-windows-static, x86-windows"
+        ));
+        assert!(err
+            .to_string()
+            .ends_with(", install it with `vcpkg install zlib:x64-windows-static-md`"));
+
+        match ::find_package("not_a_port") {
+            Err(Error::LibNotFound {
+                ref installed_triplets,
+                ..
+            }) => assert!(installed_triplets.is_empty()),
+            other => panic!("unexpected result {:?}", other),
+        }
+        clean_env();
+    }
+
+    #[test]
+    fn broken_ports() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-linux",
+            &[
+                ("foo", "1.0", &["lib/libfoo.a"]),
+                ("bar", "1.0", &["lib/libbar.a"]),
+                ("baz", "1.0", &["lib/libbaz.a"]),
+            ],
+        );
+        set_status_fields(&root, "foo", &["Depends: bar"]);
+        set_status_fields(&root, "bar", &["Status: install ok half-installed"]);
+        set_status_fields(&root, "baz", &["Status: purge ok not-installed"]);
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        assert!(!is_broken_status("install ok installed"));
+        assert!(!is_broken_status("purge ok not-installed"));
+        assert!(is_broken_status("install ok half-installed"));
+        assert!(is_broken_status("purge ok half-installed"));
+        assert!(is_broken_status("install ok not-installed"));
+
+        for port in &["foo", "bar"] {
+            match ::Config::new().vcpkg_root(root.clone()).find_package(port) {
+                Err(ref err @ Error::BrokenPort { .. }) => {
+                    assert!(err.to_string().contains(
+                        "Package bar is in a broken state for vcpkg triplet x64-linux \
+                         (install ok half-installed), run `vcpkg remove bar:x64-linux`"
+                    ));
+                }
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+        match ::Config::new().vcpkg_root(root.clone()).find_package("baz") {
+            Err(Error::LibNotFound { .. }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        clean_env();
+    }
+
+    #[test]
+    fn musl_fallback() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-linux",
+            &[("zlib", "1.3", &["include/zlib.h", "lib/libz.a"])],
+        );
+        env::set_var(VCPKG_ROOT, &root);
+        env::set_var(TARGET, "x86_64-unknown-linux-musl");
+        env::set_var(CARGO_CFG_TARGET_FEATURE, "crt-static");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        match ::find_package("zlib") {
+            Err(Error::NotMSVC) => {}
+            _ => panic!("the musl fallback is opt-in"),
+        }
+
+        let lib = ::Config::new()
+            .allow_musl_fallback(true)
+            .find_package("zlib")
+            .unwrap();
+        assert_eq!(lib.vcpkg_triplet, "x64-linux");
+        assert_eq!(lib.found_names, vec!["z"]);
+        assert!(lib.cargo_metadata.contains(
+            &"cargo:warning=linking musl target x86_64-unknown-linux-musl to libraries \
+              from vcpkg triplet x64-linux, which were built against glibc"
+                .to_owned()
+        ));
+
+        env::set_var(VCPKGRS_MUSL_FALLBACK, "1");
+        assert_eq!(::find_package("zlib").unwrap().vcpkg_triplet, "x64-linux");
+
+        // a triplet that was chosen explicitly is not warned about
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        let lib = ::find_package("zlib").unwrap();
+        assert!(!lib
+            .cargo_metadata
+            .iter()
+            .any(|x| x.contains("built against glibc")));
+        clean_env();
+    }
+
+    #[test]
+    fn map_target() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "riscv64-linux",
+            &[("zlib", "1.3", &["include/zlib.h", "lib/libz.a"])],
+        );
+        env::set_var(VCPKG_ROOT, &root);
+        env::set_var(TARGET, "riscv64gc-unknown-linux-gnu");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        match ::find_package("zlib") {
+            Err(Error::NotMSVC) => {}
+            _ => panic!("there is no default triplet for riscv64"),
+        }
+
+        let lib = ::Config::new()
+            .map_target("x86_64-unknown-linux-gnu", "x64-linux-custom")
+            .map_target("riscv64gc-unknown-linux-gnu", "riscv64-linux")
+            .find_package("zlib")
+            .unwrap();
+        assert_eq!(lib.vcpkg_triplet, "riscv64-linux");
+        assert_eq!(lib.found_names, vec!["z"]);
+
+        env::set_var(
+            VCPKGRS_TARGET_MAP,
+            "x86_64-unknown-linux-gnu=x64-linux-custom; riscv64gc-unknown-linux-gnu=riscv64-linux",
+        );
+        assert_eq!(::find_package("zlib").unwrap().vcpkg_triplet, "riscv64-linux");
+
+        // an explicitly chosen triplet wins
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        match ::find_package("zlib") {
+            Err(Error::LibNotFound { .. }) => {}
+            _ => panic!("zlib is not installed for x64-linux"),
+        }
+        clean_env();
+    }
+
+    #[test]
+    fn default_triplet_for() {
+        let _g = LOCK.lock();
+        clean_env();
+        // the environment is not looked at
+        env::set_var(VCPKGRS_DYNAMIC, "1");
+        env::set_var(TARGET, "x86_64-unknown-linux-gnu");
+        for &(target, triplet) in &[
+            ("x86_64-pc-windows-msvc", "x64-windows-static-md"),
+            ("i686-pc-windows-msvc", "x86-windows-static-md"),
+            ("x86_64-pc-windows-gnu", "x64-mingw-static"),
+            ("x86_64-unknown-linux-gnu", "x64-linux"),
+            ("aarch64-apple-darwin", "arm64-osx"),
+            ("aarch64-linux-android", "arm64-android"),
+        ] {
+            assert_eq!(::default_triplet_for(target).unwrap(), triplet);
+        }
+        match ::default_triplet_for("wasm32-unknown-unknown") {
+            Err(Error::NotMSVC) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        clean_env();
+    }
+
+    #[test]
+    fn mingw_targets() {
+        let _g = LOCK.lock();
+        clean_env();
+        let triplet = |target| default_triplet(target, false, false).unwrap().name;
+        assert_eq!(triplet("x86_64-pc-windows-gnu"), "x64-mingw-static");
+        assert_eq!(triplet("i686-pc-windows-gnu"), "x86-mingw-static");
+        assert_eq!(triplet("aarch64-pc-windows-gnullvm"), "arm64-mingw-static");
+        assert_eq!(
+            compatibility("x86_64-pc-windows-gnu", "x64-mingw-dynamic"),
+            Compatibility::KnownGood
+        );
+
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-mingw-dynamic",
+            &[
+                (
+                    "zlib",
+                    "1.3",
+                    &[
+                        "include/zlib.h",
+                        "lib/libzlib.a",
+                        "lib/libzlib.dll.a",
+                        "bin/libzlib1.dll",
+                    ],
+                ),
+                (
+                    "sdl2",
+                    "2.30.0",
+                    &["lib/libSDL2.dll.a", "lib/libSDL2main.a", "bin/SDL2.dll"],
+                ),
+            ],
+        );
+        env::set_var(TARGET, "x86_64-pc-windows-gnu");
+        env::set_var(VCPKGRS_DYNAMIC, "1");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let installed = root.join("installed").join("x64-mingw-dynamic");
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("zlib")
+            .unwrap();
+        assert_eq!(lib.vcpkg_triplet, "x64-mingw-dynamic");
+        assert!(!lib.is_static);
+        assert_eq!(lib.found_names, vec!["zlib"]);
+        assert_eq!(
+            lib.found_libs,
+            vec![installed.join("lib").join("libzlib.dll.a")]
+        );
+        assert_eq!(
+            lib.found_dlls,
+            vec![installed.join("bin").join("libzlib1.dll")]
+        );
+        assert!(tmp_dir.path().join("libzlib1.dll").exists());
+
+        // static archives next to the import libraries are linked statically, unless
+        // they are shadowed by an import library of the same name
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("sdl2")
+            .unwrap();
+        assert_eq!(lib.found_names, vec!["SDL2", "SDL2main"]);
+        assert!(lib
+            .cargo_metadata
+            .contains(&"cargo:rustc-link-lib=static=SDL2main".to_owned()));
+        assert_eq!(
+            lib.found_libs,
+            vec![
+                installed.join("lib").join("libSDL2.dll.a"),
+                installed.join("lib").join("libSDL2main.a"),
+            ]
+        );
+        clean_env();
+    }
+
+    #[cfg(feature = "cc")]
+    #[test]
+    fn apply_to_cc_build() {
+        let _g = LOCK.lock();
+        clean_env();
+        env::set_var(VCPKG_ROOT, vcpkg_test_tree_loc("normalized"));
+        env::set_var(TARGET, "x86_64-apple-darwin");
+        let tmp_dir = tempdir().unwrap();
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let lib = ::Config::new()
+            .define("ZLIB_CONST", Some("1"))
+            .find_package("zlib")
+            .unwrap();
+        let mut build = cc::Build::new();
+        build
+            .target("x86_64-unknown-linux-gnu")
+            .host("x86_64-unknown-linux-gnu")
+            .opt_level(0)
+            .cargo_metadata(false);
+        lib.apply_to(&mut build);
+        let args: Vec<String> = build
+            .get_compiler()
+            .args()
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        let include = lib.include_paths[0].display().to_string();
+        assert!(args.windows(2).any(|pair| pair[0] == "-I" && pair[1] == include)
+            || args.contains(&format!("-I{}", include)));
+        assert!(args.contains(&"-DZLIB_CONST=1".to_owned()));
+        clean_env();
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        extern crate serde_json;
+
+        let _g = LOCK.lock();
+        clean_env();
+        env::set_var(VCPKG_ROOT, vcpkg_test_tree_loc("normalized"));
+        env::set_var(TARGET, "x86_64-apple-darwin");
+        let tmp_dir = tempdir().unwrap();
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let lib = ::find_package("harfbuzz").unwrap();
+        let json = serde_json::to_string(&lib).unwrap();
+        let restored: ::Library = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.found_libs, lib.found_libs);
+        assert_eq!(restored.directives(), lib.directives());
+        assert_eq!(restored.ports, lib.ports);
+        assert_eq!(restored.vcpkg_triplet, "x64-osx");
+
+        let err = ::find_package("not_a_port").unwrap_err();
+        let json = serde_json::to_string(&err).unwrap();
+        let restored: ::Error = serde_json::from_str(&json).unwrap();
+        assert_eq!(format!("{:?}", restored), format!("{:?}", err));
+        clean_env();
+    }
+
+    #[cfg(feature = "bootstrap")]
+    #[test]
+    fn bootstrap_target_dir() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let target = tmp_dir.path().join("target");
+        let out_dir = target
+            .join("debug")
+            .join("build")
+            .join("foo-1234")
+            .join("out");
+        fs::create_dir_all(&out_dir).unwrap();
+        fs::write(target.join("CACHEDIR.TAG"), "").unwrap();
+        env::remove_var("CARGO_TARGET_DIR");
+        env::set_var(OUT_DIR, &out_dir);
+        assert_eq!(bootstrap::target_dir(), Some(target));
+        clean_env();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn install_missing() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(&root, "x64-linux", &[]);
+        let tool = root.join("vcpkg");
+        fs::write(
+            &tool,
+            "#!/bin/sh
+echo \"$@\" > args
+mkdir -p installed/x64-linux/lib
+touch installed/x64-linux/lib/libz.a
+echo x64-linux/lib/libz.a > installed/vcpkg/info/zlib_1.3_x64-linux.list
+printf 'Package: zlib\\nVersion: 1.3\\nArchitecture: x64-linux\\nStatus: install ok installed\\n' \\
+    > installed/vcpkg/updates/0000000002
+",
+        )
+        .unwrap();
+        fs::set_permissions(&tool, fs::Permissions::from_mode(0o755)).unwrap();
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        assert!(::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("zlib")
+            .is_err());
+        assert!(!root.join("args").exists());
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .install_missing(true)
+            .find_package("zlib")
+            .unwrap();
+        assert_eq!(lib.found_names, vec!["z"]);
+        assert_eq!(
+            fs::read_to_string(root.join("args")).unwrap(),
+            "install zlib:x64-linux\n"
+        );
+        clean_env();
+    }
+
+    #[test]
+    fn port_features() {
+        let _g = LOCK.lock();
+        clean_env();
+        env::set_var(VCPKG_ROOT, vcpkg_test_tree_loc("normalized"));
+        env::set_var(VCPKGRS_TRIPLET, "x86-windows");
+        env::set_var(VCPKGRS_DYNAMIC, "1");
+        let tmp_dir = tempdir().unwrap();
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let lib = ::Config::new()
+            .copy_dlls(false)
+            .find_package("harfbuzz")
+            .unwrap();
+        assert!(lib.ports.contains(&"icu".to_owned()));
+
+        let lib = ::Config::new()
+            .copy_dlls(false)
+            .feature("ucdn")
+            .find_package("harfbuzz")
+            .unwrap();
+        assert!(!lib.ports.contains(&"icu".to_owned()));
+        assert!(!lib.found_names.iter().any(|name| name.starts_with("icu")));
+
+        let lib = ::Config::new()
+            .copy_dlls(false)
+            .features(&["core", "icu"])
+            .find_package("harfbuzz")
+            .unwrap();
+        assert!(lib.ports.contains(&"icu".to_owned()));
+
+        match ::Config::new()
+            .feature("graphite2")
+            .find_package("harfbuzz")
+        {
+            Err(Error::LibNotFound { ref detail, .. }) => assert!(detail.contains("graphite2")),
+            _ => panic!("expected LibNotFound"),
+        }
+        clean_env();
+    }
+
+    #[test]
+    fn default_features() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_feature_tree(&root, Some("icu"));
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let find = |cfg: &mut ::Config| cfg.vcpkg_root(root.clone()).find_package("foo").unwrap();
+        assert_eq!(
+            find(&mut ::Config::new()).found_names,
+            vec!["foo", "png16", "icuuc"]
+        );
+        assert_eq!(
+            find(::Config::new().feature("png")).found_names,
+            vec!["foo", "png16", "icuuc"]
+        );
+        assert_eq!(
+            find(::Config::new().default_features(false).feature("png")).found_names,
+            vec!["foo", "png16"]
+        );
+        assert_eq!(
+            find(::Config::new().default_features(false)).found_names,
+            vec!["foo"]
+        );
+        assert_eq!(
+            find(::Config::new().features(&["core", "png"])).found_names,
+            vec!["foo", "png16"]
+        );
+        clean_env();
+    }
+
+    #[test]
+    fn manual_link_libs() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-linux",
+            &[
+                ("app", "1.0", &["lib/libapp.a"]),
+                (
+                    "sdl2",
+                    "2.28.5",
+                    &[
+                        "lib/libSDL2.a",
+                        "lib/manual-link/libSDL2main.a",
+                        "lib/manual-link/libSDL2_test.a",
+                    ],
+                ),
+            ],
+        );
+        set_status_fields(&root, "app", &["Depends: sdl2"]);
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        env::set_var(OUT_DIR, tmp_dir.path());
+        let manual_link = root.join("installed/x64-linux/lib/manual-link");
+        let search = format!("cargo:rustc-link-search=native={}", manual_link.display());
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("app")
+            .unwrap();
+        assert_eq!(lib.found_names, vec!["app", "SDL2"]);
+        assert!(!lib.cargo_metadata.contains(&search));
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .manual_link_lib("SDL2main")
+            .find_package("app")
+            .unwrap();
+        assert_eq!(lib.found_names, vec!["app", "SDL2main", "SDL2"]);
+        assert!(lib.cargo_metadata.contains(&search));
+        assert!(lib.found_libs.contains(&manual_link.join("libSDL2main.a")));
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .include_manual_link(true)
+            .find_package("sdl2")
+            .unwrap();
+        assert_eq!(lib.found_names, vec!["SDL2main", "SDL2_test", "SDL2"]);
+
+        match ::Config::new()
+            .vcpkg_root(root.clone())
+            .manual_link_lib("SDL2_nope")
+            .find_package("app")
+        {
+            Err(Error::LibNotFound { ref detail, .. }) => assert!(detail.contains("SDL2_nope")),
+            _ => panic!("expected LibNotFound"),
+        }
+        clean_env();
+    }
+
+    #[test]
+    fn extra_lib_subdirs() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-linux",
+            &[(
+                "openssl",
+                "3.1.4",
+                &[
+                    "lib/libssl.a",
+                    "lib/libcrypto.a",
+                    "lib/ossl-modules/liblegacy.a",
+                    "lib/engines-3/libpadlock.a",
+                ],
+            )],
+        );
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        env::set_var(OUT_DIR, tmp_dir.path());
+        let modules = root.join("installed/x64-linux/lib/ossl-modules");
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("openssl")
+            .unwrap();
+        assert_eq!(lib.found_names, vec!["ssl", "crypto"]);
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .extra_lib_subdirs(&["ossl-modules"])
+            .find_package("openssl")
+            .unwrap();
+        assert_eq!(lib.found_names, vec!["ssl", "crypto", "legacy"]);
+        assert!(lib.link_paths.contains(&modules));
+        assert!(lib.cargo_metadata.contains(&format!(
+            "cargo:rustc-link-search=native={}",
+            modules.display()
+        )));
+        assert_eq!(lib.port_libs["openssl"][2], modules.join("liblegacy.a"));
+        clean_env();
+    }
+
+    #[test]
+    fn rerun_if_changed() {
+        let _g = LOCK.lock();
+        clean_env();
+        let tmp_dir = tempdir().unwrap();
+        let root = tmp_dir.path().join("vcpkg");
+        make_vcpkg_tree(
+            &root,
+            "x64-linux",
+            &[
+                ("zlib", "1.3", &["lib/libz.a"]),
+                ("bzip2", "1.0.8", &["lib/libbz2.a"]),
+            ],
+        );
+        env::set_var(VCPKGRS_TRIPLET, "x64-linux");
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .find_package("zlib")
+            .unwrap();
+        let status_path = root.join("installed").join("vcpkg");
+        let rerun: Vec<_> = lib
+            .cargo_metadata
+            .iter()
+            .filter(|line| line.starts_with("cargo:rerun-if-changed="))
+            .cloned()
+            .collect();
+        assert_eq!(
+            rerun,
+            vec![
+                format!(
+                    "cargo:rerun-if-changed={}",
+                    status_path.join("updates").display()
+                ),
+                format!(
+                    "cargo:rerun-if-changed={}",
+                    status_path
+                        .join("info")
+                        .join("zlib_1.3_x64-linux.list")
+                        .display()
+                ),
+            ]
+        );
+
+        let lib = ::Config::new()
+            .vcpkg_root(root.clone())
+            .emit_rerun_if_changed(false)
+            .find_package("zlib")
+            .unwrap();
+        assert!(!lib
+            .cargo_metadata
+            .iter()
+            .any(|line| line.starts_with("cargo:rerun-if-changed=")));
+        clean_env();
+    }
+
+    #[test]
+    fn find_all_from_metadata() {
+        let manifest = r#"
+[package]
+name = "foo-sys"
+
+[package.metadata.vcpkg]
+git = "https://github.com/microsoft/vcpkg"
+dependencies = [
+    "zlib", # compression
+    "harfbuzz[ucdn]",
+]
+
+[package.metadata.vcpkg.target]
+x86_64-pc-windows-msvc = { triplet = "x64-windows-static", dependencies = ["zlib"] }
+"#;
+        assert_eq!(
+            metadata::vcpkg_dependencies(manifest, "x86_64-apple-darwin").unwrap(),
+            vec!["zlib", "harfbuzz"]
+        );
+        assert_eq!(
+            metadata::vcpkg_dependencies(manifest, "x86_64-pc-windows-msvc").unwrap(),
+            vec!["zlib"]
+        );
+        assert!(metadata::vcpkg_dependencies("[package]\nname = \"foo\"", "")
+            .unwrap()
+            .is_empty());
+        assert!(metadata::vcpkg_dependencies(
+            "[package.metadata.vcpkg]\ndependencies = [\"zlib\"",
+            ""
+        )
+        .is_err());
+
+        let _g = LOCK.lock();
+        clean_env();
+        env::set_var(VCPKG_ROOT, vcpkg_test_tree_loc("normalized"));
+        env::set_var(TARGET, "x86_64-apple-darwin");
+        let tmp_dir = tempdir().unwrap();
+        env::set_var(OUT_DIR, tmp_dir.path());
+        let manifest_path = tmp_dir.path().join("Cargo.toml");
+        fs::write(&manifest_path, manifest).unwrap();
+
+        let libs = ::Config::new().find_all_in(&manifest_path).unwrap();
+        assert_eq!(libs.len(), 2);
+        assert_eq!(libs[0].found_names, vec!["z"]);
+        assert_eq!(libs[1].ports[0], "harfbuzz");
+        clean_env();
+    }
+
+    #[test]
+    fn probe_trait() {
+        fn zlib_names<P: Probe>(probe: &mut P) -> Vec<String> {
+            probe.find_package("zlib").unwrap().found_names
+        }
+
+        let _g = LOCK.lock();
+        clean_env();
+        env::set_var(VCPKG_ROOT, vcpkg_test_tree_loc("normalized"));
+        env::set_var(TARGET, "x86_64-apple-darwin");
+        let tmp_dir = tempdir().unwrap();
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        assert_eq!(zlib_names(&mut ::Config::new()), vec!["z"]);
+        let probe: &mut Probe = &mut ::Config::new();
+        assert!(probe.find_package("no-such-port").is_err());
+        clean_env();
+    }
+
+    #[test]
+    fn suppress_default_libs() {
+        let _g = LOCK.lock();
+        clean_env();
+        env::set_var(VCPKG_ROOT, vcpkg_test_tree_loc("normalized"));
+        env::set_var(TARGET, "x86_64-pc-windows-msvc");
+        env::set_var(CARGO_CFG_TARGET_FEATURE, "crt-static");
+        let tmp_dir = tempdir().unwrap();
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let lib = ::Config::new()
+            .suppress_default_libs(&["msvcrt", "msvcrtd"])
+            .find_package("zlib")
+            .unwrap();
+        assert!(lib
+            .cargo_metadata
+            .iter()
+            .any(|x| x == "cargo:rustc-link-arg=/NODEFAULTLIB:msvcrt"));
+        assert!(lib
+            .cargo_metadata
+            .iter()
+            .any(|x| x == "cargo:rustc-link-arg=/NODEFAULTLIB:msvcrtd"));
+        clean_env();
+    }
+
+    #[test]
+    fn custom_target_triplet_in_config() {
         let _g = LOCK.lock();
 
         clean_env();
@@ -871,6 +5001,36 @@ mod tests {
         clean_env();
     }
 
+    #[test]
+    fn verbatim_link_names() {
+        let _g = LOCK.lock();
+
+        clean_env();
+        env::set_var(VCPKG_ROOT, vcpkg_test_tree_loc("normalized"));
+        let tmp_dir = tempdir().unwrap();
+        env::set_var(OUT_DIR, tmp_dir.path());
+
+        let zlib = ::Config::new()
+            .target_triplet("x64-osx")
+            .verbatim_link_names(true)
+            .find_package("zlib")
+            .unwrap();
+        assert!(zlib
+            .cargo_metadata
+            .iter()
+            .any(|x| x == "cargo:rustc-link-lib=static:+verbatim=libz.a"));
+        assert_eq!(zlib.found_names, vec!["libz.a"]);
+
+        // no effect for windows triplets
+        let zlib = ::Config::new()
+            .target_triplet("x64-windows-static")
+            .verbatim_link_names(true)
+            .find_package("zlib")
+            .unwrap();
+        assert_eq!(zlib.found_names, vec!["zlib"]);
+        clean_env();
+    }
+
     #[test]
     fn custom_target_triplet_by_env_no_default() {
         let _g = LOCK.lock();
@@ -991,6 +5151,10 @@ mod tests {
                 )
                 .unwrap(),
             );
+            let lib_index = pc_files.lib_index();
+            assert_eq!(lib_index.len(), 3);
+            assert_eq!(lib_index["libbrotlienc-static.a"].id, "libbrotlienc");
+
             // Note that the input is alphabetically sorted.
             let input_libs = vec![
                 "libbrotlicommon-static.a".to_owned(),
@@ -1095,54 +5259,185 @@ mod tests {
             }
         }
 
-        // Test parsing of a couple different Requires: lines.
+        // Requires.private affects the link order of static libraries.
         {
-            let pc_file = PcFile::from_str(
-                "test",
-                "Libs: -ltest\n\
-                 Requires: cairo libpng",
-                &target_triplet,
-            )
-            .unwrap();
-            assert_eq!(pc_file.deps, vec!["cairo", "libpng"]);
-            let pc_file = PcFile::from_str(
-                "test",
-                "Libs: -ltest\n\
-                 Requires: cairo xcb >= 1.6 xcb-render >= 1.6",
-                &target_triplet,
-            )
-            .unwrap();
-            assert_eq!(pc_file.deps, vec!["cairo", "xcb", "xcb-render"]);
-            let pc_file = PcFile::from_str(
-                "test",
-                "Libs: -ltest\n\
-                 Requires: glib-2.0, gobject-2.0",
-                &target_triplet,
-            )
-            .unwrap();
-            assert_eq!(pc_file.deps, vec!["glib-2.0", "gobject-2.0"]);
-            let pc_file = PcFile::from_str(
-                "test",
-                "Libs: -ltest\n\
-                 Requires: glib-2.0 >=  2.58.0, gobject-2.0 >=  2.58.0",
-                &target_triplet,
-            )
-            .unwrap();
-            assert_eq!(pc_file.deps, vec!["glib-2.0", "gobject-2.0"]);
+            let contents = "Libs: -lcurl\n\
+                            Requires: libssl\n\
+                            Requires.private: zlib >= 1.2, libssl, libnghttp2";
+            let mut pc_files = PcFiles {
+                files: HashMap::new(),
+            };
+            pc_files.files.insert(
+                "libcurl".to_owned(),
+                PcFile::from_str("libcurl", contents, &target_triplet).unwrap(),
+            );
+            pc_files.files.insert(
+                "zlib".to_owned(),
+                PcFile::from_str("zlib", "Libs: -lz", &target_triplet).unwrap(),
+            );
+            let output_libs =
+                pc_files.fix_ordering(vec!["libz.a".to_owned(), "libcurl.a".to_owned()]);
+            assert_eq!(output_libs, vec!["libcurl.a", "libz.a"]);
         }
 
         clean_env();
     }
 
+    #[test]
+    fn pc_file_relocated_prefix() {
+        let target_triplet: VcpkgTriplet = "x64-linux".into();
+        let contents = "prefix=/home/builder/vcpkg/packages/zlib_x64-linux\n\
+                        libdir=${prefix}/lib\n\
+                        \n\
+                        Name: zlib\n\
+                        Libs: -L${libdir} -lz";
+
+        // without a location the prefix is taken at face value
+        let pc_file = PcFile::from_str("zlib", contents, &target_triplet).unwrap();
+        assert_eq!(
+            pc_file.lib_dirs,
+            vec![PathBuf::from("/home/builder/vcpkg/packages/zlib_x64-linux/lib")]
+        );
+        assert_eq!(pc_file.libs, vec!["libz.a"]);
+
+        let pc_file_dir = Path::new("/cache/vcpkg/installed/x64-linux/lib/pkgconfig");
+        let pc_file =
+            PcFile::from_str_in_dir("zlib", contents, &target_triplet, Some(pc_file_dir)).unwrap();
+        assert_eq!(
+            pc_file.variables["prefix"],
+            Path::new("/cache/vcpkg/installed/x64-linux").to_string_lossy()
+        );
+        assert_eq!(
+            pc_file.lib_dirs,
+            vec![Path::new("/cache/vcpkg/installed/x64-linux").join("lib")]
+        );
+
+        // a prefix relative to the file ends up in the same place
+        let contents = "prefix=${pcfiledir}/../..\nLibs: -L${prefix}/lib -lz";
+        let pc_file =
+            PcFile::from_str_in_dir("zlib", contents, &target_triplet, Some(pc_file_dir)).unwrap();
+        assert_eq!(
+            pc_file.lib_dirs,
+            vec![Path::new("/cache/vcpkg/installed/x64-linux").join("lib")]
+        );
+    }
+
     fn clean_env() {
         env::remove_var(TARGET);
         env::remove_var(VCPKG_ROOT);
         env::remove_var(VCPKGRS_DYNAMIC);
+        env::remove_var(VCPKGRS_STATIC);
         env::remove_var(RUSTFLAGS);
         env::remove_var(CARGO_CFG_TARGET_FEATURE);
         env::remove_var(VCPKGRS_DISABLE);
         env::remove_var(format!("{}_LIBMYSQL", prefix::VCPKGRS_NO_));
         env::remove_var(VCPKGRS_TRIPLET);
+        env::remove_var("DEP_Z_INCLUDE");
+        env::remove_var(PROFILE);
+        env::remove_var(VCPKG_INSTALLED_DIR);
+        env::remove_var(VCPKG_OVERLAY_TRIPLETS);
+        env::remove_var(VCPKGRS_ALLOW_CRT_MISMATCH);
+        env::remove_var(VCPKGRS_MUSL_FALLBACK);
+        env::remove_var(VCPKGRS_TARGET_MAP);
+        env::remove_var(VCPKGRS_TRIPLET_FALLBACKS);
+        env::remove_var(VCPKGRS_LIB);
+        env::remove_var(HOST);
+        env::remove_var(VCPKG_DEFAULT_HOST_TRIPLET);
+    }
+
+    // create a vcpkg tree in `root` with the given (port, version, files) installed
+    fn make_vcpkg_tree(root: &Path, triplet: &str, ports: &[(&str, &str, &[&str])]) {
+        let status_path = root.join("installed").join("vcpkg");
+        fs::create_dir_all(status_path.join("updates")).unwrap();
+        fs::create_dir_all(status_path.join("info")).unwrap();
+        fs::write(root.join(".vcpkg-root"), "").unwrap();
+        let mut status = String::new();
+        for &(port, version, files) in ports {
+            status.push_str(&format!(
+                "Package: {}\nVersion: {}\nArchitecture: {}\nStatus: install ok installed\n\n",
+                port, version, triplet
+            ));
+            let mut manifest = String::new();
+            for file in files {
+                let path = format!("{}/{}", triplet, file);
+                let full_path = root.join("installed").join(&path);
+                fs::create_dir_all(full_path.parent().unwrap()).unwrap();
+                fs::write(full_path, "").unwrap();
+                manifest.push_str(&path);
+                manifest.push('\n');
+            }
+            fs::write(
+                status_path
+                    .join("info")
+                    .join(format!("{}_{}_{}.list", port, version, triplet)),
+                manifest,
+            )
+            .unwrap();
+        }
+        fs::write(status_path.join("updates").join("0000000001"), status).unwrap();
+    }
+
+    // set `fields` such as `Depends: bar` in the paragraph of `port` in the status
+    // database of a tree made with `make_vcpkg_tree`, replacing any field of the
+    // same name
+    fn set_status_fields(root: &Path, port: &str, fields: &[&str]) {
+        let updates = root.join("installed/vcpkg/updates/0000000001");
+        let status = fs::read_to_string(&updates).unwrap();
+        let mut paragraphs: Vec<String> = status.split("\n\n").map(String::from).collect();
+        let header = format!("Package: {}\n", port);
+        let paragraph = paragraphs
+            .iter_mut()
+            .find(|paragraph| paragraph.starts_with(&header) && !paragraph.contains("\nFeature:"))
+            .unwrap();
+        for field in fields {
+            let name = &field[..field.find(':').unwrap() + 1];
+            let mut lines: Vec<&str> = paragraph
+                .lines()
+                .filter(|line| !line.starts_with(name))
+                .collect();
+            lines.push(field);
+            let edited = lines.join("\n");
+            *paragraph = edited;
+        }
+        fs::write(&updates, paragraphs.join("\n\n")).unwrap();
+    }
+
+    // add a paragraph, such as one for a feature, to the status database of a
+    // tree made with `make_vcpkg_tree`
+    fn add_status_paragraph(root: &Path, paragraph: &str) {
+        let updates = root.join("installed/vcpkg/updates/0000000001");
+        let mut status = fs::read_to_string(&updates).unwrap();
+        status.push_str(paragraph);
+        status.push_str("\n\n");
+        fs::write(&updates, status).unwrap();
+    }
+
+    // a tree with the port foo and its features icu and png, which depend on the
+    // ports icu and libpng
+    fn make_feature_tree(root: &Path, default_features: Option<&str>) {
+        make_vcpkg_tree(
+            root,
+            "x64-linux",
+            &[
+                ("foo", "1.0", &["lib/libfoo.a"]),
+                ("icu", "74.1", &["lib/libicuuc.a"]),
+                ("libpng", "1.6.43", &["lib/libpng16.a"]),
+            ],
+        );
+        if let Some(default_features) = default_features {
+            let field = format!("Default-Features: {}", default_features);
+            set_status_fields(root, "foo", &[&field]);
+        }
+        for &(feature, dep) in &[("icu", "icu"), ("png", "libpng")] {
+            add_status_paragraph(
+                root,
+                &format!(
+                    "Package: foo\nFeature: {}\nDepends: {}\nArchitecture: x64-linux\n\
+                     Status: install ok installed",
+                    feature, dep
+                ),
+            );
+        }
     }
 
     // path to a to vcpkg installation to test against
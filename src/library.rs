@@ -1,7 +1,14 @@
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
+#[cfg(feature = "cc")]
+use crate::Linkage;
+use crate::{Crt, MetadataDirective, TripletSettings};
+
 /// Details of a package that was found
-#[derive(Debug)]
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[non_exhaustive]
 pub struct Library {
     /// Paths for the linker to search for static or import libraries
     pub link_paths: Vec<PathBuf>,
@@ -12,7 +19,7 @@ pub struct Library {
     /// Paths to include files
     pub include_paths: Vec<PathBuf>,
 
-    /// cargo: metadata lines
+    /// cargo: metadata lines, see `directives` for them in parsed form
     pub cargo_metadata: Vec<String>,
 
     /// libraries found are static
@@ -32,6 +39,47 @@ pub struct Library {
 
     /// the vcpkg triplet that has been selected
     pub vcpkg_triplet: String,
+
+    /// the settings of `vcpkg_triplet`, read from its triplet file, which may be an
+    /// overlay triplet, when there is one
+    pub triplet_settings: Option<TripletSettings>,
+
+    /// C runtimes requested by `/DEFAULTLIB` directives in the found MSVC libraries,
+    /// if `Config::inspect_crt` is set
+    pub crt_demands: BTreeMap<PathBuf, Vec<Crt>>,
+
+    /// the archive that the static libraries were merged into, see `Config::bundle`
+    pub bundled_lib: Option<PathBuf>,
+
+    /// preprocessor defines to compile code using the libraries with, from `Config::define`
+    /// and the Cflags of the ports' pkg-config files
+    pub defines: Vec<(String, Option<String>)>,
+
+    /// system libraries named in the pkg-config files of the ports, see
+    /// `Config::emit_system_libs`, and in their usage files, see `Config::emit_usage_libs`
+    pub system_libs: Vec<String>,
+
+    /// executables installed by the ports that were asked for, such as `protoc`
+    pub tools: Vec<PathBuf>,
+
+    /// installed versions of the ports, by port name
+    pub port_versions: BTreeMap<String, String>,
+
+    /// include directories of the packages the ports were built into, in
+    /// `packages/<port>_<triplet>`, by port name, see `Config::emit_port_includes`
+    pub port_include_paths: BTreeMap<String, PathBuf>,
+
+    /// static or import libraries installed by each of the `ports`, by port name
+    pub port_libs: BTreeMap<String, Vec<PathBuf>>,
+
+    /// the debug builds of the libraries and DLLs were found, and the DLLs that were
+    /// copied are the debug ones, see `Config::use_debug_profile`
+    pub is_debug: bool,
+
+    /// the dylibs found are loaded through an `@rpath/` install name, as vcpkg builds
+    /// them on macOS, so the binaries linked to them need an rpath to the directory
+    /// they are in to start, see `Config::rpath`
+    pub needs_rpath: bool,
 }
 
 impl Library {
@@ -48,6 +96,65 @@ impl Library {
             found_names: Vec::new(),
             ports: Vec::new(),
             vcpkg_triplet: vcpkg_triplet.to_string(),
+            triplet_settings: None,
+            crt_demands: BTreeMap::new(),
+            bundled_lib: None,
+            defines: Vec::new(),
+            system_libs: Vec::new(),
+            tools: Vec::new(),
+            port_versions: BTreeMap::new(),
+            port_include_paths: BTreeMap::new(),
+            port_libs: BTreeMap::new(),
+            is_debug: false,
+            needs_rpath: false,
+        }
+    }
+
+    /// The `cargo_metadata` lines parsed into `MetadataDirective` values.
+    pub fn directives(&self) -> Vec<MetadataDirective> {
+        self.cargo_metadata
+            .iter()
+            .map(|line| MetadataDirective::parse(line))
+            .collect()
+    }
+
+    /// The `directives` rendered as lines of cargo metadata.
+    pub fn to_cargo_lines(&self) -> Vec<String> {
+        self.directives()
+            .iter()
+            .map(|directive| directive.to_cargo_line())
+            .collect()
+    }
+
+    /// The installed version of one of the `ports`, as recorded by vcpkg.
+    pub fn version(&self, port: &str) -> Option<&str> {
+        self.port_versions.get(port).map(|version| version.as_str())
+    }
+
+    /// Configure `build` to compile C or C++ code that uses the libraries: add
+    /// the `include_paths` and `defines`, and for MSVC triplets link the C
+    /// runtime the same way the triplet does.
+    ///
+    /// ```rust,no_run
+    /// let lib = vcpkg::find_package("zlib").unwrap();
+    /// let mut build = cc::Build::new();
+    /// lib.apply_to(&mut build);
+    /// build.file("src/shim.c").compile("shim");
+    /// ```
+    #[cfg(feature = "cc")]
+    pub fn apply_to(&self, build: &mut ::cc::Build) {
+        for include_path in &self.include_paths {
+            build.include(include_path);
+        }
+        for &(ref name, ref value) in &self.defines {
+            build.define(name, value.as_ref().map(|value| value.as_str()));
+        }
+        let settings = match self.triplet_settings {
+            Some(ref settings) => settings.clone(),
+            None => TripletSettings::resolve(&self.vcpkg_triplet, None),
+        };
+        if settings.static_lib_suffix == "lib" {
+            build.static_crt(settings.crt_linkage == Linkage::Static);
         }
     }
 }
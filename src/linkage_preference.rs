@@ -0,0 +1,44 @@
+/// Which of the default triplets for the rust target, the static or the dynamic
+/// one, packages are found in, see `Config::linkage_preference`.
+///
+/// Setting `VCPKGRS_DYNAMIC` still selects the dynamic triplet whatever the
+/// preference is, as `VCPKGRS_STATIC` or `VCPKGRS_DYNAMIC=0` do the static one,
+/// and a triplet that is configured explicitly, such as with `VCPKGRS_TRIPLET`
+/// or `Config::target_triplet`, is used as it is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkagePreference {
+    /// Only look in the static triplet.
+    StaticOnly,
+    /// Only look in the dynamic triplet.
+    DynamicOnly,
+    /// Look in the static triplet, then in the dynamic one if the packages are
+    /// not installed there.
+    PreferStatic,
+    /// Look in the dynamic triplet, then in the static one if the packages are
+    /// not installed there.
+    PreferDynamic,
+}
+
+impl LinkagePreference {
+    /// Whether the dynamic triplet is the one looked in first.
+    pub(crate) fn prefers_dynamic(self) -> bool {
+        match self {
+            LinkagePreference::DynamicOnly | LinkagePreference::PreferDynamic => true,
+            LinkagePreference::StaticOnly | LinkagePreference::PreferStatic => false,
+        }
+    }
+
+    /// Whether the triplet of the other linkage is looked in as well.
+    pub(crate) fn falls_back(self) -> bool {
+        match self {
+            LinkagePreference::PreferStatic | LinkagePreference::PreferDynamic => true,
+            LinkagePreference::StaticOnly | LinkagePreference::DynamicOnly => false,
+        }
+    }
+
+    /// Whether the packages may be found in the dynamic triplet, which counts as
+    /// opting in to dynamic linking, as `VCPKGRS_DYNAMIC` does.
+    pub(crate) fn allows_dynamic(self) -> bool {
+        self != LinkagePreference::StaticOnly
+    }
+}
@@ -0,0 +1,196 @@
+/// Read the vcpkg dependencies of a crate from the `[package.metadata.vcpkg]`
+/// table of its `Cargo.toml`, in the format used by
+/// [cargo-vcpkg](https://crates.io/crates/cargo-vcpkg):
+///
+/// ```toml
+/// [package.metadata.vcpkg]
+/// dependencies = ["sdl2", "zlib"]
+///
+/// [package.metadata.vcpkg.target]
+/// x86_64-pc-windows-msvc = { dependencies = ["sdl2", "zlib", "openssl"] }
+///
+/// [package.metadata.vcpkg.target.x86_64-unknown-linux-gnu]
+/// dependencies = ["sdl2"]
+/// ```
+///
+/// The dependencies given for `target`, in either form, replace the default list. Features such
+/// as `sdl2[vulkan]` are dropped, leaving the port name.
+///
+/// This understands only as much TOML as is needed for these tables.
+pub(crate) fn vcpkg_dependencies(manifest: &str, target: &str) -> Result<Vec<String>, String> {
+    let mut section = String::new();
+    let mut default_deps = None;
+    let mut target_deps = None;
+    let target_section = format!("package.metadata.vcpkg.target.{}", target);
+
+    let mut lines = manifest.lines().enumerate();
+    while let Some((number, line)) = lines.next() {
+        let line = strip_comment(line).trim();
+        if line.starts_with('[') {
+            section = line
+                .trim_matches(|c| c == '[' || c == ']')
+                .split('.')
+                .map(|part| part.trim().trim_matches('"'))
+                .collect::<Vec<_>>()
+                .join(".");
+            continue;
+        }
+        let (key, value) = match line.find('=') {
+            Some(pos) => (line[..pos].trim(), line[pos + 1..].trim()),
+            None => continue,
+        };
+
+        // arrays may span several lines
+        let mut value = value.to_owned();
+        if value.starts_with('[') || value.starts_with('{') {
+            let close = if value.starts_with('[') { ']' } else { '}' };
+            while !value.ends_with(close) {
+                match lines.next() {
+                    Some((_, line)) => value.push_str(strip_comment(line).trim()),
+                    None => return Err(format!("line {}: unterminated value", number + 1)),
+                }
+            }
+        }
+
+        let key = key.trim_matches('"');
+        if section == "package.metadata.vcpkg" && key == "dependencies" {
+            default_deps = Some(parse_string_array(&value, number)?);
+        } else if section == "package.metadata.vcpkg.target" && key == target {
+            for (key, value) in parse_inline_table(&value, number)? {
+                if key == "dependencies" {
+                    target_deps = Some(parse_string_array(value, number)?);
+                }
+            }
+        } else if section == target_section && key == "dependencies" {
+            target_deps = Some(parse_string_array(&value, number)?);
+        }
+    }
+
+    Ok(target_deps.or(default_deps).unwrap_or(Vec::new()))
+}
+
+fn strip_comment(line: &str) -> &str {
+    // '#' may also appear inside strings, but not in the values read here
+    match line.find('#') {
+        Some(pos) => &line[..pos],
+        None => line,
+    }
+}
+
+/// Split an inline table such as `{ triplet = "x64-windows", dependencies = ["zlib"] }`
+/// into its keys and values. Commas inside arrays and strings don't end a value.
+fn parse_inline_table(value: &str, number: usize) -> Result<Vec<(&str, &str)>, String> {
+    let value = value.trim();
+    if !value.starts_with('{') || !value.ends_with('}') {
+        return Err(format!("line {}: expected an inline table", number + 1));
+    }
+    let body = &value[1..value.len() - 1];
+    let mut entries = Vec::new();
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut start = 0;
+    for (pos, c) in body.char_indices().chain(Some((body.len(), ','))) {
+        match c {
+            '"' => in_string = !in_string,
+            '[' if !in_string => depth += 1,
+            ']' if !in_string => depth -= 1,
+            ',' if !in_string && depth == 0 => {
+                let entry = body[start..pos].trim();
+                start = pos + 1;
+                if entry.is_empty() {
+                    continue;
+                }
+                let eq = entry.find('=').ok_or_else(|| {
+                    format!("line {}: expected key = value, found {}", number + 1, entry)
+                })?;
+                let key = entry[..eq].trim().trim_matches('"');
+                entries.push((key, entry[eq + 1..].trim()));
+            }
+            _ => {}
+        }
+    }
+    Ok(entries)
+}
+
+fn parse_string_array(value: &str, number: usize) -> Result<Vec<String>, String> {
+    let value = value.trim();
+    if !value.starts_with('[') || !value.ends_with(']') {
+        return Err(format!("line {}: expected an array of strings", number + 1));
+    }
+    let mut items = Vec::new();
+    for item in value[1..value.len() - 1].split(',') {
+        let item = item.trim();
+        if item.is_empty() {
+            continue;
+        }
+        if item.len() < 2 || !item.starts_with('"') || !item.ends_with('"') {
+            return Err(format!(
+                "line {}: expected a string, found {}",
+                number + 1,
+                item
+            ));
+        }
+        let port = item[1..item.len() - 1].split('[').next().unwrap();
+        items.push(port.trim().to_owned());
+    }
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_inline_table() {
+        let manifest = r#"
+[package.metadata.vcpkg]
+dependencies = ["sdl2"]
+
+[package.metadata.vcpkg.target]
+x86_64-pc-windows-msvc = { dev-dependencies = ["gtest"], dependencies = ["zlib", "sdl2[vulkan]"] }
+x86_64-apple-darwin = { dev-dependencies = ["gtest"] }
+"#;
+        for &(target, deps) in &[
+            ("x86_64-pc-windows-msvc", &["zlib", "sdl2"][..]),
+            ("x86_64-apple-darwin", &["sdl2"]),
+            ("x86_64-unknown-linux-gnu", &["sdl2"]),
+        ] {
+            assert_eq!(
+                vcpkg_dependencies(manifest, target).unwrap(),
+                deps,
+                "{}",
+                target
+            );
+        }
+    }
+
+    #[test]
+    fn target_subtable() {
+        let manifest = r#"
+[package.metadata.vcpkg]
+dependencies = ["sdl2"]
+
+[package.metadata.vcpkg.target.x86_64-unknown-linux-gnu]
+dev-dependencies = ["gtest"]
+dependencies = ["zlib"]
+
+[package.metadata.vcpkg.target."x86_64-pc-windows-msvc"]
+triplet = "x64-windows-static"
+dependencies = [
+    "openssl",
+]
+"#;
+        for &(target, deps) in &[
+            ("x86_64-unknown-linux-gnu", &["zlib"][..]),
+            ("x86_64-pc-windows-msvc", &["openssl"]),
+            ("x86_64-apple-darwin", &["sdl2"]),
+        ] {
+            assert_eq!(
+                vcpkg_dependencies(manifest, target).unwrap(),
+                deps,
+                "{}",
+                target
+            );
+        }
+    }
+}
@@ -1,6 +1,7 @@
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::Read;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use crate::{Error, VcpkgTriplet, VcpkgTarget};
 
@@ -15,8 +16,22 @@ pub(crate) struct PcFile {
     pub(crate) id: String,
     /// List of libraries found as '-l', translated to a given vcpkg_target. e.g. libbrotlicommon.a
     pub(crate) libs: Vec<String>,
-    /// List of pkgconfig dependencies, e.g. PcFile::id.
+    /// The names given to '-l' in Libs, e.g. `brotlicommon-static`, in the same order as `libs`.
+    pub(crate) lib_names: Vec<String>,
+    /// List of pkgconfig dependencies, e.g. PcFile::id. For static triplets this
+    /// includes those from Requires.private, which also affect the link order.
     pub(crate) deps: Vec<String>,
+    /// Variables defined in the file, e.g. `prefix`, with other variables already expanded.
+    pub(crate) variables: HashMap<String, String>,
+    /// Library search paths found as '-L'.
+    pub(crate) lib_dirs: Vec<PathBuf>,
+    /// Preprocessor definitions found as '-D' in Cflags, e.g. `CURL_STATICLIB`.
+    pub(crate) defines: Vec<(String, Option<String>)>,
+    /// Header search paths found as '-I' in Cflags.
+    pub(crate) include_dirs: Vec<PathBuf>,
+    /// Names of the libraries found as '-l' in Libs.private, which are only
+    /// needed for static linking, e.g. `m` or `pthread`.
+    pub(crate) private_libs: Vec<String>,
 }
 
 impl PcFile {
@@ -38,29 +53,95 @@ impl PcFile {
 
         file.read_to_string(&mut pc_file_contents)
             .map_err(|_| Error::VcpkgInstallation(format!("Couldn't read {}", path.display())))?;
-        PcFile::from_str(&id, &pc_file_contents, &vcpkg_target.target_triplet)
+        PcFile::from_str_in_dir(
+            &id,
+            &pc_file_contents,
+            &vcpkg_target.target_triplet,
+            path.parent(),
+        )
     }
 
     pub(crate) fn from_str(
         id: &str,
         s: &str,
         target_triplet: &VcpkgTriplet,
+    ) -> Result<Self, Error> {
+        PcFile::from_str_in_dir(id, s, target_triplet, None)
+    }
+
+    /// Parse the contents of a .pc file that lives in `pc_file_dir`.
+    ///
+    /// vcpkg writes an absolute `prefix=` into some .pc files, which goes stale when the
+    /// tree is restored from a cache to a different path. When the directory of the file
+    /// is known, such a prefix is replaced with the actual installed directory, which is
+    /// two levels above the `lib/pkgconfig` directory.
+    pub(crate) fn from_str_in_dir(
+        id: &str,
+        s: &str,
+        target_triplet: &VcpkgTriplet,
+        pc_file_dir: Option<&Path>,
     ) -> Result<Self, Error> {
         let mut libs = Vec::new();
+        let mut lib_names = Vec::new();
         let mut deps = Vec::new();
+        let mut lib_dirs = Vec::new();
+        let mut defines = Vec::new();
+        let mut include_dirs = Vec::new();
+        let mut private_libs = Vec::new();
+        let mut variables = HashMap::new();
+
+        let actual_prefix = pc_file_dir
+            .and_then(|dir| dir.parent())
+            .and_then(|dir| dir.parent());
+        if let Some(dir) = pc_file_dir {
+            variables.insert("pcfiledir".to_owned(), dir.to_string_lossy().into_owned());
+        }
+
+        let mut properties = Vec::new();
+        for line in s.lines() {
+            let line = line.trim();
+            if line.starts_with('#') {
+                continue;
+            }
+            // A line is a variable definition if '=' comes before any ':'.
+            let colon = line.find(':');
+            match line.find('=') {
+                Some(eq) if colon.map_or(true, |colon| eq < colon) => {
+                    let name = line[..eq].trim();
+                    let mut value = expand_variables(line[eq + 1..].trim(), &variables);
+                    if name == "prefix" {
+                        if let Some(actual_prefix) = actual_prefix {
+                            let declared = Path::new(&value);
+                            if declared.is_absolute() && declared != actual_prefix {
+                                value = actual_prefix.to_string_lossy().into_owned();
+                            }
+                        }
+                    }
+                    variables.insert(name.to_owned(), value);
+                }
+                _ => {
+                    if let Some(colon) = colon {
+                        let value = expand_variables(&line[colon + 1..], &variables);
+                        properties.push((&line[..colon], value));
+                    }
+                }
+            }
+        }
 
-        let preparsed_lines_iter = s
-            .lines()
-            .filter_map(|line| line.split_once(|c| c == ':'))
+        let preparsed_lines_iter = properties
+            .iter()
             // we defer the evaluation of split_whitespace() until we actually need it
-            .map(|(prop_kw, remainder)| (prop_kw, move || remainder.split_whitespace()));
+            .map(|&(prop_kw, ref remainder)| (prop_kw, move || remainder.split_whitespace()));
 
         // Read abour property keywords of .pc files here:
         // https://manpages.ubuntu.com/manpages/focal/man5/pc.5.html#:~:text=has%20been%20done.-,PROPERTY%20KEYWORDS,-Name%20%20%20%20The%20displayed
         for (prop_kw, split_remainder) in preparsed_lines_iter {
-            // We could collect a lot of stuff here, but we only care about Requires and Libs for the moment.
+            // We could collect a lot of stuff here, but we only care about Requires, Libs and Cflags for the moment.
             match prop_kw {
-                "Requires" => {
+                "Requires" | "Requires.private" => {
+                    if prop_kw == "Requires.private" && !target_triplet.is_static {
+                        continue;
+                    }
                     let mut requires_args = split_remainder()
                         .flat_map(|e| e.split(","))
                         .filter(|s| !s.is_empty());
@@ -71,12 +152,16 @@ impl PcFile {
                             requires_args.next();
                             continue;
                         }
-                        deps.push(dep.to_owned());
+                        if !deps.iter().any(|d| d == dep) {
+                            deps.push(dep.to_owned());
+                        }
                     }
                 }
                 "Libs" => {
                     for lib_flag in split_remainder() {
-                        if lib_flag.starts_with("-l") {
+                        if lib_flag.starts_with("-L") {
+                            lib_dirs.push(PathBuf::from(lib_flag.trim_left_matches("-L")));
+                        } else if lib_flag.starts_with("-l") {
                             // reconstruct the library name.
                             let lib = format!(
                                 "{}{}.{}",
@@ -89,9 +174,40 @@ impl PcFile {
                                 target_triplet.lib_suffix
                             );
                             libs.push(lib);
+                            lib_names.push(lib_flag.trim_left_matches("-l").to_owned());
                         }
                     }
                 }
+                "Libs.private" => {
+                    for lib_flag in split_remainder() {
+                        if lib_flag.starts_with("-l") && lib_flag.len() > 2 {
+                            private_libs.push(lib_flag[2..].to_owned());
+                        }
+                    }
+                }
+                "Cflags" => {
+                    let mut cflags = split_remainder();
+                    while let Some(cflag) = cflags.next() {
+                        if !cflag.starts_with("-D") && !cflag.starts_with("-I") {
+                            continue;
+                        }
+                        // the argument may also be given separately
+                        let arg = match &cflag[2..] {
+                            "" => match cflags.next() {
+                                Some(arg) => arg,
+                                None => break,
+                            },
+                            arg => arg,
+                        };
+                        if cflag.starts_with("-I") {
+                            include_dirs.push(PathBuf::from(arg));
+                            continue;
+                        }
+                        let mut parts = arg.splitn(2, '=');
+                        let name = parts.next().unwrap().to_owned();
+                        defines.push((name, parts.next().map(|value| value.to_owned())));
+                    }
+                }
                 _ => continue,
             }
         }
@@ -99,7 +215,83 @@ impl PcFile {
         Ok(PcFile {
             id: id.to_string(),
             libs,
+            lib_names,
             deps,
+            variables,
+            lib_dirs,
+            defines,
+            include_dirs,
+            private_libs,
         })
     }
 }
+
+/// Expand `${name}` references to previously defined variables. Undefined
+/// variables expand to nothing, as they do in pkg-config, and `$$` is a literal `$`.
+fn expand_variables(value: &str, variables: &HashMap<String, String>) -> String {
+    let mut expanded = String::new();
+    let mut rest = value;
+    while let Some(pos) = rest.find('$') {
+        expanded.push_str(&rest[..pos]);
+        rest = &rest[pos..];
+        if rest.starts_with("$$") {
+            expanded.push('$');
+            rest = &rest[2..];
+        } else if let (true, Some(end)) = (rest.starts_with("${"), rest.find('}')) {
+            if let Some(var) = variables.get(&rest[2..end]) {
+                expanded.push_str(var);
+            }
+            rest = &rest[end + 1..];
+        } else {
+            expanded.push('$');
+            rest = &rest[1..];
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requires() {
+        for &(triplet, requires, deps) in &[
+            ("x64-linux", "Requires: cairo libpng", &["cairo", "libpng"][..]),
+            (
+                "x64-linux",
+                "Requires: cairo xcb >= 1.6 xcb-render >= 1.6",
+                &["cairo", "xcb", "xcb-render"],
+            ),
+            ("x64-linux", "Requires: glib-2.0, gobject-2.0", &["glib-2.0", "gobject-2.0"]),
+            (
+                "x64-linux",
+                "Requires: glib-2.0 >=  2.58.0, gobject-2.0 >=  2.58.0",
+                &["glib-2.0", "gobject-2.0"],
+            ),
+            ("x64-linux", "Requires: libB <=1.0 , libmysql-client = 0.9, ", &["libB"]),
+            ("x64-linux", "Requires:", &[]),
+            (
+                "x64-linux",
+                "Requires: libssl\nRequires.private: zlib >= 1.2, libssl, libnghttp2",
+                &["libssl", "zlib", "libnghttp2"],
+            ),
+            (
+                "x64-windows-static",
+                "Requires.private: zlib libssl",
+                &["zlib", "libssl"],
+            ),
+            (
+                "x64-linux-dynamic",
+                "Requires: libssl\nRequires.private: zlib >= 1.2, libssl, libnghttp2",
+                &["libssl"],
+            ),
+            ("x64-windows", "Requires.private: zlib", &[]),
+        ] {
+            let contents = format!("Libs: -ltest\n{}", requires);
+            let pc_file = PcFile::from_str("test", &contents, &triplet.into()).unwrap();
+            assert_eq!(pc_file.deps, deps, "{}: {}", triplet, requires);
+        }
+    }
+}
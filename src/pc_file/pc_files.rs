@@ -41,8 +41,95 @@ impl PcFiles {
         Ok(PcFiles { files })
     }
 
+    /// Load the given .pc files, such as those listed in the manifest of a port.
+    pub(crate) fn load_files(vcpkg_target: &VcpkgTarget, paths: &[PathBuf]) -> Result<Self, Error> {
+        let mut files = HashMap::new();
+        for path in paths {
+            let pc_file = PcFile::parse(vcpkg_target, path)?;
+            files.insert(pc_file.id.to_owned(), pc_file);
+        }
+        Ok(PcFiles { files })
+    }
+
+    /// The preprocessor definitions from the Cflags of all the files, without duplicates.
+    pub(crate) fn defines(&self) -> Vec<(String, Option<String>)> {
+        let mut ids: Vec<&String> = self.files.keys().collect();
+        ids.sort();
+        let mut defines: Vec<(String, Option<String>)> = Vec::new();
+        for id in ids {
+            for define in &self.files[id].defines {
+                if !defines.contains(define) {
+                    defines.push(define.clone());
+                }
+            }
+        }
+        defines
+    }
+
+    /// The libraries from the Libs of all the files that are neither among
+    /// `port_libs` nor installed in the triplet's lib directory, such as `pthread`
+    /// or `ws2_32`, without duplicates.
+    pub(crate) fn system_libs(
+        &self,
+        vcpkg_target: &VcpkgTarget,
+        port_libs: &[String],
+    ) -> Vec<String> {
+        let mut ids: Vec<&String> = self.files.keys().collect();
+        ids.sort();
+        let mut system_libs: Vec<String> = Vec::new();
+        for id in ids {
+            let pc_file = &self.files[id];
+            for (name, lib) in pc_file.lib_names.iter().zip(&pc_file.libs) {
+                if port_libs.contains(lib)
+                    || vcpkg_target.lib_path.join(lib).exists()
+                    || system_libs.contains(name)
+                {
+                    continue;
+                }
+                system_libs.push(name.clone());
+            }
+        }
+        system_libs
+    }
+
+    /// The libraries from the Libs.private of all the files, without duplicates.
+    pub(crate) fn private_libs(&self) -> Vec<String> {
+        let mut ids: Vec<&String> = self.files.keys().collect();
+        ids.sort();
+        let mut private_libs: Vec<String> = Vec::new();
+        for id in ids {
+            for private_lib in &self.files[id].private_libs {
+                if !private_libs.contains(private_lib) {
+                    private_libs.push(private_lib.clone());
+                }
+            }
+        }
+        private_libs
+    }
+
+    /// The header search paths from the Cflags of all the files, without duplicates.
+    pub(crate) fn include_dirs(&self) -> Vec<PathBuf> {
+        let mut ids: Vec<&String> = self.files.keys().collect();
+        ids.sort();
+        let mut include_dirs: Vec<PathBuf> = Vec::new();
+        for id in ids {
+            for include_dir in &self.files[id].include_dirs {
+                if !include_dirs.contains(include_dir) {
+                    include_dirs.push(include_dir.clone());
+                }
+            }
+        }
+        include_dirs
+    }
+
     /// Use the .pc files as a hint to the library sort order.
-    pub(crate) fn fix_ordering(&self, mut libs: Vec<String>) -> Vec<String> {
+    pub(crate) fn fix_ordering(&self, libs: Vec<String>) -> Vec<String> {
+        self.fix_ordering_or_give_up(libs).0
+    }
+
+    /// Like `fix_ordering`, but also tells whether the ordering was given up on
+    /// because the libraries were still moving after a few passes.
+    pub(crate) fn fix_ordering_or_give_up(&self, mut libs: Vec<String>) -> (Vec<String>, bool) {
         // Overall heuristic: for each library given as input, identify which PcFile declared it.
         // Then, looking at that PcFile, check its Requires: (deps), and if the pc file for that
         // dep is in our set, check if its libraries are in our set of libs.  If so, move it to the
@@ -51,11 +138,12 @@ impl PcFiles {
         // We may need to do this a few times to properly handle the case where A -> (depends on) B
         // -> C -> D and libraries were originally sorted D, C, B, A.  Avoid recursion so we don't
         // have to detect potential cycles.
+        let lib_index = self.lib_index();
         for _iter in 0..3 {
             let mut required_lib_order: Vec<String> = Vec::new();
             for lib in &libs {
                 required_lib_order.push(lib.to_owned());
-                if let Some(pc_file) = self.locate_pc_file_by_lib(lib) {
+                if let Some(pc_file) = lib_index.get(lib.as_str()) {
                     // Consider its requirements:
                     for dep in &pc_file.deps {
                         // Only consider pkgconfig dependencies we know about.
@@ -78,18 +166,105 @@ impl PcFiles {
             // Termination:
             if required_lib_order == libs {
                 // Nothing changed, we're done here.
-                return libs;
+                return (libs, false);
             }
             libs = required_lib_order;
         }
-        println!("cargo:warning=vcpkg gave up trying to resolve pkg-config ordering.");
-        libs
+        (libs, true)
+    }
+
+    /// Map each library to the PcFile that contains it, so that ports with many
+    /// libraries and .pc files don't need a scan of every file per library.
+    pub(crate) fn lib_index(&self) -> HashMap<&str, &PcFile> {
+        let mut index = HashMap::new();
+        for pc_file in self.files.values() {
+            for lib in &pc_file.libs {
+                index.entry(lib.as_str()).or_insert(pc_file);
+            }
+        }
+        index
     }
-    /// Locate which PcFile contains this library, if any.
-    pub(crate) fn locate_pc_file_by_lib(&self, lib: &str) -> Option<&PcFile> {
-        self.files
-            .iter()
-            .map(|(_id, pc_file)| pc_file)
-            .find(|pc_file| pc_file.libs.iter().map(String::as_str).any(|s| s == lib))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vcpkg_target::tests::target;
+
+    fn pc_files(triplet: &str, contents: &[&str]) -> PcFiles {
+        let mut files = HashMap::new();
+        for (i, contents) in contents.iter().enumerate() {
+            let id = format!("lib{}", i);
+            let pc_file = PcFile::from_str(&id, contents, &triplet.into()).unwrap();
+            files.insert(id, pc_file);
+        }
+        PcFiles { files }
+    }
+
+    #[test]
+    fn system_libs() {
+        for &(triplet, contents, port_libs, system_libs) in &[
+            (
+                "x64-linux",
+                &["Libs: -lcurl -ldl"][..],
+                &["libcurl.a"][..],
+                &["dl"][..],
+            ),
+            (
+                "x64-linux",
+                &["Libs: -L/lib -lcurl -pthread -lm"],
+                &["libcurl.a"],
+                &["m"],
+            ),
+            (
+                "x64-linux",
+                &["Libs: -lcurl", "Libs: -lz -ldl -lm", "Libs: -ldl"],
+                &[],
+                &["curl", "z", "dl", "m"],
+            ),
+            ("x64-linux", &["Libs.private: -lpthread"], &[], &[]),
+            (
+                "x64-windows",
+                &["Libs: -lcurl -lws2_32"],
+                &["curl.lib"],
+                &["ws2_32"],
+            ),
+            ("x64-windows", &["Libs: -llibcurl"], &["libcurl.lib"], &[]),
+            (
+                "x64-linux-dynamic",
+                &["Libs: -lcurl -lrt"],
+                &["libcurl.so"],
+                &["rt"],
+            ),
+        ] {
+            let pc_files = pc_files(triplet, contents);
+            let port_libs: Vec<String> = port_libs.iter().map(|&l| l.to_owned()).collect();
+            assert_eq!(
+                pc_files.system_libs(&target(triplet), &port_libs),
+                system_libs,
+                "{}: {:?}",
+                triplet,
+                contents
+            );
+        }
+    }
+
+    #[test]
+    fn private_libs() {
+        for &(contents, private_libs) in &[
+            (
+                &["Libs.private: -lssh -lpthread -lm -pthread"][..],
+                &["ssh", "pthread", "m"][..],
+            ),
+            (&["Libs.private: -l -lm"], &["m"]),
+            (&["Libs: -lcurl"], &[]),
+            (
+                &["Libs.private: -lm", "Libs.private: -lm -ldl"],
+                &["m", "dl"],
+            ),
+        ] {
+            let pc_files = pc_files("x64-linux", contents);
+            assert_eq!(pc_files.private_libs(), private_libs, "{:?}", contents);
+        }
     }
 }
@@ -1,11 +1,80 @@
-#[derive(Clone, Debug)]
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// A port installed in a vcpkg tree. `load_ports` reads the version, dependencies
+/// and features from the status database; the files and what is read from the
+/// .pc files among them are left empty until `load_port_manifest`.
+#[derive(Clone, Debug, Default)]
 pub(crate) struct Port {
+    // version recorded in the status database
+    pub(crate) version: String,
+
     // dlls if any
     pub(crate) dlls: Vec<String>,
 
     // libs (static or import)
     pub(crate) libs: Vec<String>,
 
+    // libs in subdirectories of lib such as manual-link by the subdirectory,
+    // which are only linked when asked for
+    pub(crate) subdir_libs: BTreeMap<String, Vec<String>>,
+
+    // executables, relative to the installed triplet directory
+    pub(crate) tools: Vec<String>,
+
+    // whether the port installs any headers
+    pub(crate) has_headers: bool,
+
     // ports that this port depends on
     pub(crate) deps: Vec<String>,
+
+    // ports built for the host triplet that this port depends on, such as tools
+    pub(crate) host_deps: Vec<String>,
+
+    // installed features and the ports they depend on
+    pub(crate) features: BTreeMap<String, Vec<String>>,
+
+    // features that the port was installed with unless they were turned off
+    pub(crate) default_features: Vec<String>,
+
+    // the .list file the libs, dlls and tools were read from
+    pub(crate) manifest: PathBuf,
+
+    // preprocessor definitions from the Cflags of the port's .pc files
+    pub(crate) defines: Vec<(String, Option<String>)>,
+
+    // header directories other than the triplet's include directory from the
+    // Cflags of the port's .pc files
+    pub(crate) include_dirs: Vec<PathBuf>,
+
+    // libraries that are not vcpkg files from the Libs of the port's .pc files
+    pub(crate) system_libs: Vec<String>,
+
+    // libraries from the Libs.private of the port's .pc files, needed when
+    // linking statically
+    pub(crate) private_libs: Vec<String>,
+
+    // how reading the port's files fell short, see `Config::warn_on_fallback`
+    pub(crate) fallbacks: Vec<String>,
+}
+
+impl Port {
+    /// Whether the port only helps to build other ports, like `vcpkg-cmake` or
+    /// `pkgconf`, installing neither libraries nor headers. Only meaningful once
+    /// the manifest has been read.
+    pub(crate) fn is_build_tool(&self) -> bool {
+        self.libs.is_empty() && self.dlls.is_empty() && !self.has_headers
+    }
+
+    /// The ports this port depends on with the given features enabled, or with
+    /// all of its installed features if `features` is `None`.
+    pub(crate) fn deps_with_features(&self, features: Option<&[String]>) -> Vec<String> {
+        let mut deps = self.deps.clone();
+        for (feature, feature_deps) in &self.features {
+            if features.map_or(true, |features| features.contains(feature)) {
+                deps.extend(feature_deps.iter().cloned());
+            }
+        }
+        deps
+    }
 }
@@ -0,0 +1,115 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::{load_port_manifest, load_ports, Error, Port, VcpkgTarget};
+
+/// The files of a status database with their modification times, which change
+/// whenever vcpkg installs or removes a port.
+type Stamp = Vec<(PathBuf, Option<SystemTime>)>;
+
+/// The status database and library directory, which also tell the triplet and
+/// whether the debug libraries are used, and the linkage of the triplet, which
+/// may come from a triplet file and decides which of the files are libraries.
+/// The host triplet decides which dependencies are tools.
+type Key = (PathBuf, PathBuf, String, bool, Option<String>);
+
+fn status_stamp(status_path: &Path) -> Stamp {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    let status = status_path.join("status");
+    let updates = status_path.join("updates");
+    let mut stamp = vec![
+        (status.clone(), modified(&status)),
+        (updates.clone(), modified(&updates)),
+    ];
+    if let Ok(entries) = fs::read_dir(&updates) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let time = modified(&path);
+            stamp.push((path, time));
+        }
+    }
+    stamp.sort();
+    stamp
+}
+
+/// Ports read from the status database of a vcpkg tree, and the ports whose
+/// manifests have been read as well.
+struct Entry {
+    stamp: Stamp,
+    ports: BTreeMap<String, Port>,
+    loaded: BTreeMap<String, Port>,
+}
+
+/// Ports read from the status databases of vcpkg trees, kept so that a build
+/// script that finds several packages reads each database and manifest once.
+#[derive(Default)]
+struct PortCache {
+    entries: BTreeMap<Key, Entry>,
+}
+
+thread_local! {
+    static PORT_CACHE: RefCell<PortCache> = RefCell::new(PortCache::default());
+}
+
+fn key(target: &VcpkgTarget) -> Key {
+    (
+        target.status_path.clone(),
+        target.lib_path.clone(),
+        target.target_triplet.lib_suffix.clone(),
+        target.target_triplet.is_static,
+        target.host_triplet.clone(),
+    )
+}
+
+/// The ports installed for `target` as `load_ports` reads them, read again only
+/// if the status database has changed since the last time.
+pub(crate) fn load_ports_cached(target: &VcpkgTarget) -> Result<BTreeMap<String, Port>, Error> {
+    let key = key(target);
+    let stamp = status_stamp(&target.status_path);
+    let cached = PORT_CACHE.with(|cache| match cache.borrow().entries.get(&key) {
+        Some(entry) if entry.stamp == stamp => Some(entry.ports.clone()),
+        _ => None,
+    });
+    if let Some(ports) = cached {
+        return Ok(ports);
+    }
+    let ports = load_ports(target)?;
+    let entry = Entry {
+        stamp,
+        ports: ports.clone(),
+        loaded: BTreeMap::new(),
+    };
+    PORT_CACHE.with(|cache| cache.borrow_mut().entries.insert(key, entry));
+    Ok(ports)
+}
+
+/// Read the manifest of a port returned by `load_ports_cached` as
+/// `load_port_manifest` does, unless it has been read before.
+pub(crate) fn load_port_manifest_cached(
+    port_name: &str,
+    port: &mut Port,
+    target: &VcpkgTarget,
+) -> Result<(), Error> {
+    let key = key(target);
+    let cached = PORT_CACHE.with(|cache| {
+        cache
+            .borrow()
+            .entries
+            .get(&key)
+            .and_then(|entry| entry.loaded.get(port_name).cloned())
+    });
+    if let Some(loaded) = cached {
+        *port = loaded;
+        return Ok(());
+    }
+    load_port_manifest(port_name, port, target)?;
+    PORT_CACHE.with(|cache| {
+        if let Some(entry) = cache.borrow_mut().entries.get_mut(&key) {
+            entry.loaded.insert(port_name.to_owned(), port.clone());
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use crate::{Config, Error, Library};
+
+/// The core operations of finding packages, implemented by `Config`.
+///
+/// Build logic that is written against `Probe` rather than `Config` can be
+/// handed an alternative, such as a pkg-config based finder or a stub in
+/// unit tests:
+///
+/// ```rust
+/// use vcpkg::{Error, Library, Probe};
+///
+/// fn link_zlib<P: Probe>(probe: &mut P) -> Result<Vec<String>, Error> {
+///     probe.find_package("zlib").map(|lib| lib.found_names)
+/// }
+///
+/// struct Stub;
+///
+/// impl Probe for Stub {
+///     fn find_package(&mut self, _port_name: &str) -> Result<Library, Error> {
+///         let mut lib = Library::default();
+///         lib.found_names.push("z".to_owned());
+///         Ok(lib)
+///     }
+///
+///     fn find_all(&mut self) -> Result<Vec<Library>, Error> {
+///         Ok(Vec::new())
+///     }
+/// }
+///
+/// assert_eq!(link_zlib(&mut Stub).unwrap(), vec!["z"]);
+/// ```
+pub trait Probe {
+    /// Find a package and the packages it depends on, see `Config::find_package`.
+    fn find_package(&mut self, port_name: &str) -> Result<Library, Error>;
+
+    /// Find the packages listed in the crate's metadata, see `Config::find_all`.
+    fn find_all(&mut self) -> Result<Vec<Library>, Error>;
+}
+
+impl Probe for Config {
+    fn find_package(&mut self, port_name: &str) -> Result<Library, Error> {
+        Config::find_package(self, port_name)
+    }
+
+    fn find_all(&mut self) -> Result<Vec<Library>, Error> {
+        Config::find_all(self)
+    }
+}
@@ -0,0 +1,97 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::env_vars::cargo::build_rs::OUT_DIR;
+use crate::Library;
+
+/// Name of the file written to OUT_DIR by `Config::write_probe_json`.
+pub(crate) const PROBE_JSON: &'static str = "vcpkg-probe.json";
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn json_strings<'a, I: IntoIterator<Item = &'a str>>(items: I) -> String {
+    let items: Vec<String> = items.into_iter().map(json_string).collect();
+    format!("[{}]", items.join(", "))
+}
+
+fn json_paths(paths: &[PathBuf]) -> String {
+    let paths: Vec<String> = paths
+        .iter()
+        .map(|p| p.to_string_lossy().into_owned())
+        .collect();
+    json_strings(paths.iter().map(String::as_str))
+}
+
+/// Render the result of a probe as a JSON object.
+pub(crate) fn probe_json(lib: &Library) -> String {
+    let versions: Vec<String> = lib
+        .port_versions
+        .iter()
+        .map(|(port, version)| format!("{}: {}", json_string(port), json_string(version)))
+        .collect();
+    let fields = vec![
+        ("vcpkg_triplet", json_string(&lib.vcpkg_triplet)),
+        ("is_static", lib.is_static.to_string()),
+        ("is_debug", lib.is_debug.to_string()),
+        ("ports", json_strings(lib.ports.iter().map(String::as_str))),
+        ("port_versions", format!("{{{}}}", versions.join(", "))),
+        ("include_paths", json_paths(&lib.include_paths)),
+        ("link_paths", json_paths(&lib.link_paths)),
+        ("dll_paths", json_paths(&lib.dll_paths)),
+        ("found_libs", json_paths(&lib.found_libs)),
+        ("found_dlls", json_paths(&lib.found_dlls)),
+        (
+            "found_names",
+            json_strings(lib.found_names.iter().map(String::as_str)),
+        ),
+        (
+            "cargo_metadata",
+            json_strings(lib.cargo_metadata.iter().map(String::as_str)),
+        ),
+    ];
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|&(name, ref value)| format!("  {}: {}", json_string(name), value))
+        .collect();
+    format!("{{\n{}\n}}\n", fields.join(",\n"))
+}
+
+/// Write the result of a probe to OUT_DIR. This is a debugging aid, so failing
+/// to write the file produces a warning rather than failing the build.
+pub(crate) fn write_probe_json(lib: &mut Library) {
+    let out_dir = match env::var_os(OUT_DIR) {
+        Some(out_dir) => out_dir,
+        None => {
+            lib.cargo_metadata.push(format!(
+                "cargo:warning=vcpkg could not write {} because {} is not set",
+                PROBE_JSON, OUT_DIR
+            ));
+            return;
+        }
+    };
+    let path = Path::new(&out_dir).join(PROBE_JSON);
+    if let Err(e) = fs::write(&path, probe_json(lib)) {
+        lib.cargo_metadata.push(format!(
+            "cargo:warning=vcpkg could not write {}: {}",
+            path.display(),
+            e
+        ));
+    }
+}
@@ -0,0 +1,97 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+use crate::Library;
+
+/// How the directories of the shared libraries of a dynamic triplet other than the
+/// Windows ones are added to the rpath of the binaries linked to them, see
+/// `Config::rpath`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rpath {
+    /// The absolute paths of the directories in the vcpkg tree.
+    Absolute,
+    /// Paths relative to the directory of the binary, which start with `$ORIGIN`,
+    /// or `@loader_path` on macOS. The paths are from the cargo profile directory,
+    /// where cargo puts executables, and from `deps` below it, where it puts tests,
+    /// so the target directory can be moved along with the vcpkg tree.
+    Origin,
+}
+
+/// How much of a Mach-O dylib to read for its install name, which is in one of
+/// the load commands at the start of the file.
+const LOAD_COMMANDS_LEN: u64 = 64 * 1024;
+
+/// Whether a macOS dylib has an install name starting with `@rpath/`, as vcpkg
+/// gives them. A binary linked to such a dylib records that name, and only finds
+/// the dylib at run time if one of its rpaths points to the directory it is in.
+pub(crate) fn has_rpath_install_name(dylib: &Path) -> bool {
+    let mut contents = Vec::new();
+    match File::open(dylib).and_then(|f| f.take(LOAD_COMMANDS_LEN).read_to_end(&mut contents)) {
+        Ok(_) => contents.windows(b"@rpath/".len()).any(|w| w == b"@rpath/"),
+        Err(_) => false,
+    }
+}
+
+/// Set `Library::needs_rpath` if any of the dylibs found has an `@rpath/` install name.
+pub(crate) fn detect_rpath_install_names(lib: &mut Library) {
+    lib.needs_rpath = lib
+        .found_libs
+        .iter()
+        .filter(|path| path.to_str().map_or(false, |path| path.ends_with(".dylib")))
+        .any(|path| has_rpath_install_name(path));
+}
+
+/// The rpath entries that find the shared libraries in `dirs`. `profile_dir` is the
+/// cargo profile directory, without which `Rpath::Origin` falls back to the
+/// absolute paths.
+pub(crate) fn rpath_entries(
+    rpath: Rpath,
+    dirs: &[PathBuf],
+    profile_dir: Option<&Path>,
+    is_macos: bool,
+) -> Vec<PathBuf> {
+    let origin = Path::new(if is_macos { "@loader_path" } else { "$ORIGIN" });
+    let mut entries = Vec::new();
+    for dir in dirs {
+        let relative = match (rpath, profile_dir) {
+            (Rpath::Origin, Some(profile_dir)) => {
+                relative_path(profile_dir, dir).and_then(|from_profile| {
+                    relative_path(&profile_dir.join("deps"), dir)
+                        .map(|from_deps| (from_profile, from_deps))
+                })
+            }
+            _ => None,
+        };
+        match relative {
+            Some((from_profile, from_deps)) => {
+                entries.push(origin.join(from_profile));
+                entries.push(origin.join(from_deps));
+            }
+            None => entries.push(dir.clone()),
+        }
+    }
+    entries
+}
+
+/// The path of `to` relative to the directory `from`, if both are absolute and
+/// on the same drive.
+fn relative_path(from: &Path, to: &Path) -> Option<PathBuf> {
+    if !from.is_absolute() || !to.is_absolute() {
+        return None;
+    }
+    let from: Vec<Component> = from.components().collect();
+    let to: Vec<Component> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|&(a, b)| a == b).count();
+    if common == 0 || from[common..].iter().any(|c| *c == Component::ParentDir) {
+        return None;
+    }
+    let mut path = PathBuf::new();
+    for _ in common..from.len() {
+        path.push("..");
+    }
+    for component in &to[common..] {
+        path.push(component.as_os_str());
+    }
+    Some(path)
+}
@@ -0,0 +1,91 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use crate::{Port, VcpkgTarget};
+
+/// Some file systems only record modification times to the nearest two seconds,
+/// and vcpkg writes the manifest shortly before the status update.
+const TIMESTAMP_SLACK: Duration = Duration::from_secs(2);
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Modification time of the newest of the status database files.
+fn newest_status_time(target: &VcpkgTarget) -> Option<SystemTime> {
+    let mut newest = modified(&target.status_path.join("status"));
+    if let Ok(entries) = fs::read_dir(target.status_path.join("updates")) {
+        for time in entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| modified(&e.path()))
+        {
+            if newest.map_or(true, |newest| time > newest) {
+                newest = Some(time);
+            }
+        }
+    }
+    newest
+}
+
+/// Look for signs that the vcpkg tree was only partially upgraded, which
+/// otherwise shows up as confusing `LibNotFound` errors.
+///
+/// Two things are checked for each of the `ports` selected for linking:
+/// whether the info directory has manifests for versions other than the one
+/// the status database records, and whether the recorded manifest is newer
+/// than any of the status database files.
+pub(crate) fn stale_installation_warnings(
+    target: &VcpkgTarget,
+    ports: &BTreeMap<String, Port>,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let info_dir = target.status_path.join("info");
+    let triplet = &target.target_triplet.name;
+    let suffix = format!("_{}.list", triplet);
+
+    let manifests: Vec<String> = match fs::read_dir(&info_dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.file_name().into_string().ok())
+            .filter(|name| name.ends_with(&suffix))
+            .collect(),
+        Err(_) => return warnings,
+    };
+    let status_time = newest_status_time(target);
+
+    for (name, port) in ports {
+        let prefix = format!("{}_", name);
+        let mut versions: Vec<&str> = manifests
+            .iter()
+            // the prefix and suffix overlap in a stray `<port>_<triplet>.list`
+            .filter(|file| file.starts_with(&prefix) && file.len() > prefix.len() + suffix.len())
+            .map(|file| &file[prefix.len()..file.len() - suffix.len()])
+            .filter(|version| *version != port.version)
+            .collect();
+        if !versions.is_empty() {
+            versions.sort();
+            warnings.push(format!(
+                "cargo:warning=vcpkg port {} is recorded as version {} for {} but manifests \
+                 for {} are also present, the tree may be partially upgraded",
+                name,
+                port.version,
+                triplet,
+                versions.join(", ")
+            ));
+        }
+
+        let manifest = info_dir.join(format!("{}{}{}", prefix, port.version, suffix));
+        if let (Some(manifest_time), Some(status_time)) = (modified(&manifest), status_time) {
+            if manifest_time > status_time + TIMESTAMP_SLACK {
+                warnings.push(format!(
+                    "cargo:warning=vcpkg manifest {} is newer than the status database, \
+                     the tree may be partially upgraded",
+                    manifest.display()
+                ));
+            }
+        }
+    }
+    warnings
+}
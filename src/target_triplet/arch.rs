@@ -495,6 +495,10 @@ impl Arch {
         rustc_name: Some("mipsel"),
     };
 
+    /// # Vcpkg targets
+    /// 
+    /// * `mips64-linux`
+    /// 
     /// # Rustc targets
     /// 
     /// * `mips64-openwrt-linux-musl`
@@ -507,7 +511,7 @@ impl Arch {
     pub(super) const MIPS64: Self = Self {
         #[cfg(any(test,feature = "semver_exempt_llvm_ttc"))]
         llvm_name: Some("mips64"),
-        vcpkg_name: None,
+        vcpkg_name: Some("mips64"),
         rustc_name: Some("mips64"),
     };
 
@@ -603,6 +607,10 @@ impl Arch {
         rustc_name: Some("powerpc64le"),
     };
 
+    /// # Vcpkg targets
+    /// 
+    /// * `riscv32-linux`
+    /// 
     /// # Rustc targets
     ///
     /// * `riscv32gc-unknown-linux-gnu`
@@ -629,10 +637,14 @@ impl Arch {
     pub(super) const RISCV32: Self = Self {
         #[cfg(any(test,feature = "semver_exempt_llvm_ttc"))]
         llvm_name: Some("riscv32"),
-        vcpkg_name: None,
+        vcpkg_name: Some("riscv32"),
         rustc_name: Some("riscv32"),
     };
 
+    /// # Vcpkg targets
+    /// 
+    /// * `riscv64-linux`
+    /// 
     /// # Rustc targets
     ///
     /// * `riscv64gc-unknown-freebsd`
@@ -656,7 +668,7 @@ impl Arch {
     pub(super) const RISCV64: Self = Self {
         #[cfg(any(test,feature = "semver_exempt_llvm_ttc"))]
         llvm_name: Some("riscv64"),
-        vcpkg_name: None,
+        vcpkg_name: Some("riscv64"),
         rustc_name: Some("riscv64"),
     };
 
@@ -908,6 +920,38 @@ impl Arch {
         rustc_name: Some("wasm64"),
     };
 
+    /// Architectures known to vcpkg or rustc, used to match the two up.
+    pub(super) const ALL: &'static [Self] = &[
+        Self::ARM,
+        Self::ARMEB,
+        Self::AARCH64_AKA_ARM64,
+        Self::AARCH64_32_AKA_ARM64_32,
+        Self::AVR,
+        Self::BPFEL,
+        Self::BPFEB,
+        Self::HEXAGON,
+        Self::MIPS,
+        Self::MIPSEL,
+        Self::MIPS64,
+        Self::MIPS64EL,
+        Self::MSP430,
+        Self::PPC_AKA_POWERPC,
+        Self::PPC64_AKA_POWERPC64,
+        Self::PPC64LE_AKA_POWERPC64LE,
+        Self::RISCV32,
+        Self::RISCV64,
+        Self::SPARC,
+        Self::SPARCV9,
+        Self::SPARC64,
+        Self::SYSTEMZ_AKA_S390X,
+        Self::THUMB,
+        Self::X86,
+        Self::X86_64_AKA_X64,
+        Self::NVPTX64,
+        Self::WASM32,
+        Self::WASM64,
+    ];
+
     #[cfg(any(test,feature = "semver_exempt_llvm_ttc"))]
     decl_supported_only_by_llvm!();
 }
\ No newline at end of file
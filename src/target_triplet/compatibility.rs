@@ -0,0 +1,191 @@
+use super::arch::Arch;
+use super::settings::{Linkage, TripletSettings};
+use super::sub::Sub;
+use super::triplet::Triplet;
+
+/// How well libraries built for a vcpkg triplet suit a rust target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compatibility {
+    /// The triplet is one that vcpkg-rs selects for the rust target.
+    KnownGood,
+    /// Nothing is known to be wrong with the combination, e.g. a custom
+    /// triplet for the right architecture and operating system.
+    Plausible,
+    /// The libraries are not suitable for the rust target.
+    Mismatched(Mismatch),
+}
+
+/// The reason a vcpkg triplet does not suit a rust target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mismatch {
+    /// The triplet is for a different CPU architecture.
+    Arch,
+    /// The triplet is for a different operating system or ABI.
+    Os,
+    /// The triplet was built against a C runtime with a different linkage.
+    Linkage,
+}
+
+/// Operating systems as they appear in vcpkg triplet names.
+pub(super) const VCPKG_OSES: &'static [&'static str] = &[
+    "windows",
+    "uwp",
+    "mingw",
+    "osx",
+    "ios",
+    "android",
+    "linux",
+    "freebsd",
+    "openbsd",
+    "netbsd",
+    "haiku",
+    "illumos",
+    "solaris",
+    "emscripten",
+];
+
+/// The vcpkg name of the architecture of a rust target, `Some(None)` if
+/// vcpkg has no triplets for it and `None` if it is not known at all.
+pub(super) fn vcpkg_arch(rust_target: &str) -> Option<Option<&'static str>> {
+    let rustc_arch = rust_target.split('-').next().unwrap_or("");
+    match rustc_arch {
+        // rustc names 32 bit x86 after the oldest supported CPU
+        "i386" | "i586" | "i686" => return Some(Arch::X86.vcpkg_name),
+        _ => {}
+    }
+    let arch = Arch::ALL.iter().find(|arch| {
+        let rustc_name = match arch.rustc_name {
+            Some(rustc_name) => rustc_name,
+            None => return false,
+        };
+        rustc_arch == rustc_name
+            || (rustc_arch.starts_with(rustc_name)
+                && Sub::ALL
+                    .iter()
+                    .any(|sub| sub.rustc_name == Some(&rustc_arch[rustc_name.len()..])))
+    })?;
+    if arch.rustc_name == Arch::THUMB.rustc_name {
+        // vcpkg builds for thumb mode under the arm triplets
+        return Some(Arch::ARM.vcpkg_name);
+    }
+    Some(arch.vcpkg_name)
+}
+
+/// The vcpkg name of the operating system of a rust target.
+fn vcpkg_os(rust_target: &str) -> Option<&'static str> {
+    let oses: &[(&str, &'static str)] = &[
+        ("-uwp-windows-msvc", "uwp"),
+        ("-windows-msvc", "windows"),
+        ("-windows-gnu", "mingw"),
+        ("-apple-darwin", "osx"),
+        ("-apple-ios", "ios"),
+        ("-android", "android"),
+        ("-linux", "linux"),
+        ("-freebsd", "freebsd"),
+        ("-openbsd", "openbsd"),
+        ("-netbsd", "netbsd"),
+        ("-haiku", "haiku"),
+        ("-illumos", "illumos"),
+        ("-solaris", "solaris"),
+        ("-emscripten", "emscripten"),
+    ];
+    oses.iter()
+        .find(|&&(pattern, _)| rust_target.contains(pattern))
+        .map(|&(_, os)| os)
+}
+
+/// Determine whether the libraries of `vcpkg_triplet` suit `rust_target`,
+/// assuming the default target features of `rust_target`.
+///
+/// ```
+/// use vcpkg::{compatibility, Compatibility, Mismatch};
+///
+/// assert_eq!(
+///     compatibility("x86_64-pc-windows-msvc", "x64-windows-static-md"),
+///     Compatibility::KnownGood
+/// );
+/// assert_eq!(
+///     compatibility("x86_64-unknown-linux-gnu", "arm64-linux"),
+///     Compatibility::Mismatched(Mismatch::Arch)
+/// );
+/// ```
+pub fn compatibility(rust_target: &str, vcpkg_triplet: &str) -> Compatibility {
+    check(
+        rust_target,
+        false,
+        &TripletSettings::from_name(vcpkg_triplet),
+    )
+}
+
+/// Like `compatibility` with `crt_static` giving the `crt-static` target feature,
+/// and the CRT linkage taken from `settings` rather than the triplet name.
+///
+/// ```
+/// use vcpkg::{check_compatibility, Compatibility, Mismatch, TripletSettings};
+///
+/// let settings = TripletSettings::from_name("x64-windows-static-md");
+/// assert_eq!(
+///     check_compatibility("x86_64-pc-windows-msvc", true, &settings),
+///     Compatibility::Mismatched(Mismatch::Linkage)
+/// );
+/// ```
+pub fn check(
+    rust_target: &str,
+    crt_static: bool,
+    settings: &TripletSettings,
+) -> Compatibility {
+    let triplet = Triplet::parse(&settings.name);
+
+    match vcpkg_arch(rust_target) {
+        Some(Some(arch)) if arch != triplet.arch() => {
+            return Compatibility::Mismatched(Mismatch::Arch)
+        }
+        Some(None) => return Compatibility::Mismatched(Mismatch::Arch),
+        _ => {}
+    }
+
+    let rust_os = vcpkg_os(rust_target);
+    if let (Some(rust_os), Some(triplet_os)) = (rust_os, triplet.os()) {
+        if rust_os != triplet_os {
+            return Compatibility::Mismatched(Mismatch::Os);
+        }
+    }
+
+    // only MSVC has C runtimes of both linkages that can't be mixed
+    if rust_target.contains("-windows-msvc") {
+        if (settings.crt_linkage == Linkage::Static) != crt_static {
+            return Compatibility::Mismatched(Mismatch::Linkage);
+        }
+    }
+
+    let is_default = |dynamic| {
+        Triplet::for_rust_target(rust_target, crt_static, dynamic)
+            .map(|default| default.to_string() == settings.name)
+            .unwrap_or(false)
+    };
+    if is_default(false) || is_default(true) {
+        Compatibility::KnownGood
+    } else {
+        Compatibility::Plausible
+    }
+}
+
+/// Check that libraries built for `other` can be linked together with those
+/// built for `base`: the architecture and operating system must be the same,
+/// and on Windows so must the linkage of the C runtime.
+pub(crate) fn check_mixable(
+    base: &TripletSettings,
+    other: &TripletSettings,
+) -> Result<(), Mismatch> {
+    let (base_triplet, other_triplet) = (Triplet::parse(&base.name), Triplet::parse(&other.name));
+    if base_triplet.arch() != other_triplet.arch() {
+        return Err(Mismatch::Arch);
+    }
+    if base_triplet.os() != other_triplet.os() {
+        return Err(Mismatch::Os);
+    }
+    if base.static_lib_suffix == "lib" && base.crt_linkage != other.crt_linkage {
+        return Err(Mismatch::Linkage);
+    }
+    Ok(())
+}
@@ -1,6 +1,15 @@
 mod rustc_support_tier;
 mod arch;
+mod compatibility;
+mod settings;
 mod sub;
+mod triplet;
+
+pub use self::compatibility::check as check_compatibility;
+pub(crate) use self::compatibility::check_mixable;
+pub use self::compatibility::{compatibility, Compatibility, Mismatch};
+pub use self::settings::{Linkage, TripletSettings};
+pub use self::triplet::Triplet;
 
 #[derive(Clone)]
 pub(crate) struct VcpkgTriplet {
@@ -13,26 +22,52 @@ pub(crate) struct VcpkgTriplet {
 impl VcpkgTriplet {
     const NON_WINDOWS_LIB_SUFFIX: &'static str = "a";
     const WINDOWS_LIB_SUFFIX: &'static str = "lib";
+
+    /// Is this a Windows triplet, MSVC or mingw, which keeps DLLs in `bin`?
+    pub(crate) fn is_windows(&self) -> bool {
+        self.lib_suffix == Self::WINDOWS_LIB_SUFFIX || Triplet::parse(&self.name).is_windows()
+    }
+
+    /// Take the linkage from the settings of the triplet, which may have been read
+    /// from a triplet file, rather than guessing it from the name.
+    pub(crate) fn with_settings(mut self, settings: &TripletSettings) -> VcpkgTriplet {
+        self.is_static = settings.is_static();
+        if self.lib_suffix != Self::WINDOWS_LIB_SUFFIX {
+            self.lib_suffix = if self.is_static {
+                Self::NON_WINDOWS_LIB_SUFFIX.to_owned()
+            } else if Triplet::parse(&self.name).os() == Some("mingw") {
+                "dll.a".to_owned()
+            } else {
+                settings.dynamic_lib_suffix.clone()
+            };
+        }
+        self
+    }
 }
 
 impl<S: AsRef<str>> From<S> for VcpkgTriplet
 {
-    fn from(triplet: S) -> VcpkgTriplet {
-        let triplet = triplet.as_ref();
-        if triplet.contains("windows") {
-            VcpkgTriplet {
-                name: triplet.into(),
-                is_static: triplet.contains("-static"),
-                lib_suffix: "lib".into(),
-                strip_lib_prefix: false,
-            }
+    fn from(name: S) -> VcpkgTriplet {
+        let name = name.as_ref();
+        let triplet = Triplet::parse(name);
+        let is_static = triplet.library_linkage() == Linkage::Static;
+        let lib_suffix = if triplet.is_msvc() {
+            VcpkgTriplet::WINDOWS_LIB_SUFFIX
+        } else if is_static {
+            VcpkgTriplet::NON_WINDOWS_LIB_SUFFIX
+        } else if triplet.os() == Some("mingw") {
+            // import libraries are lib*.dll.a archives next to the static ones
+            "dll.a"
+        } else if triplet.os() == Some("osx") || triplet.os() == Some("ios") {
+            "dylib"
         } else {
-            VcpkgTriplet {
-                name: triplet.into(),
-                is_static: true,
-                lib_suffix: "a".into(),
-                strip_lib_prefix: true,
-            }
+            "so"
+        };
+        VcpkgTriplet {
+            name: name.into(),
+            is_static,
+            lib_suffix: lib_suffix.into(),
+            strip_lib_prefix: !triplet.is_msvc(),
         }
     }
 }
@@ -0,0 +1,223 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use super::triplet::Triplet;
+use crate::env_vars::vcpkg_rs::VCPKG_OVERLAY_TRIPLETS;
+
+/// Whether something is linked statically or dynamically.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Linkage {
+    Static,
+    Dynamic,
+}
+
+/// The settings of a vcpkg triplet that matter when linking to the libraries it produces.
+///
+/// These correspond to the `VCPKG_LIBRARY_LINKAGE`, `VCPKG_CRT_LINKAGE` and
+/// `VCPKG_BUILD_TYPE` variables of a vcpkg triplet file, plus the file naming
+/// conventions of the target platform.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TripletSettings {
+    /// name of the triplet, e.g. `x64-windows-static-md`
+    pub name: String,
+    /// linkage of the libraries built by vcpkg
+    pub library_linkage: Linkage,
+    /// linkage of the C runtime the libraries were built against
+    pub crt_linkage: Linkage,
+    /// extension of static and import libraries, e.g. `lib` or `a`
+    pub static_lib_suffix: String,
+    /// extension of dynamic libraries, e.g. `dll`, `so` or `dylib`
+    pub dynamic_lib_suffix: String,
+    /// library file names start with `lib`, which is not part of the link name
+    pub strip_lib_prefix: bool,
+    /// only release libraries are built (`VCPKG_BUILD_TYPE release`)
+    pub release_only: bool,
+}
+
+impl TripletSettings {
+    /// Infer the settings of a triplet from its name as `Triplet::parse` reads it,
+    /// following the naming conventions of the triplets that ship with vcpkg.
+    pub fn from_name(name: &str) -> TripletSettings {
+        let triplet = Triplet::parse(name);
+        let (static_lib_suffix, dynamic_lib_suffix) = if triplet.is_msvc() {
+            ("lib", "dll")
+        } else if triplet.os() == Some("mingw") {
+            // import libraries are lib*.dll.a archives next to the static ones
+            ("a", "dll")
+        } else if triplet.os() == Some("osx") || triplet.os() == Some("ios") {
+            ("a", "dylib")
+        } else {
+            ("a", "so")
+        };
+        TripletSettings {
+            name: name.to_owned(),
+            library_linkage: triplet.library_linkage(),
+            crt_linkage: triplet.crt_linkage(),
+            static_lib_suffix: static_lib_suffix.to_owned(),
+            dynamic_lib_suffix: dynamic_lib_suffix.to_owned(),
+            strip_lib_prefix: !triplet.is_msvc(),
+            release_only: triplet.is_release_only(),
+        }
+    }
+
+    /// Read the settings of a triplet from the contents of its cmake triplet file.
+    ///
+    /// Settings that are not `set()` in the file are inferred from the name as
+    /// in `from_name`. Only literal values are understood.
+    pub fn from_cmake(name: &str, contents: &str) -> TripletSettings {
+        let mut settings = TripletSettings::from_name(name);
+        for (variable, value) in cmake_variables(contents) {
+            let linkage = match value {
+                "static" => Some(Linkage::Static),
+                "dynamic" => Some(Linkage::Dynamic),
+                _ => None,
+            };
+            match (variable, linkage) {
+                ("VCPKG_LIBRARY_LINKAGE", Some(linkage)) => settings.library_linkage = linkage,
+                ("VCPKG_CRT_LINKAGE", Some(linkage)) => settings.crt_linkage = linkage,
+                ("VCPKG_BUILD_TYPE", _) => settings.release_only = value == "release",
+                _ => {}
+            }
+        }
+        settings
+    }
+
+    /// Find the settings of a triplet, reading its cmake triplet file from one of
+    /// the directories in `VCPKG_OVERLAY_TRIPLETS` or the `triplets` or
+    /// `triplets/community` directory of `vcpkg_root` if it exists, and inferring
+    /// them from the name otherwise.
+    pub fn resolve(name: &str, vcpkg_root: Option<&Path>) -> TripletSettings {
+        TripletSettings::from_file(name, vcpkg_root)
+            .unwrap_or_else(|| TripletSettings::from_name(name))
+    }
+
+    /// Like `resolve`, but `None` if there is no cmake triplet file for the triplet.
+    /// Overlay triplets take precedence over those that ship with vcpkg, as they
+    /// do in vcpkg.
+    pub(crate) fn from_file(name: &str, vcpkg_root: Option<&Path>) -> Option<TripletSettings> {
+        read_triplet_file(name, vcpkg_root)
+            .map(|contents| TripletSettings::from_cmake(name, &contents))
+    }
+
+    /// Whether the cmake triplet file for the triplet sets `VCPKG_CRT_LINKAGE`, so
+    /// that its CRT linkage is known rather than inferred from the name.
+    pub(crate) fn file_sets_crt_linkage(name: &str, vcpkg_root: Option<&Path>) -> bool {
+        read_triplet_file(name, vcpkg_root).map_or(false, |contents| {
+            cmake_variables(&contents).any(|(variable, _)| variable == "VCPKG_CRT_LINKAGE")
+        })
+    }
+
+    /// Are the libraries built by this triplet linked statically?
+    pub fn is_static(&self) -> bool {
+        self.library_linkage == Linkage::Static
+    }
+}
+
+/// Read the cmake triplet file for the triplet `name` from one of the directories in
+/// `VCPKG_OVERLAY_TRIPLETS` or the `triplets` or `triplets/community` directory of
+/// `vcpkg_root`, in that order.
+fn read_triplet_file(name: &str, vcpkg_root: Option<&Path>) -> Option<String> {
+    let file_name = format!("{}.cmake", name);
+    let mut dirs = Vec::new();
+    if let Some(overlays) = env::var_os(VCPKG_OVERLAY_TRIPLETS) {
+        dirs.extend(env::split_paths(&overlays));
+    }
+    if let Some(vcpkg_root) = vcpkg_root {
+        let triplets = vcpkg_root.join("triplets");
+        dirs.push(triplets.clone());
+        dirs.push(triplets.join("community"));
+    }
+    dirs.iter()
+        .filter_map(|dir| fs::read_to_string(dir.join(&file_name)).ok())
+        .next()
+}
+
+/// The variables given literal values with `set()` in a cmake triplet file, with
+/// the value unquoted.
+fn cmake_variables<'a>(contents: &'a str) -> impl Iterator<Item = (&'a str, &'a str)> + 'a {
+    contents.lines().filter_map(|line| {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if !line.to_ascii_lowercase().starts_with("set(") || !line.ends_with(')') {
+            return None;
+        }
+        let mut args = line[4..line.len() - 1].split_whitespace();
+        match (args.next(), args.next()) {
+            (Some(variable), Some(value)) => Some((variable, value.trim_matches('"'))),
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linkage_from_name() {
+        use self::Linkage::*;
+
+        for &(name, library_linkage, crt_linkage) in &[
+            ("x64-windows", Dynamic, Dynamic),
+            ("x64-windows-static", Static, Static),
+            ("x64-windows-static-md", Static, Dynamic),
+            ("x86-windows-static", Static, Static),
+            ("arm64-windows-static-md", Static, Dynamic),
+            ("arm-windows", Dynamic, Dynamic),
+            ("arm-windows-static", Static, Static),
+            ("arm-windows-static-md", Static, Dynamic),
+            ("x64-linux", Static, Dynamic),
+            ("x64-linux-dynamic", Dynamic, Dynamic),
+            ("arm64-osx-dynamic", Dynamic, Dynamic),
+            ("x64-uwp", Dynamic, Dynamic),
+            ("arm64-uwp", Dynamic, Dynamic),
+            ("x64-uwp-static-md", Static, Dynamic),
+            ("x64-freebsd", Static, Dynamic),
+            ("x64-openbsd", Static, Dynamic),
+        ] {
+            let settings = TripletSettings::from_name(name);
+            assert_eq!(settings.name, name);
+            assert_eq!(settings.library_linkage, library_linkage, "{}", name);
+            assert_eq!(settings.crt_linkage, crt_linkage, "{}", name);
+            assert!(!settings.release_only, "{}", name);
+        }
+        assert!(TripletSettings::from_name("x64-linux-release").release_only);
+    }
+
+    #[test]
+    fn file_names_from_name() {
+        for &(name, static_suffix, dynamic_suffix, strip_lib_prefix) in &[
+            ("x64-windows", "lib", "dll", false),
+            ("arm-windows", "lib", "dll", false),
+            ("arm-windows-static-md", "lib", "dll", false),
+            ("x64-uwp", "lib", "dll", false),
+            ("arm64-uwp", "lib", "dll", false),
+            ("x64-linux", "a", "so", true),
+            ("x64-linux-dynamic", "a", "so", true),
+            ("arm64-osx-dynamic", "a", "dylib", true),
+            ("x64-freebsd", "a", "so", true),
+            ("x64-openbsd", "a", "so", true),
+        ] {
+            let settings = TripletSettings::from_name(name);
+            assert_eq!(settings.static_lib_suffix, static_suffix, "{}", name);
+            assert_eq!(settings.dynamic_lib_suffix, dynamic_suffix, "{}", name);
+            assert_eq!(settings.strip_lib_prefix, strip_lib_prefix, "{}", name);
+        }
+    }
+
+    #[test]
+    fn settings_from_cmake() {
+        let settings = TripletSettings::from_cmake(
+            "x64-rust-static",
+            "set(VCPKG_TARGET_ARCHITECTURE x64)\n\
+             set(VCPKG_CRT_LINKAGE dynamic) # like -static-md\n\
+             set(VCPKG_LIBRARY_LINKAGE static)\n\
+             set(VCPKG_BUILD_TYPE release)\n",
+        );
+        assert_eq!(settings.library_linkage, Linkage::Static);
+        assert_eq!(settings.crt_linkage, Linkage::Dynamic);
+        assert!(settings.release_only);
+    }
+}
@@ -23,7 +23,7 @@ impl Sub {
     pub(super) const ARM_V6: Self = Self {
         #[cfg(any(test,feature = "semver_exempt_llvm_ttc"))]
         llvm_name: None,
-        vcpkg_name: None,
+        vcpkg_name: Some("v6"),
         rustc_name: Some("v6"),
     };
 
@@ -71,6 +71,16 @@ impl Sub {
 
     pub(super) const ARMEB_V7R: Self = Self::ARM_V7R;
 
+    /// ARMv7 with the NEON SIMD extension, which vcpkg writes as a component of its
+    /// own, as in `arm-neon-android`, and rustc as part of the architecture, as in
+    /// `thumbv7neon-linux-androideabi`.
+    pub(super) const ARM_NEON: Self = Self {
+        #[cfg(any(test,feature = "semver_exempt_llvm_ttc"))]
+        llvm_name: None,
+        vcpkg_name: Some("neon"),
+        rustc_name: None,
+    };
+
     pub(super) const RISCV32_GC: Self = Self {
         #[cfg(any(test,feature = "semver_exempt_llvm_ttc"))]
         llvm_name: None,
@@ -158,4 +168,30 @@ impl Sub {
         vcpkg_name: None,
         rustc_name: Some("v8m"),
     };
+
+    /// Sub-architectures known to rustc, e.g. the `v7` in `armv7`.
+    pub(super) const ALL: &'static [Self] = &[
+        Self::ARM_V4T,
+        Self::ARM_V5TE,
+        Self::ARM_V6,
+        Self::ARM_V6K,
+        Self::ARM_V7,
+        Self::ARM_V7A,
+        Self::ARM_V7K,
+        Self::ARM_V7R,
+        Self::ARM_V7S,
+        Self::ARM_NEON,
+        Self::RISCV32_GC,
+        Self::RISCV32_I,
+        Self::RISCV32_IM,
+        Self::RISCV32_IMAC,
+        Self::RISCV32_IMC,
+        Self::THUMB_V4T,
+        Self::THUMB_V6M,
+        Self::THUMB_V7A,
+        Self::THUMB_V7EM,
+        Self::THUMB_V7M,
+        Self::THUMB_V7NEON,
+        Self::THUMB_V8M,
+    ];
 }
\ No newline at end of file
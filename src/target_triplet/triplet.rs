@@ -0,0 +1,458 @@
+use std::fmt;
+
+use super::arch::Arch;
+use super::compatibility::{vcpkg_arch, VCPKG_OSES};
+use super::settings::Linkage;
+use super::sub::Sub;
+
+/// The components of a vcpkg triplet name, such as `x64-windows-static-md`.
+///
+/// A name is split into the architecture, the architecture variant, the
+/// operating system and the linkage suffixes, so that triplets can be
+/// compared structurally rather than by matching substrings of their names.
+/// Components that are not understood, e.g. those of custom triplets, are kept
+/// as qualifiers.
+///
+/// ```
+/// use vcpkg::{Linkage, Triplet};
+///
+/// let triplet = Triplet::parse("x64-windows-static-md");
+/// assert_eq!(triplet.arch(), "x64");
+/// assert_eq!(triplet.os(), Some("windows"));
+/// assert_eq!(triplet.library_linkage(), Linkage::Static);
+/// assert_eq!(triplet.crt_linkage(), Linkage::Dynamic);
+///
+/// let triplet = Triplet::new("arm64", "osx").with_library_linkage(Linkage::Dynamic);
+/// assert_eq!(triplet.to_string(), "arm64-osx-dynamic");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Triplet {
+    arch: String,
+    sub: Option<String>,
+    os: Option<String>,
+    qualifiers: Vec<String>,
+    library_linkage: Option<Linkage>,
+    crt_linkage: Option<Linkage>,
+    release_only: bool,
+}
+
+impl Triplet {
+    /// A triplet for `arch` and `os` with the default linkage of `os`.
+    pub fn new(arch: &str, os: &str) -> Triplet {
+        Triplet {
+            arch: arch.to_owned(),
+            sub: None,
+            os: Some(os.to_owned()),
+            qualifiers: Vec::new(),
+            library_linkage: None,
+            crt_linkage: None,
+            release_only: false,
+        }
+    }
+
+    /// Split a triplet name into its components. This never fails: an unknown
+    /// architecture is kept as it is and unknown components become qualifiers,
+    /// which `to_string` puts between the operating system and the suffixes.
+    pub fn parse(name: &str) -> Triplet {
+        let mut components = name.split('-').peekable();
+        let (arch, sub) = split_arch(components.next().unwrap_or(""));
+        let mut triplet = Triplet {
+            arch,
+            sub,
+            os: None,
+            qualifiers: Vec::new(),
+            library_linkage: None,
+            crt_linkage: None,
+            release_only: false,
+        };
+        while let Some(component) = components.next() {
+            match component {
+                "static" if triplet.library_linkage.is_none() => {
+                    triplet.library_linkage = Some(Linkage::Static);
+                    if components.peek() == Some(&"md") {
+                        components.next();
+                        triplet.crt_linkage = Some(Linkage::Dynamic);
+                    }
+                }
+                "dynamic" if triplet.library_linkage.is_none() => {
+                    triplet.library_linkage = Some(Linkage::Dynamic);
+                }
+                "release" => triplet.release_only = true,
+                _ if triplet.os.is_none() && VCPKG_OSES.contains(&component) => {
+                    triplet.os = Some(component.to_owned());
+                }
+                _ if triplet.os.is_none() && triplet.sub.is_none() && is_vcpkg_sub(component) => {
+                    triplet.sub = Some(component.to_owned());
+                }
+                _ => triplet.qualifiers.push(component.to_owned()),
+            }
+        }
+        triplet
+    }
+
+    /// The triplet vcpkg-rs selects for a rust target, if there is one.
+    ///
+    /// `crt_static` corresponds to the `crt-static` target feature and `dynamic`
+    /// to `VCPKGRS_DYNAMIC`.
+    pub(crate) fn for_rust_target(
+        target: &str,
+        crt_static: bool,
+        dynamic: bool,
+    ) -> Option<Triplet> {
+        let mut components = target.splitn(2, '-');
+        let (rustc_arch, platform) = (components.next()?, components.next()?);
+        let &(_, os, arches) = DEFAULT_PLATFORMS
+            .iter()
+            .find(|&&(default_platform, _, _)| default_platform == platform)?;
+        let arch = vcpkg_arch(target)??;
+        if !arches.contains(&arch) {
+            return None;
+        }
+
+        let triplet = Triplet::new(arch, os);
+        let triplet = match os {
+            "osx" | "linux" if dynamic => triplet.with_library_linkage(Linkage::Dynamic),
+            // the NDK's armeabi-v7a ABI, which has NEON
+            "android" if arch == "arm" => match rustc_arch {
+                "armv7" | "thumbv7neon" => triplet.with_sub("neon"),
+                _ => return None,
+            },
+            // mingw triplets always spell out their linkage
+            "mingw" if dynamic => triplet.with_library_linkage(Linkage::Dynamic),
+            "mingw" => triplet.with_library_linkage(Linkage::Static),
+            "windows" if crt_static => triplet
+                .with_library_linkage(Linkage::Static)
+                .with_crt_linkage(Linkage::Static),
+            "windows" if dynamic => triplet.with_library_linkage(Linkage::Dynamic),
+            "windows" => triplet
+                .with_library_linkage(Linkage::Static)
+                .with_crt_linkage(Linkage::Dynamic),
+            _ => triplet,
+        };
+        Some(triplet)
+    }
+
+    /// Set the variant of the architecture, e.g. `neon` or `v6` for `arm`.
+    pub fn with_sub(mut self, sub: &str) -> Triplet {
+        self.sub = Some(sub.to_owned());
+        self
+    }
+
+    /// Set the linkage of the libraries built for the triplet.
+    pub fn with_library_linkage(mut self, linkage: Linkage) -> Triplet {
+        self.library_linkage = Some(linkage);
+        self
+    }
+
+    /// Set the linkage of the C runtime, which only Windows triplets encode in
+    /// their names.
+    pub fn with_crt_linkage(mut self, linkage: Linkage) -> Triplet {
+        self.crt_linkage = Some(linkage);
+        self
+    }
+
+    /// Set whether only release libraries are built for the triplet.
+    pub fn with_release_only(mut self, release_only: bool) -> Triplet {
+        self.release_only = release_only;
+        self
+    }
+
+    /// vcpkg's name for the architecture, e.g. `x64` or `arm64`.
+    pub fn arch(&self) -> &str {
+        &self.arch
+    }
+
+    /// The variant of the architecture, e.g. `neon` in `arm-neon-android`.
+    pub fn sub(&self) -> Option<&str> {
+        self.sub.as_ref().map(|sub| &sub[..])
+    }
+
+    /// The operating system, e.g. `windows`, `mingw` or `linux`.
+    pub fn os(&self) -> Option<&str> {
+        self.os.as_ref().map(|os| &os[..])
+    }
+
+    /// Components of the name that are not understood, in order.
+    pub fn qualifiers(&self) -> &[String] {
+        &self.qualifiers
+    }
+
+    /// The linkage of the libraries, following the naming conventions of the
+    /// triplets that ship with vcpkg when the name does not say.
+    pub fn library_linkage(&self) -> Linkage {
+        match self.library_linkage {
+            Some(linkage) => linkage,
+            None if self.is_msvc() => Linkage::Dynamic,
+            None => Linkage::Static,
+        }
+    }
+
+    /// The linkage of the C runtime the libraries are built against.
+    pub fn crt_linkage(&self) -> Linkage {
+        match self.crt_linkage {
+            Some(linkage) => linkage,
+            None if self.is_msvc() && self.library_linkage() == Linkage::Static => Linkage::Static,
+            None => Linkage::Dynamic,
+        }
+    }
+
+    /// Only release libraries are built, as for `-release` triplets.
+    pub fn is_release_only(&self) -> bool {
+        self.release_only
+    }
+
+    /// Is this a Windows triplet, MSVC or mingw?
+    pub fn is_windows(&self) -> bool {
+        self.is_msvc() || self.os() == Some("mingw")
+    }
+
+    /// Is this a triplet for the MSVC toolchain, `windows` or `uwp`?
+    pub(crate) fn is_msvc(&self) -> bool {
+        self.os() == Some("windows") || self.os() == Some("uwp")
+    }
+}
+
+impl fmt::Display for Triplet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.arch)?;
+        if let Some(ref sub) = self.sub {
+            // versions are run together with the architecture, as in armv6
+            if sub.starts_with('v') && sub[1..].starts_with(|c: char| c.is_ascii_digit()) {
+                f.write_str(sub)?;
+            } else {
+                write!(f, "-{}", sub)?;
+            }
+        }
+        if let Some(ref os) = self.os {
+            write!(f, "-{}", os)?;
+        }
+        for qualifier in &self.qualifiers {
+            write!(f, "-{}", qualifier)?;
+        }
+        let suffix = match (self.library_linkage(), self.crt_linkage()) {
+            (Linkage::Static, Linkage::Dynamic) if self.is_msvc() => "-static-md",
+            (Linkage::Static, _) if self.is_msvc() || self.library_linkage.is_some() => "-static",
+            (Linkage::Dynamic, _) if !self.is_msvc() => "-dynamic",
+            _ => "",
+        };
+        f.write_str(suffix)?;
+        if self.release_only {
+            f.write_str("-release")?;
+        }
+        Ok(())
+    }
+}
+
+/// The rust target platforms, the part of a target name after the architecture,
+/// that vcpkg-rs selects triplets for, with the vcpkg operating system and the
+/// vcpkg architectures that have a default triplet on it.
+const DEFAULT_PLATFORMS: &'static [(&'static str, &'static str, &'static [&'static str])] = &[
+    (
+        "pc-windows-msvc",
+        "windows",
+        &["x64", "arm64", "arm", "x86"],
+    ),
+    ("pc-windows-gnu", "mingw", &["x64", "arm64", "x86"]),
+    ("pc-windows-gnullvm", "mingw", &["x64", "arm64", "x86"]),
+    ("apple-darwin", "osx", &["x64", "arm64"]),
+    ("apple-ios", "ios", &["arm64"]),
+    ("unknown-linux-gnu", "linux", &["x64"]),
+    ("linux-android", "android", &["arm64", "x64", "x86"]),
+    ("linux-androideabi", "android", &["arm"]),
+    ("unknown-haiku", "haiku", &["x64"]),
+    ("unknown-illumos", "illumos", &["x64"]),
+    ("pc-solaris", "solaris", &["x64"]),
+    ("unknown-freebsd", "freebsd", &["x64"]),
+    ("unknown-openbsd", "openbsd", &["x64"]),
+];
+
+/// Split the first component of a triplet name into a known architecture and
+/// a variant run together with it, as in `armv6`.
+fn split_arch(component: &str) -> (String, Option<String>) {
+    let known = |name: &str| Arch::ALL.iter().any(|arch| arch.vcpkg_name == Some(name));
+    if !known(component) {
+        for arch in Arch::ALL {
+            let arch = match arch.vcpkg_name {
+                Some(arch) => arch,
+                None => continue,
+            };
+            if component.starts_with(arch) && is_vcpkg_sub(&component[arch.len()..]) {
+                return (arch.to_owned(), Some(component[arch.len()..].to_owned()));
+            }
+        }
+    }
+    (component.to_owned(), None)
+}
+
+fn is_vcpkg_sub(name: &str) -> bool {
+    Sub::ALL.iter().any(|sub| sub.vcpkg_name == Some(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn default_name(target: &str, crt_static: bool, dynamic: bool) -> Option<String> {
+        Triplet::for_rust_target(target, crt_static, dynamic).map(|triplet| triplet.to_string())
+    }
+
+    #[test]
+    fn other_targets() {
+        for &(target, triplet) in &[
+            ("x86_64-apple-darwin", Some("x64-osx")),
+            ("aarch64-apple-darwin", Some("arm64-osx")),
+            ("i686-apple-darwin", None),
+            ("aarch64-apple-ios", Some("arm64-ios")),
+            ("aarch64-apple-ios-sim", None),
+            ("x86_64-unknown-linux-gnu", Some("x64-linux")),
+            ("x86_64-unknown-linux-musl", None),
+            ("aarch64-unknown-linux-gnu", None),
+            ("aarch64-linux-android", Some("arm64-android")),
+            ("x86_64-linux-android", Some("x64-android")),
+            ("i686-linux-android", Some("x86-android")),
+            ("armv7-linux-androideabi", Some("arm-neon-android")),
+            ("thumbv7neon-linux-androideabi", Some("arm-neon-android")),
+            ("arm-linux-androideabi", None),
+            ("x86_64-unknown-haiku", Some("x64-haiku")),
+            ("x86_64-unknown-illumos", Some("x64-illumos")),
+            ("x86_64-pc-solaris", Some("x64-solaris")),
+            ("x86_64-unknown-freebsd", Some("x64-freebsd")),
+            ("aarch64-unknown-freebsd", None),
+            ("x86_64-unknown-openbsd", Some("x64-openbsd")),
+            ("wasm32-unknown-unknown", None),
+        ] {
+            assert_eq!(
+                default_name(target, false, false).as_ref().map(|s| &s[..]),
+                triplet
+            );
+        }
+        for &(target, triplet) in &[
+            ("x86_64-apple-darwin", "x64-osx-dynamic"),
+            ("x86_64-unknown-linux-gnu", "x64-linux-dynamic"),
+            ("x86_64-unknown-freebsd", "x64-freebsd"),
+            ("aarch64-linux-android", "arm64-android"),
+        ] {
+            assert_eq!(default_name(target, false, true).unwrap(), triplet);
+        }
+    }
+
+    #[test]
+    fn windows_targets() {
+        for &(target, triplet) in &[
+            ("x86_64-pc-windows-msvc", Some("x64-windows-static-md")),
+            ("aarch64-pc-windows-msvc", Some("arm64-windows-static-md")),
+            ("thumbv7a-pc-windows-msvc", Some("arm-windows-static-md")),
+            ("i686-pc-windows-msvc", Some("x86-windows-static-md")),
+            ("i586-pc-windows-msvc", Some("x86-windows-static-md")),
+            ("arm64ec-pc-windows-msvc", None),
+            ("x86_64-uwp-windows-msvc", None),
+            ("x86_64-pc-windows-gnu", Some("x64-mingw-static")),
+            ("i686-pc-windows-gnu", Some("x86-mingw-static")),
+            ("x86_64-pc-windows-gnullvm", Some("x64-mingw-static")),
+            ("aarch64-pc-windows-gnullvm", Some("arm64-mingw-static")),
+            ("i686-pc-windows-gnullvm", Some("x86-mingw-static")),
+            ("x86_64-uwp-windows-gnu", None),
+        ] {
+            assert_eq!(
+                default_name(target, false, false).as_ref().map(|s| &s[..]),
+                triplet
+            );
+        }
+
+        for &(target, arch) in &[
+            ("x86_64-pc-windows-msvc", "x64"),
+            ("aarch64-pc-windows-msvc", "arm64"),
+            ("thumbv7a-pc-windows-msvc", "arm"),
+        ] {
+            for &(crt_static, dynamic, suffix) in &[
+                (false, false, "windows-static-md"),
+                (true, false, "windows-static"),
+                (false, true, "windows"),
+                (true, true, "windows-static"),
+            ] {
+                assert_eq!(
+                    default_name(target, crt_static, dynamic).unwrap(),
+                    format!("{}-{}", arch, suffix)
+                );
+            }
+        }
+        assert_eq!(
+            default_name("i686-pc-windows-gnu", false, true).unwrap(),
+            "x86-mingw-dynamic"
+        );
+    }
+
+    #[test]
+    fn parse() {
+        use self::Linkage::*;
+
+        for &(name, arch, sub, os) in &[
+            ("arm-neon-android", "arm", Some("neon"), Some("android")),
+            ("armv6-linux", "arm", Some("v6"), Some("linux")),
+            ("arm64-linux", "arm64", None, Some("linux")),
+            ("x64-windows-static", "x64", None, Some("windows")),
+            ("x64-mingw-dynamic", "x64", None, Some("mingw")),
+        ] {
+            let triplet = Triplet::parse(name);
+            assert_eq!(triplet.arch(), arch, "{}", name);
+            assert_eq!(triplet.sub(), sub, "{}", name);
+            assert_eq!(triplet.os(), os, "{}", name);
+        }
+        for &(name, library_linkage, crt_linkage) in &[
+            ("arm-neon-android", Static, Dynamic),
+            ("x64-linux", Static, Dynamic),
+            ("x64-windows", Dynamic, Dynamic),
+            ("x86-windows-static", Static, Static),
+            ("x64-windows-static-md", Static, Dynamic),
+            ("x64-mingw-dynamic", Dynamic, Dynamic),
+            ("x64-mingw-static", Static, Dynamic),
+        ] {
+            let triplet = Triplet::parse(name);
+            assert_eq!(triplet.library_linkage(), library_linkage, "{}", name);
+            assert_eq!(triplet.crt_linkage(), crt_linkage, "{}", name);
+        }
+        assert!(Triplet::parse("x64-mingw-static").is_windows());
+        assert!(!Triplet::parse("x64-linux").is_windows());
+
+        let triplet = Triplet::parse("x64-windows-v142-static-md-release");
+        assert_eq!(triplet.qualifiers(), &["v142".to_owned()]);
+        assert_eq!(triplet.library_linkage(), Static);
+        assert_eq!(triplet.crt_linkage(), Dynamic);
+        assert!(triplet.is_release_only());
+    }
+
+    #[test]
+    fn round_trip() {
+        for name in &[
+            "x64-windows",
+            "x64-windows-static",
+            "arm64-windows-static-md",
+            "x64-uwp",
+            "x64-mingw-static",
+            "x64-mingw-dynamic",
+            "x64-linux",
+            "x64-linux-dynamic",
+            "x64-linux-release",
+            "arm-neon-android",
+            "armv6-linux",
+            "x64-windows-v142-static-md-release",
+            "x64-rust-static",
+        ] {
+            assert_eq!(Triplet::parse(name).to_string(), *name);
+        }
+    }
+
+    #[test]
+    fn builder() {
+        let triplet = Triplet::new("x64", "windows")
+            .with_library_linkage(Linkage::Static)
+            .with_crt_linkage(Linkage::Dynamic)
+            .with_release_only(true);
+        assert_eq!(triplet.to_string(), "x64-windows-static-md-release");
+        let triplet = Triplet::new("arm", "linux").with_sub("v6");
+        assert_eq!(triplet.to_string(), "armv6-linux");
+        let triplet = Triplet::new("x64", "linux").with_library_linkage(Linkage::Dynamic);
+        assert_eq!(triplet.to_string(), "x64-linux-dynamic");
+        assert_eq!(Triplet::new("x64", "windows").to_string(), "x64-windows");
+    }
+}
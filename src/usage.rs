@@ -0,0 +1,166 @@
+use std::fs;
+
+use crate::{Library, VcpkgTarget};
+
+/// Words that can appear among the libraries of `target_link_libraries`.
+const CMAKE_KEYWORDS: &'static [&'static str] = &[
+    "PRIVATE",
+    "PUBLIC",
+    "INTERFACE",
+    "LINK_PRIVATE",
+    "LINK_PUBLIC",
+    "LINK_INTERFACE_LIBRARIES",
+    "debug",
+    "optimized",
+    "general",
+];
+
+/// Find the system libraries that the `usage` file of a port asks for.
+///
+/// The file is free text, usually cmake snippets, so this picks out the plain
+/// library names given to `target_link_libraries`, `-lfoo` flags and `foo.lib`
+/// file names. Targets such as `CURL::libcurl` and variable references are not
+/// system libraries and are skipped.
+pub(crate) fn usage_system_libs(usage: &str) -> Vec<String> {
+    let mut libs = Vec::new();
+    let mut add = |name: &str| {
+        let is_name = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.');
+        if is_name && !libs.iter().any(|lib| lib == name) {
+            libs.push(name.to_owned());
+        }
+    };
+
+    let mut rest = usage;
+    while let Some(pos) = rest.find("target_link_libraries(") {
+        rest = &rest[pos + "target_link_libraries(".len()..];
+        let end = rest.find(')').unwrap_or(rest.len());
+        // the first argument is the target being linked
+        for arg in rest[..end].split_whitespace().skip(1) {
+            if !CMAKE_KEYWORDS.contains(&arg) && !arg.contains("::") {
+                add(arg.trim_end_matches(".lib"));
+            }
+        }
+        rest = &rest[end..];
+    }
+
+    for word in usage.split(|c: char| c.is_whitespace() || "(),\"'".contains(c)) {
+        if word.starts_with("-l") {
+            add(&word[2..]);
+        } else if word.to_ascii_lowercase().ends_with(".lib") {
+            add(&word[..word.len() - 4]);
+        }
+    }
+    libs
+}
+
+/// Library file extensions that are taken off a name before it is compared.
+const LIB_SUFFIXES: &'static [&'static str] = &[".dll.a", ".lib", ".a", ".so", ".dylib", ".dll"];
+
+/// The name a library goes by however it is spelled, lower case and without a
+/// `lib` prefix or a library file extension, so that `libcurl.lib`, `-lcurl`
+/// and `CURL` are all `curl`.
+fn normalized_name(name: &str) -> String {
+    let mut name = name.to_ascii_lowercase();
+    // versioned shared objects such as libfoo.so.1
+    if let Some(pos) = name.find(".so.") {
+        name.truncate(pos);
+    }
+    for suffix in LIB_SUFFIXES {
+        if name.ends_with(suffix) {
+            let len = name.len() - suffix.len();
+            name.truncate(len);
+            break;
+        }
+    }
+    if name.starts_with("lib") && name.len() > 3 {
+        name.drain(..3);
+    }
+    name
+}
+
+/// Whether `name` from a usage file is a library of the ports that were found
+/// rather than a system library, as with `CURL` or `libcurl` in the usage file
+/// of curl.
+pub(crate) fn is_port_lib(name: &str, lib: &Library) -> bool {
+    let name = normalized_name(name);
+    lib.found_names
+        .iter()
+        .map(|found| found.as_str())
+        .chain(
+            lib.port_libs
+                .values()
+                .flatten()
+                .filter_map(|path| path.file_name())
+                .filter_map(|file_name| file_name.to_str()),
+        )
+        .any(|port_lib| normalized_name(port_lib) == name)
+}
+
+/// Read the system libraries named in the `usage` file of a port, if it has one.
+pub(crate) fn port_system_libs(target: &VcpkgTarget, port: &str) -> Vec<String> {
+    match fs::read_to_string(target.share_dir(port).join("usage")) {
+        Ok(usage) => usage_system_libs(&usage),
+        Err(_) => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn system_libs() {
+        for &(usage, libs) in &[
+            (
+                "target_link_libraries(main PRIVATE CURL::libcurl ${ZLIB_LIBRARIES} ws2_32\n\
+                 optimized crypt32 CURL)",
+                &["ws2_32", "crypt32", "CURL"][..],
+            ),
+            ("target_link_libraries(main PUBLIC debug foo.lib)", &["foo"]),
+            (
+                "Link with -lpthread, -lcurl, bcrypt.lib and \"Normaliz.lib\".",
+                &["pthread", "curl", "bcrypt", "Normaliz"],
+            ),
+            (
+                "Add Ws2_32.LIB and -lm -lm to the linker flags.",
+                &["Ws2_32", "m"],
+            ),
+            (
+                "target_link_libraries(main PRIVATE unofficial::sqlite3::sqlite3)",
+                &[],
+            ),
+            (
+                "The package zlib is compatible with built-in CMake targets.",
+                &[],
+            ),
+        ] {
+            assert_eq!(usage_system_libs(usage), libs, "{}", usage);
+        }
+    }
+
+    #[test]
+    fn port_libs() {
+        let mut lib = Library::default();
+        lib.found_names.push("libcurl".to_owned());
+        lib.port_libs.insert(
+            "zlib".to_owned(),
+            vec![PathBuf::from("installed/x64-linux/lib/libz.so.1")],
+        );
+        for &(name, is_port_lib) in &[
+            ("CURL", true),
+            ("curl", true),
+            ("libcurl.lib", true),
+            ("z", true),
+            ("libz.a", true),
+            ("ws2_32", false),
+            ("lib", false),
+        ] {
+            assert_eq!(super::is_port_lib(name, &lib), is_port_lib, "{}", name);
+        }
+    }
+}
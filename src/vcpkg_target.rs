@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use crate::VcpkgTriplet;
 
@@ -15,17 +15,310 @@ pub(crate) struct VcpkgTarget {
 
     // target-specific settings.
     pub(crate) target_triplet: VcpkgTriplet,
+
+    // are lib_path and bin_path in the debug tree of the triplet?
+    pub(crate) is_debug: bool,
+
+    // the triplet that vcpkg builds tools for, if it is known
+    pub(crate) host_triplet: Option<String>,
 }
 
 impl VcpkgTarget {
+    /// The name a library in the manifest is recorded with in `Port::libs`, which is
+    /// its whole file name. The `lib` prefix is only taken off when the library is
+    /// linked, see `link_name`.
     pub(crate) fn link_name_for_lib(&self, filename: &std::path::Path) -> Option<String> {
-        if self.target_triplet.strip_lib_prefix {
-            filename.to_str().map(|s| s.to_owned())
-        // filename
-        //     .to_str()
-        //     .map(|s| s.trim_left_matches("lib").to_owned())
+        filename.to_str().map(|s| s.to_owned())
+    }
+
+    /// Strip the library suffix from a file name in the manifest. Versioned shared
+    /// objects such as `libfoo.so.1` don't end in the suffix and are kept whole.
+    pub(crate) fn lib_stem(&self, filename: &str) -> String {
+        let suffix = format!(".{}", self.target_triplet.lib_suffix);
+        if filename.ends_with(&suffix) {
+            filename[..filename.len() - suffix.len()].to_owned()
+        } else if self.is_lib_file(filename) {
+            filename[..filename.len() - ".a".len()].to_owned()
         } else {
-            filename.to_str().map(|s| s.to_owned())
+            filename.to_owned()
+        }
+    }
+
+    /// Whether the triplet links to DLLs through `.dll.a` import libraries, as the
+    /// mingw dynamic triplets do. Some ports install static `.a` archives next to
+    /// them, such as `libSDL2main.a`.
+    fn has_import_archives(&self) -> bool {
+        self.target_triplet.lib_suffix == "dll.a"
+    }
+
+    /// Whether a file name in the manifest is a library to link, which ends in the
+    /// suffix of the triplet, or is one of the static archives installed next to
+    /// the import libraries of a mingw dynamic triplet.
+    pub(crate) fn is_lib_file(&self, filename: &str) -> bool {
+        filename.ends_with(&format!(".{}", self.target_triplet.lib_suffix))
+            || (self.has_import_archives() && filename.ends_with(".a"))
+    }
+
+    /// Whether a library file is a static archive of a mingw dynamic triplet rather
+    /// than an import library, so it has to be linked statically.
+    pub(crate) fn is_static_archive(&self, filename: &str) -> bool {
+        self.has_import_archives() && !filename.ends_with(".dll.a")
+    }
+
+    /// Remove the static archives that have an import library of the same name
+    /// among `libs`, such as `libfoo.a` next to `libfoo.dll.a`, so that the
+    /// library is linked once, to the DLL.
+    pub(crate) fn drop_shadowed_archives(&self, libs: &mut Vec<String>) {
+        if !self.has_import_archives() {
+            return;
+        }
+        let imported: Vec<String> = libs
+            .iter()
+            .filter(|lib| lib.ends_with(".dll.a"))
+            .map(|lib| self.lib_stem(lib))
+            .collect();
+        libs.retain(|lib| !self.is_static_archive(lib) || !imported.contains(&self.lib_stem(lib)));
+    }
+
+    /// The name a library is linked with from its stem, without the `lib`
+    /// prefix on triplets whose linker adds it. Only one prefix is taken off, so
+    /// `liblibfoo` is linked as `libfoo`.
+    pub(crate) fn link_name(&self, stem: &str) -> String {
+        if self.target_triplet.strip_lib_prefix
+            && stem.len() > "lib".len()
+            && stem.starts_with("lib")
+        {
+            stem["lib".len()..].to_owned()
+        } else {
+            stem.to_owned()
+        }
+    }
+
+    /// Whether a library can only be linked by its file name, as the linker of the
+    /// triplet adds a `lib` prefix to the names it is given but the stem has none.
+    pub(crate) fn needs_verbatim(&self, stem: &str) -> bool {
+        self.target_triplet.strip_lib_prefix && self.link_name(stem) == stem
+    }
+
+    /// The file name of a library from its stem, see `lib_stem`, which may be in a
+    /// subdirectory of `lib_path`. On a mingw dynamic triplet this is the static
+    /// archive if there is one but no import library.
+    pub(crate) fn lib_file_name(&self, stem: &str) -> String {
+        if is_versioned_shared_lib(stem) {
+            return stem.to_owned();
+        }
+        let file_name = format!("{}.{}", stem, self.target_triplet.lib_suffix);
+        let archive = format!("{}.a", stem);
+        if self.has_import_archives()
+            && !self.lib_path.join(&file_name).exists()
+            && self.lib_path.join(&archive).exists()
+        {
+            archive
+        } else {
+            file_name
+        }
+    }
+
+    /// Strip the `.dll` suffix from a DLL file name. Shared objects on other
+    /// platforms are kept whole, as they are usually versioned.
+    pub(crate) fn dll_stem(&self, filename: &str) -> String {
+        if self.target_triplet.is_windows() {
+            Path::new(filename)
+                .file_stem()
+                .unwrap()
+                .to_string_lossy()
+                .into_owned()
+        } else {
+            filename.to_owned()
+        }
+    }
+
+    /// Where a tool from the manifest of a port, such as `tools/protobuf/protoc.exe`, is installed.
+    pub(crate) fn tool_location(&self, tool: &str) -> PathBuf {
+        self.include_path.with_file_name(tool)
+    }
+
+    /// The `share/<port>` directory of a port, which holds its usage notes and cmake files.
+    pub(crate) fn share_dir(&self, port: &str) -> PathBuf {
+        self.include_path.with_file_name("share").join(port)
+    }
+
+    /// The directory that DLLs, or shared objects on other platforms, are installed in.
+    pub(crate) fn dll_dir(&self) -> &PathBuf {
+        if self.target_triplet.is_windows() {
+            &self.bin_path
+        } else {
+            &self.lib_path
+        }
+    }
+
+    /// The stem of the debug variant of a library when `lib_path` is in the debug
+    /// tree, which is often named with a `d` suffix such as `zlibd` or `libpng16d`.
+    /// The stem is returned unchanged if it is installed or no variant is.
+    pub(crate) fn debug_lib_stem(&self, stem: &str) -> String {
+        let exists = |stem: &str| self.lib_path.join(self.lib_file_name(stem)).exists();
+        if !self.is_debug || exists(stem) {
+            return stem.to_owned();
+        }
+        debug_names(stem)
+            .into_iter()
+            .find(|name| exists(name))
+            .unwrap_or(stem.to_owned())
+    }
+
+    /// The stem of the debug variant of a DLL, see `debug_lib_stem`.
+    pub(crate) fn debug_dll_stem(&self, stem: &str) -> String {
+        let exists = |stem: &str| self.dll_location(stem).exists();
+        if !self.is_debug || exists(stem) {
+            return stem.to_owned();
+        }
+        debug_names(stem)
+            .into_iter()
+            .find(|name| exists(name))
+            .unwrap_or(stem.to_owned())
+    }
+
+    /// Where a DLL, or a shared object on other platforms, is installed.
+    pub(crate) fn dll_location(&self, stem: &str) -> PathBuf {
+        if self.target_triplet.is_windows() {
+            self.bin_path.join(format!("{}.dll", stem))
+        } else {
+            self.lib_path.join(stem)
+        }
+    }
+}
+
+/// Names that the debug variant of a library or DLL may have: `zlib` becomes
+/// `zlibd`, and a version at the end is kept last, so `zlib1` becomes `zlibd1`.
+fn debug_names(stem: &str) -> Vec<String> {
+    let mut names = vec![format!("{}d", stem)];
+    let version_len = stem
+        .chars()
+        .rev()
+        .take_while(|c| c.is_ascii_digit())
+        .count();
+    if version_len > 0 && version_len < stem.len() {
+        let (name, version) = stem.split_at(stem.len() - version_len);
+        names.push(format!("{}d{}", name, version));
+    }
+    names
+}
+
+/// Is this a shared object with a version after the suffix, such as `libfoo.so.1.2.3`?
+pub(crate) fn is_versioned_shared_lib(filename: &str) -> bool {
+    match filename.find(".so.") {
+        Some(pos) => filename[pos + 4..]
+            .split('.')
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit())),
+        None => false,
+    }
+}
+
+/// The unversioned name of a macOS dylib with a version before the suffix, such as
+/// `libfoo.dylib` for `libfoo.1.2.3.dylib`.
+pub(crate) fn unversioned_dylib(filename: &str) -> Option<String> {
+    if !filename.ends_with(".dylib") {
+        return None;
+    }
+    let stem = &filename[..filename.len() - ".dylib".len()];
+    let pos = stem.find('.')?;
+    let is_version = stem[pos + 1..]
+        .split('.')
+        .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+    if is_version {
+        Some(format!("{}.dylib", &stem[..pos]))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A target for `triplet` whose directories don't exist.
+    pub(crate) fn target(triplet: &str) -> VcpkgTarget {
+        let installed = PathBuf::from("installed").join(triplet);
+        VcpkgTarget {
+            lib_path: installed.join("lib"),
+            bin_path: installed.join("bin"),
+            include_path: installed.join("include"),
+            status_path: PathBuf::from("installed").join("vcpkg"),
+            packages_path: PathBuf::from("packages"),
+            target_triplet: triplet.into(),
+            is_debug: false,
+            host_triplet: None,
+        }
+    }
+
+    #[test]
+    fn versioned_shared_libs() {
+        for &(name, is_versioned) in &[
+            ("libfoo.so.1", true),
+            ("libfoo.so.1.2.3", true),
+            ("libfoo.so", false),
+            ("libfoo.so.", false),
+            ("libfoo.so.1.", false),
+            ("libfoo.so.1a", false),
+            ("libfoo.so.debug", false),
+            ("libfoo.a", false),
+        ] {
+            assert_eq!(is_versioned_shared_lib(name), is_versioned, "{}", name);
+        }
+    }
+
+    #[test]
+    fn shared_lib_link_names() {
+        let target = target("x64-linux-dynamic");
+        for &(filename, stem, link_name) in &[
+            ("libfoo.so", "libfoo", "foo"),
+            ("libfoo.so.1", "libfoo.so.1", "foo.so.1"),
+            ("libfoo.so.1.2.3", "libfoo.so.1.2.3", "foo.so.1.2.3"),
+            ("liblibfoo.so", "liblibfoo", "libfoo"),
+            ("foo.so", "foo", "foo"),
+        ] {
+            let lib_stem = target.lib_stem(filename);
+            assert_eq!(lib_stem, stem, "{}", filename);
+            assert_eq!(target.link_name(&lib_stem), link_name, "{}", filename);
+            assert_eq!(target.needs_verbatim(&lib_stem), lib_stem == link_name);
+        }
+        assert_eq!(target.lib_file_name("libfoo.so.1"), "libfoo.so.1");
+        assert_eq!(target.lib_file_name("libfoo"), "libfoo.so");
+    }
+
+    #[test]
+    fn mingw_lib_files() {
+        let dynamic = target("x64-mingw-dynamic");
+        for &(name, is_lib, is_static, stem, link_name) in &[
+            ("libfoo.dll.a", true, false, "libfoo", "foo"),
+            ("libSDL2main.a", true, true, "libSDL2main", "SDL2main"),
+            ("foo.dll.a", true, false, "foo", "foo"),
+        ] {
+            assert_eq!(dynamic.is_lib_file(name), is_lib, "{}", name);
+            assert_eq!(dynamic.is_static_archive(name), is_static, "{}", name);
+            assert_eq!(dynamic.lib_stem(name), stem, "{}", name);
+            assert_eq!(dynamic.link_name(stem), link_name, "{}", name);
+        }
+
+        let mut libs = vec![
+            "libfoo.a".to_owned(),
+            "libfoo.dll.a".to_owned(),
+            "libSDL2main.a".to_owned(),
+        ];
+        dynamic.drop_shadowed_archives(&mut libs);
+        assert_eq!(libs, ["libfoo.dll.a", "libSDL2main.a"]);
+
+        let static_ = target("x64-mingw-static");
+        for &(name, is_lib, stem) in &[
+            ("libfoo.a", true, "libfoo"),
+            ("libfoo.dll.a", true, "libfoo.dll"),
+        ] {
+            assert_eq!(static_.is_lib_file(name), is_lib, "{}", name);
+            assert!(!static_.is_static_archive(name), "{}", name);
+            assert_eq!(static_.lib_stem(name), stem, "{}", name);
         }
+        let mut libs = vec!["libfoo.a".to_owned(), "libfoo.dll.a".to_owned()];
+        static_.drop_shadowed_archives(&mut libs);
+        assert_eq!(libs.len(), 2);
     }
 }
@@ -0,0 +1,13 @@
+use std::env;
+use std::fmt;
+
+use crate::env_vars::vcpkg_rs::VCPKGRS_VERBOSE;
+
+/// Explain a decision of the probe on stderr when `VCPKGRS_VERBOSE` is set.
+/// Cargo keeps the stderr of a build script in its output file and shows it
+/// with `cargo build -vv`, and it is never taken for a `cargo:` directive.
+pub(crate) fn log(message: fmt::Arguments) {
+    if env::var_os(VCPKGRS_VERBOSE).is_some() {
+        eprintln!("vcpkg-rs: {}", message);
+    }
+}
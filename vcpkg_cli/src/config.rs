@@ -0,0 +1,79 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Settings read from the vcpkg_cli configuration file.
+///
+/// The file contains `key = value` lines, blank lines and `#` comments:
+///
+/// ```text
+/// # rust target to find libraries for
+/// target = x86_64-pc-windows-msvc
+/// # vcpkg triplet to use instead of the one vcpkg-rs would select
+/// triplet = x64-windows-static-md
+/// # vcpkg installation to use instead of VCPKG_ROOT
+/// root = C:\src\vcpkg
+/// # output format, `text` or `json`
+/// format = text
+/// ```
+#[derive(Default)]
+pub struct CliConfig {
+    pub target: Option<String>,
+    pub triplet: Option<String>,
+    pub root: Option<PathBuf>,
+    pub format: Option<String>,
+}
+
+impl CliConfig {
+    /// The configuration file used when `--config` is not given, which can be
+    /// overridden with `VCPKG_CLI_CONFIG`.
+    pub fn default_path() -> Option<PathBuf> {
+        if let Some(path) = env::var_os("VCPKG_CLI_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+        let config_dir = if cfg!(windows) {
+            env::var_os("APPDATA").map(PathBuf::from)
+        } else {
+            env::var_os("XDG_CONFIG_HOME")
+                .map(PathBuf::from)
+                .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        };
+        config_dir.map(|dir| dir.join("vcpkg_cli").join("config"))
+    }
+
+    /// Read a configuration file. A missing file is not an error when
+    /// `required` is false.
+    pub fn load(path: &PathBuf, required: bool) -> Result<CliConfig, String> {
+        match fs::read_to_string(path) {
+            Ok(contents) => {
+                CliConfig::parse(&contents).map_err(|e| format!("{}: {}", path.display(), e))
+            }
+            Err(ref e) if !required && e.kind() == ::std::io::ErrorKind::NotFound => {
+                Ok(CliConfig::default())
+            }
+            Err(e) => Err(format!("could not read {}: {}", path.display(), e)),
+        }
+    }
+
+    pub fn parse(contents: &str) -> Result<CliConfig, String> {
+        let mut config = CliConfig::default();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = match line.find('=') {
+                Some(pos) => (line[..pos].trim(), line[pos + 1..].trim()),
+                None => return Err(format!("line {}: expected `key = value`", number + 1)),
+            };
+            match key {
+                "target" => config.target = Some(value.to_owned()),
+                "triplet" => config.triplet = Some(value.to_owned()),
+                "root" => config.root = Some(PathBuf::from(value)),
+                "format" => config.format = Some(value.to_owned()),
+                _ => return Err(format!("line {}: unknown key `{}`", number + 1, key)),
+            }
+        }
+        Ok(config)
+    }
+}
@@ -0,0 +1,208 @@
+use std::env;
+
+use vcpkg::{Compatibility, Config, Error, Linkage, Mismatch};
+
+/// Whether rustc links the C runtime statically, from the `crt-static` target
+/// feature in `CARGO_CFG_TARGET_FEATURE` when run from a build script, or in
+/// `CARGO_ENCODED_RUSTFLAGS` or `RUSTFLAGS` otherwise. `None` if it can't be
+/// told, such as when it is set in a cargo config file.
+fn crt_static() -> Option<bool> {
+    if let Ok(features) = env::var("CARGO_CFG_TARGET_FEATURE") {
+        return Some(features.split(',').any(|feature| feature == "crt-static"));
+    }
+    let flags = env::var("CARGO_ENCODED_RUSTFLAGS")
+        .or_else(|_| env::var("RUSTFLAGS"))
+        .ok()?;
+    rustflags_crt_static(&flags)
+}
+
+/// The last `+crt-static` or `-crt-static` given with `-C target-feature` in
+/// rustflags, which may be separated by whitespace or, when encoded, by `\x1f`.
+fn rustflags_crt_static(flags: &str) -> Option<bool> {
+    let mut crt_static = None;
+    for flag in flags.split(|c: char| c.is_whitespace() || c == '\x1f') {
+        let features = match flag.find("target-feature=") {
+            Some(pos) => &flag[pos + "target-feature=".len()..],
+            None => continue,
+        };
+        for feature in features.split(',') {
+            match feature {
+                "+crt-static" => crt_static = Some(true),
+                "-crt-static" => crt_static = Some(false),
+                _ => {}
+            }
+        }
+    }
+    crt_static
+}
+
+/// The findings of `vcpkg_cli doctor`.
+#[derive(Default)]
+struct Report {
+    problems: usize,
+}
+
+impl Report {
+    fn ok(&mut self, message: String) {
+        println!("ok:      {}", message);
+    }
+
+    fn problem(&mut self, message: String) {
+        println!("problem: {}", message);
+        self.problems += 1;
+    }
+}
+
+/// Check the vcpkg installation and the environment for the mistakes that most
+/// often keep vcpkg-rs from finding packages, printing what was found. Returns
+/// the number of problems.
+pub fn doctor(cfg: &mut Config, target: &str) -> usize {
+    let mut report = Report::default();
+
+    for var in &["VCPKGRS_DISABLE", "NO_VCPKG"] {
+        if env::var_os(var).is_some() {
+            report.problem(format!(
+                "{} is set, so vcpkg-rs will not look for packages",
+                var
+            ));
+        }
+    }
+
+    match vcpkg::find_vcpkg_root(cfg) {
+        Ok(root) => {
+            if root.join(".vcpkg-root").exists() {
+                report.ok(format!("found the vcpkg root {}", root.display()));
+            } else if root
+                .join("installed")
+                .join("vcpkg")
+                .join("status")
+                .is_file()
+            {
+                report.ok(format!("found the exported vcpkg tree {}", root.display()));
+            } else {
+                report.problem(format!(
+                    "{} is not a vcpkg root as it has no .vcpkg-root file, \
+                     set VCPKG_ROOT to the directory vcpkg was cloned into",
+                    root.display()
+                ));
+            }
+        }
+        Err(err) => report.problem(err.to_string()),
+    }
+
+    let triplet = match cfg.selected_triplet() {
+        Ok(triplet) => triplet,
+        Err(Error::NotMSVC) => {
+            report.problem(format!(
+                "vcpkg-rs has no default triplet for {}, set VCPKGRS_TRIPLET to the triplet to use",
+                target
+            ));
+            return report.problems;
+        }
+        Err(err) => {
+            report.problem(format!(
+                "could not select a triplet for {}: {}",
+                target, err
+            ));
+            return report.problems;
+        }
+    };
+    // without a known crt-static the triplet is assumed to suit it
+    let crt_static = crt_static();
+    let assumed_crt_static = crt_static.unwrap_or(triplet.crt_linkage == Linkage::Static);
+    match vcpkg::check_compatibility(target, assumed_crt_static, &triplet) {
+        Compatibility::Mismatched(Mismatch::Arch) => report.problem(format!(
+            "triplet {} is for a different architecture than {}",
+            triplet.name, target
+        )),
+        Compatibility::Mismatched(Mismatch::Os) => report.problem(format!(
+            "triplet {} is for a different operating system than {}",
+            triplet.name, target
+        )),
+        Compatibility::Mismatched(Mismatch::Linkage)
+            if env::var_os("VCPKGRS_ALLOW_CRT_MISMATCH").is_none() =>
+        {
+            report.problem(format!(
+                "triplet {} links the C runtime {} but rustc links it {}",
+                triplet.name,
+                if assumed_crt_static {
+                    "dynamically"
+                } else {
+                    "statically"
+                },
+                if assumed_crt_static {
+                    "statically"
+                } else {
+                    "dynamically"
+                }
+            ))
+        }
+        _ => report.ok(format!(
+            "{} uses the vcpkg triplet {}",
+            target, triplet.name
+        )),
+    }
+    if crt_static.is_none() && target.contains("-windows-msvc") {
+        report.ok(format!(
+            "the C runtime linkage of triplet {} was not checked, as crt-static is not \
+             set in RUSTFLAGS",
+            triplet.name
+        ));
+    }
+
+    match cfg.installation() {
+        Ok(installation) => {
+            if installation.ports().is_empty() {
+                report.problem(format!(
+                    "no ports are installed for triplet {}, install them with \
+                     `vcpkg install <port>:{}`",
+                    triplet.name, triplet.name
+                ));
+            } else {
+                report.ok(format!(
+                    "the status database lists {} ports for triplet {}",
+                    installation.ports().len(),
+                    triplet.name
+                ));
+            }
+            let triplet_dir = installation.installed_root().join(installation.triplet());
+            if triplet_dir.is_dir() {
+                report.ok(format!(
+                    "found the installed tree {}",
+                    triplet_dir.display()
+                ));
+            } else {
+                report.problem(format!(
+                    "{} does not exist, so nothing can be found for triplet {}",
+                    triplet_dir.display(),
+                    installation.triplet()
+                ));
+            }
+        }
+        Err(err) => report.problem(format!("could not read the status database: {}", err)),
+    }
+
+    // VCPKGRS_DYNAMIC=0 and VCPKGRS_STATIC force the static triplets
+    let dynamic_set = vcpkg::forced_dynamic() == Some(true);
+    match triplet.library_linkage {
+        Linkage::Static if dynamic_set => report.problem(format!(
+            "VCPKGRS_DYNAMIC is set, but triplet {} is static so it has no effect",
+            triplet.name
+        )),
+        Linkage::Dynamic if !dynamic_set => report.problem(format!(
+            "triplet {} is dynamic, which needs VCPKGRS_DYNAMIC=1 or VCPKGRS_DYNAMIC_<PORT>=1",
+            triplet.name
+        )),
+        _ => report.ok(format!(
+            "VCPKGRS_DYNAMIC is {}set for the {} triplet",
+            if dynamic_set { "" } else { "not " },
+            if triplet.is_static() {
+                "static"
+            } else {
+                "dynamic"
+            }
+        )),
+    }
+
+    report.problems
+}
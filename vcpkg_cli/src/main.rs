@@ -1,11 +1,21 @@
 extern crate clap;
+extern crate serde_json;
 extern crate vcpkg;
 
-use clap::{App, AppSettings, Arg, SubCommand};
+mod config;
+mod doctor;
+
+use clap::{App, AppSettings, Arg, Shell, SubCommand};
+use config::CliConfig;
 use std::env;
+use std::io;
+use std::path::PathBuf;
+use std::process;
 
-fn main() {
-    let app = App::new("vcpkg library finder")
+const DEFAULT_TARGET: &str = "x86_64-pc-windows-msvc";
+
+fn app() -> App<'static, 'static> {
+    App::new("vcpkg library finder")
         .about("Allows examining what vcpkg will find in a build script")
         .setting(AppSettings::SubcommandRequired)
         .arg(
@@ -13,9 +23,17 @@ fn main() {
                 .short("t")
                 .long("target")
                 .value_name("RUST TARGET TRIPLE")
-                .help("the rust toolchain triple to find libraries for")
+                .help("the rust toolchain triple to find libraries for [default: x86_64-pc-windows-msvc]")
                 .takes_value(true)
-                .default_value("x86_64-pc-windows-msvc"),
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("config")
+                .short("c")
+                .long("config")
+                .value_name("FILE")
+                .help("read settings from FILE instead of the default configuration file")
+                .takes_value(true),
         )
         .subcommand(
             SubCommand::with_name("probe")
@@ -32,20 +50,115 @@ fn main() {
                         .long("linkage")
                         .takes_value(true)
                         .possible_values(&["dll", "static"]),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("print the library that was found as JSON"),
                 ),
-        );
+        )
+        .subcommand(
+            SubCommand::with_name("deps")
+                .about("show the ports a package depends on, in the order they are linked")
+                .arg(
+                    Arg::with_name("package")
+                        .index(1)
+                        .required(true)
+                        .help("the port to show the dependencies of"),
+                )
+                .arg(
+                    Arg::with_name("libs")
+                        .long("libs")
+                        .help("list the libraries each port provides"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("doctor")
+                .about("look for problems with the vcpkg tree and the environment"),
+        )
+        .subcommand(
+            SubCommand::with_name("completions")
+                .about("print a shell completion script")
+                .arg(
+                    Arg::with_name("shell")
+                        .index(1)
+                        .required(true)
+                        .possible_values(&Shell::variants()),
+                ),
+        )
+}
 
-    let matches = app.get_matches();
+fn main() {
+    let matches = app().get_matches();
+
+    if let Some(matches) = matches.subcommand_matches("completions") {
+        let shell = matches.value_of("shell").unwrap().parse::<Shell>().unwrap();
+        app().gen_completions_to("vcpkg_cli", shell, &mut io::stdout());
+        return;
+    }
+
+    let settings = match matches.value_of("config") {
+        Some(path) => CliConfig::load(&PathBuf::from(path), true),
+        None => match CliConfig::default_path() {
+            Some(path) => CliConfig::load(&path, false),
+            None => Ok(CliConfig::default()),
+        },
+    };
+    let settings = settings.unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    });
+    match settings.format.as_deref() {
+        None | Some("text") | Some("json") => {}
+        Some(format) => {
+            eprintln!("error: unsupported output format `{}`", format);
+            process::exit(1);
+        }
+    }
 
     // set TARGET as if we are running under cargo
-    env::set_var("TARGET", matches.value_of("target").unwrap());
+    let target = matches
+        .value_of("target")
+        .or(settings.target.as_deref())
+        .unwrap_or(DEFAULT_TARGET);
+    env::set_var("TARGET", target);
+
+    if matches.subcommand_matches("doctor").is_some() {
+        let problems = doctor::doctor(&mut new_config(&settings), target);
+        if problems > 0 {
+            println!("{} problem(s) found", problems);
+            process::exit(1);
+        }
+        println!("no problems found");
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("deps") {
+        let port_name = matches.value_of("package").unwrap();
+        let lib = match new_config(&settings).find_package(port_name) {
+            Ok(lib) => lib,
+            Err(err) => {
+                println!("Failed:  {}", err);
+                process::exit(1);
+            }
+        };
+        for port in &lib.ports {
+            match lib.version(port) {
+                Some(version) => println!("{} {}", port, version),
+                None => println!("{}", port),
+            }
+            if matches.is_present("libs") {
+                for path in lib.port_libs.get(port).into_iter().flatten() {
+                    println!("  {}", path.display());
+                }
+            }
+        }
+    }
 
     if let Some(matches) = matches.subcommand_matches("probe") {
         let lib_name = matches.value_of("package").unwrap();
 
-        let mut cfg = vcpkg::Config::new();
-        cfg.cargo_metadata(false);
-        cfg.copy_dlls(false);
+        let mut cfg = new_config(&settings);
         if let Some(linkage) = matches.value_of("linkage") {
             match linkage {
                 "dll" => {
@@ -60,9 +173,17 @@ fn main() {
             }
         }
 
-        match cfg.find_package(lib_name) {
+        let json = matches.is_present("json") || settings.format.as_deref() == Some("json");
+        let result = cfg.find_package(lib_name);
+        if json {
+            print_json(&result);
+            return;
+        }
+
+        match result {
             Ok(lib) => {
                 println!("Found library {}", lib_name);
+                println!("Vcpkg triplet: {}", lib.vcpkg_triplet);
 
                 if !lib.include_paths.is_empty() {
                     println!("Include paths:");
@@ -117,6 +238,37 @@ fn main() {
     }
 }
 
+/// A `vcpkg::Config` that only looks at the vcpkg tree, with the triplet and
+/// root from the configuration file.
+fn new_config(settings: &CliConfig) -> vcpkg::Config {
+    let mut cfg = vcpkg::Config::new();
+    cfg.cargo_metadata(false);
+    cfg.copy_dlls(false);
+    if let Some(ref triplet) = settings.triplet {
+        cfg.target_triplet(triplet);
+    }
+    if let Some(ref root) = settings.root {
+        cfg.vcpkg_root(root.clone());
+    }
+    cfg
+}
+
+/// Print the outcome of a probe as JSON: the `Library` that was found, or an
+/// object with an `error` message. Exits with an error status if the probe failed.
+fn print_json(result: &Result<vcpkg::Library, vcpkg::Error>) {
+    let json = match *result {
+        Ok(ref lib) => serde_json::to_string_pretty(lib),
+        Err(ref err) => serde_json::to_string_pretty(&serde_json::json!({
+            "error": err.to_string(),
+            "kind": err,
+        })),
+    };
+    println!("{}", json.expect("could not serialize probe result"));
+    if result.is_err() {
+        process::exit(1);
+    }
+}
+
 fn remove_vars() {
     env::remove_var("VCPKGRS_DYNAMIC");
     env::remove_var("CARGO_CFG_TARGET_FEATURE");